@@ -0,0 +1,109 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use zeroize::Zeroizing;
+
+use crate::credential_helper::Helpers;
+use crate::passphrase_cache::PassphraseCache;
+
+/// Handle to report that an operation succeeded with the credentials from a credentials callback.
+///
+/// When the user entered a username and password in a prompt and the server accepted them,
+/// approving them stores them with the `store` action of the configured git credential helpers, like git does.
+/// That way, the user does not have to enter them again for the next operation.
+///
+/// The convenience functions like [`GitAuthenticator::fetch()`][crate::GitAuthenticator::fetch()] approve the credentials automatically.
+/// If you use the credentials callback directly, get it from [`GitAuthenticator::credentials_with_approval()`][crate::GitAuthenticator::credentials_with_approval()],
+/// and call [`Self::approve()`] after the git operation succeeded.
+///
+/// Nothing is stored if the credentials did not come from a prompt,
+/// or if [`GitAuthenticator::store_prompted_credentials()`][crate::GitAuthenticator::store_prompted_credentials()] is disabled.
+///
+/// Similarly, approving remembers the passphrase that the user entered for an SSH key,
+/// if [`GitAuthenticator::cache_passphrases()`][crate::GitAuthenticator::cache_passphrases()] is enabled.
+#[derive(Clone, Default)]
+pub struct CredentialApproval {
+	pending: Rc<RefCell<Option<PendingApproval>>>,
+	passphrase: Rc<RefCell<Option<PendingPassphrase>>>,
+}
+
+/// Prompted credentials that are waiting for approval.
+struct PendingApproval {
+	/// The credential helpers to store the credentials with.
+	helpers: Helpers,
+
+	/// The username entered by the user.
+	username: String,
+
+	/// The password entered by the user.
+	password: String,
+}
+
+/// A passphrase for an SSH key that is waiting for approval.
+struct PendingPassphrase {
+	/// The cache to remember the passphrase in.
+	cache: PassphraseCache,
+
+	/// The private key that the passphrase is for.
+	private_key: PathBuf,
+
+	/// The passphrase entered by the user.
+	passphrase: Zeroizing<String>,
+}
+
+impl std::fmt::Debug for CredentialApproval {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("CredentialApproval")
+			.field("pending", &self.pending.borrow().as_ref().map(|x| &x.username))
+			.field("passphrase", &self.passphrase.borrow().as_ref().map(|x| &x.private_key))
+			.finish()
+	}
+}
+
+impl CredentialApproval {
+	/// Record the credentials that the user entered in a prompt, replacing earlier pending credentials.
+	pub(crate) fn set(&self, helpers: Helpers, username: &str, password: &str) {
+		*self.pending.borrow_mut() = Some(PendingApproval {
+			helpers,
+			username: username.into(),
+			password: password.into(),
+		});
+	}
+
+	/// Record the passphrase that the user entered for an SSH key, replacing an earlier pending passphrase.
+	pub(crate) fn set_passphrase(&self, cache: PassphraseCache, private_key: &Path, passphrase: Zeroizing<String>) {
+		*self.passphrase.borrow_mut() = Some(PendingPassphrase {
+			cache,
+			private_key: private_key.into(),
+			passphrase,
+		});
+	}
+
+	/// Forget the pending credentials, because the server asked for credentials again.
+	pub(crate) fn clear(&self) {
+		self.pending.borrow_mut().take();
+		self.passphrase.borrow_mut().take();
+	}
+
+	/// Check if there are prompted credentials or passphrases waiting for approval.
+	pub fn is_pending(&self) -> bool {
+		self.pending.borrow().is_some() || self.passphrase.borrow().is_some()
+	}
+
+	/// Approve the credentials that were used for the last successful operation.
+	///
+	/// If the user entered them in a prompt, they are stored with the configured credential helpers.
+	/// If the user entered the passphrase of an SSH key, it is remembered for the next operations.
+	/// Only call this after the git operation succeeded: if the server rejected the credentials, they should not be stored.
+	///
+	/// The pending credentials are stored at most once.
+	pub fn approve(&self) {
+		if let Some(pending) = self.pending.borrow_mut().take() {
+			pending.helpers.store(&pending.username, &pending.password);
+		}
+		if let Some(pending) = self.passphrase.borrow_mut().take() {
+			pending.cache.insert(&pending.private_key, pending.passphrase);
+		}
+	}
+}
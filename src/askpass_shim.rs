@@ -0,0 +1,539 @@
+//! Askpass shim to forward prompts of child `git` processes to the [`Prompter`] of the parent process.
+//!
+//! The shim is a small script in a private temporary directory.
+//! When a child process runs the script as askpass program, the script sends the prompt back to the parent process,
+//! and the parent shows the prompt with its prompter.
+//!
+//! On Unix, the script is a POSIX shell script that talks to the parent over named FIFOs in the private directory.
+//! On Windows, the script is a batch file that runs a PowerShell script, which talks to the parent over a named pipe.
+
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::prompter::ClonePrompter;
+use crate::temp_dir::TempDir;
+use crate::token::token_matches;
+use crate::Prompter;
+
+/// Environment variable with the secret token that the shim must send to the parent process.
+const TOKEN_VAR: &str = "AUTH_GIT2_ASKPASS_TOKEN";
+
+/// The maximum length of a request from the shim.
+const MAX_REQUEST_LEN: u64 = 64 * 1024;
+
+/// Askpass shim that forwards prompts of child `git` processes to the prompter of an authenticator.
+///
+/// Some applications use [`git2`] for most operations, but also run `git` commands in child processes.
+/// With the shim, both show the same prompts to the user.
+///
+/// The shim writes a small askpass script to a temporary directory that is only accessible by the current user.
+/// When a child process runs the script, it forwards the prompt to the parent process.
+/// The parent process shows the prompt with its prompter until the shim is dropped,
+/// and the directory with the script is removed when the shim is dropped.
+///
+/// Only processes that know the secret token passed in the environment can get an answer.
+///
+/// The script needs `/bin/sh` and `mkfifo` on Unix, and PowerShell on Windows.
+/// The shim is not supported on other platforms.
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use auth_git2::GitAuthenticator;
+///
+/// let auth = GitAuthenticator::default();
+/// let shim = auth.askpass_shim()?;
+/// let mut command = std::process::Command::new("git");
+/// command.args(["fetch", "origin"]);
+/// shim.configure(&mut command);
+/// let status = command.status()?;
+/// # let _ = status;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AskpassShim {
+	/// The askpass program.
+	program: PathBuf,
+
+	/// The secret token that clients must send.
+	token: String,
+
+	/// The name of the named pipe that the server listens on.
+	#[cfg(windows)]
+	pipe_name: String,
+
+	/// Flag to tell the server thread to stop.
+	stop: Arc<AtomicBool>,
+
+	/// The server thread.
+	thread: Option<std::thread::JoinHandle<()>>,
+
+	/// Private directory with the script, removed after the server thread stopped.
+	dir: TempDir,
+}
+
+impl std::fmt::Debug for AskpassShim {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("AskpassShim")
+			.field("dir", &self.dir.path())
+			.field("program", &self.program)
+			.finish_non_exhaustive()
+	}
+}
+
+impl AskpassShim {
+	/// Start a new shim that forwards prompts to the given prompter.
+	#[cfg(unix)]
+	pub(crate) fn start(prompter: Box<dyn ClonePrompter>) -> std::io::Result<Self> {
+		let dir = TempDir::create("auth-git2-askpass")?;
+		let token = crate::token::random_token()?;
+		let requests_path = dir.path().join("requests");
+		make_fifo(&requests_path)?;
+		// Open the FIFO for reading and writing, so opening it never blocks and it never reaches the end of the file.
+		let requests = std::fs::OpenOptions::new().read(true).write(true).open(&requests_path)?;
+		let program = dir.path().join("askpass");
+		write_script(&program, &unix_script(dir.path()))?;
+
+		let stop = Arc::new(AtomicBool::new(false));
+		let thread = std::thread::Builder::new()
+			.name("auth-git2-askpass".into())
+			.spawn({
+				let dir = dir.path().to_owned();
+				let token = token.clone();
+				let stop = stop.clone();
+				move || serve(requests, &dir, &token, &stop, prompter)
+			})?;
+		Ok(Self {
+			program,
+			token,
+			stop,
+			thread: Some(thread),
+			dir,
+		})
+	}
+
+	/// Start a new shim that forwards prompts to the given prompter.
+	#[cfg(windows)]
+	pub(crate) fn start(prompter: Box<dyn ClonePrompter>) -> std::io::Result<Self> {
+		let dir = TempDir::create("auth-git2-askpass")?;
+		let token = crate::token::random_token()?;
+		let pipe_name = format!("auth-git2-askpass-{}-{}", std::process::id(), &crate::token::random_token()?[..16]);
+		let listener = crate::named_pipe::PipeListener::bind(&pipe_name)?;
+		write_script(&dir.path().join("askpass.ps1"), &powershell_script(&pipe_name))?;
+		let program = dir.path().join("askpass.cmd");
+		write_script(&program, WINDOWS_BATCH_SCRIPT)?;
+
+		let stop = Arc::new(AtomicBool::new(false));
+		let thread = std::thread::Builder::new()
+			.name("auth-git2-askpass".into())
+			.spawn({
+				let token = token.clone();
+				let stop = stop.clone();
+				move || serve(listener, &token, &stop, prompter)
+			})?;
+		Ok(Self {
+			program,
+			token,
+			pipe_name,
+			stop,
+			thread: Some(thread),
+			dir,
+		})
+	}
+
+	/// Start a new shim that forwards prompts to the given prompter.
+	#[cfg(not(any(unix, windows)))]
+	pub(crate) fn start(_prompter: Box<dyn ClonePrompter>) -> std::io::Result<Self> {
+		Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "the askpass shim is not supported on this platform"))
+	}
+
+	/// Get the path of the askpass script.
+	pub fn program(&self) -> &Path {
+		&self.program
+	}
+
+	/// Get the environment variables to set for child processes.
+	///
+	/// This sets `GIT_ASKPASS` and `SSH_ASKPASS` to the shim,
+	/// and `SSH_ASKPASS_REQUIRE` to `force` so that SSH uses the shim even if there is a terminal.
+	pub fn envs(&self) -> Vec<(OsString, OsString)> {
+		vec![
+			("GIT_ASKPASS".into(), self.program.clone().into()),
+			("SSH_ASKPASS".into(), self.program.clone().into()),
+			("SSH_ASKPASS_REQUIRE".into(), "force".into()),
+			(TOKEN_VAR.into(), self.token.clone().into()),
+		]
+	}
+
+	/// Configure a command to use the shim for prompts.
+	///
+	/// See [`Self::envs()`] for the environment variables that are set.
+	pub fn configure(&self, command: &mut std::process::Command) {
+		command.envs(self.envs());
+	}
+
+	/// Wake up the server thread, so it sees the stop flag.
+	#[cfg(unix)]
+	fn wake_server(&self) {
+		let requests = std::fs::OpenOptions::new().write(true).open(self.dir.path().join("requests"));
+		if let Ok(mut requests) = requests {
+			let _ = requests.write_all(b"\n");
+		}
+	}
+
+	/// Wake up the server thread, so it sees the stop flag.
+	#[cfg(windows)]
+	fn wake_server(&self) {
+		let _ = crate::named_pipe::connect(&self.pipe_name);
+	}
+
+	/// Wake up the server thread, so it sees the stop flag.
+	#[cfg(not(any(unix, windows)))]
+	fn wake_server(&self) {
+	}
+}
+
+impl Drop for AskpassShim {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::SeqCst);
+		self.wake_server();
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Write a script that is only accessible by the current user.
+fn write_script(path: &Path, contents: &str) -> std::io::Result<()> {
+	let mut options = std::fs::OpenOptions::new();
+	options.write(true).create_new(true);
+	#[cfg(unix)]
+	std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o700);
+	let mut file = options.open(path)?;
+	file.write_all(contents.as_bytes())?;
+	file.sync_all()
+}
+
+/// Create a FIFO that is only accessible by the current user.
+#[cfg(unix)]
+fn make_fifo(path: &Path) -> std::io::Result<()> {
+	let output = std::process::Command::new("mkfifo")
+		.args(["-m", "600"])
+		.arg(path)
+		.stdin(std::process::Stdio::null())
+		.output()?;
+	if !output.status.success() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		return Err(std::io::Error::other(format!("mkfifo failed: {}", stderr.trim())));
+	}
+	Ok(())
+}
+
+/// Quote a value for a POSIX shell.
+#[cfg(unix)]
+fn shell_quote(value: &str) -> String {
+	format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Get the askpass script for Unix.
+///
+/// The script creates a response FIFO, sends one request line with the token, the name of the response FIFO and the prompt,
+/// and reads the answer from the response FIFO.
+/// It keeps the response FIFO open for reading and writing, so the parent can always write the answer without blocking.
+#[cfg(unix)]
+fn unix_script(dir: &Path) -> String {
+	format!(
+		r#"#!/bin/sh
+# Askpass shim of auth-git2: forwards the prompt to the parent process.
+dir={dir}
+response="response-$$"
+mkfifo -m 600 "$dir/$response" || exit 2
+trap 'rm -f "$dir/$response"' EXIT
+exec 3<>"$dir/$response"
+prompt=$(printf '%s' "$1" | tr '\r\n' '  ')
+printf '%s %s %s\n' "${token_var}" "$response" "$prompt" >"$dir/requests" || exit 2
+IFS= read -r status <&3 || exit 2
+case "$status" in
+	ok) ;;
+	cancel) exit 1 ;;
+	*) echo "Failed to forward prompt to parent process: $status" >&2; exit 2 ;;
+esac
+IFS= read -r answer <&3 || exit 2
+printf '%s\n' "$answer"
+"#,
+		dir = shell_quote(&dir.to_string_lossy()),
+		token_var = TOKEN_VAR,
+	)
+}
+
+/// The askpass program for Windows, which runs the PowerShell script next to it.
+#[cfg(windows)]
+const WINDOWS_BATCH_SCRIPT: &str = "@echo off\r\npowershell.exe -NoLogo -NoProfile -NonInteractive -ExecutionPolicy Bypass -File \"%~dp0askpass.ps1\" %1\r\nexit /b %ERRORLEVEL%\r\n";
+
+/// Get the PowerShell script that forwards the prompt to the parent process over a named pipe.
+#[cfg(windows)]
+fn powershell_script(pipe_name: &str) -> String {
+	format!(
+		r#"# Askpass shim of auth-git2: forwards the prompt to the parent process.
+param([string]$Prompt = '')
+$ErrorActionPreference = 'Stop'
+try {{
+	$pipe = New-Object System.IO.Pipes.NamedPipeClientStream('.', '{pipe_name}', [System.IO.Pipes.PipeDirection]::InOut)
+	$pipe.Connect(10000)
+	$encoding = New-Object System.Text.UTF8Encoding($false)
+	$reader = New-Object System.IO.StreamReader($pipe, $encoding)
+	$writer = New-Object System.IO.StreamWriter($pipe, $encoding)
+	$writer.NewLine = "`n"
+	$writer.AutoFlush = $true
+	$writer.WriteLine($env:{token_var})
+	if ($reader.ReadLine() -ne 'ready') {{ throw 'the parent process rejected the token' }}
+	$writer.WriteLine(($Prompt -replace "[`r`n]", ' '))
+	$status = $reader.ReadLine()
+	if ($status -eq 'cancel') {{ exit 1 }}
+	if ($status -ne 'ok') {{ throw "unexpected answer: $status" }}
+	$answer = $encoding.GetBytes($reader.ReadLine() + "`n")
+	$stdout = [Console]::OpenStandardOutput()
+	$stdout.Write($answer, 0, $answer.Length)
+	$stdout.Flush()
+	exit 0
+}} catch {{
+	[Console]::Error.WriteLine("Failed to forward prompt to parent process: $_")
+	exit 2
+}}
+"#,
+		token_var = TOKEN_VAR,
+	)
+}
+
+/// Serve prompt requests from the request FIFO until the stop flag is set.
+#[cfg(unix)]
+fn serve(requests: std::fs::File, dir: &Path, token: &str, stop: &AtomicBool, mut prompter: Box<dyn ClonePrompter>) {
+	use std::io::Read;
+	let mut pending_password = None;
+	let mut requests = BufReader::new(requests);
+	loop {
+		let mut request = String::new();
+		let result = requests.by_ref().take(MAX_REQUEST_LEN).read_line(&mut request);
+		if stop.load(Ordering::SeqCst) {
+			break;
+		}
+		match result {
+			Ok(_) => (),
+			// The invalid data is consumed, so the next request can still be read.
+			Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+				debug!("askpass shim: received invalid request: {e}");
+				continue;
+			},
+			Err(e) => {
+				warn!("askpass shim: failed to read requests: {e}");
+				break;
+			},
+		}
+		let request = request.trim_end_matches(['\r', '\n']);
+		if request.is_empty() {
+			continue;
+		}
+		if let Err(e) = handle(dir, request, token, prompter.as_prompter_mut(), &mut pending_password) {
+			debug!("askpass shim: failed to handle request: {e}");
+		}
+	}
+}
+
+/// Handle a single prompt request from the request FIFO.
+#[cfg(unix)]
+fn handle(dir: &Path, request: &str, token: &str, prompter: &mut dyn Prompter, pending_password: &mut Option<(String, String)>) -> std::io::Result<()> {
+	let mut fields = request.splitn(3, ' ');
+	let client_token = fields.next().unwrap_or_default();
+	let response = fields.next().unwrap_or_default();
+	let prompt = fields.next().unwrap_or_default();
+
+	// Only answer on the response FIFOs that the script creates in the private directory.
+	let valid_response = response.strip_prefix("response-")
+		.is_some_and(|pid| !pid.is_empty() && pid.bytes().all(|x| x.is_ascii_digit()));
+	if !valid_response {
+		return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("invalid response FIFO: {response:?}")));
+	}
+	// Open the FIFO for reading and writing, so the answer can be written even if the script has gone away.
+	let mut response = std::fs::OpenOptions::new().read(true).write(true).open(dir.join(response))?;
+
+	if !token_matches(token, client_token) {
+		warn!("askpass shim: rejected request with invalid token");
+		writeln!(response, "denied")?;
+		return response.flush();
+	}
+	write_answer(&mut response, answer_prompt(prompter, prompt, pending_password))
+}
+
+/// Serve prompt requests on the named pipe until the stop flag is set.
+#[cfg(windows)]
+fn serve(mut listener: crate::named_pipe::PipeListener, token: &str, stop: &AtomicBool, mut prompter: Box<dyn ClonePrompter>) {
+	let mut pending_password = None;
+	loop {
+		let stream = listener.accept();
+		if stop.load(Ordering::SeqCst) {
+			break;
+		}
+		let stream = match stream {
+			Ok(x) => x,
+			Err(e) => {
+				warn!("askpass shim: failed to accept connection: {e}");
+				break;
+			},
+		};
+		if let Err(e) = handle(&stream, token, prompter.as_prompter_mut(), &mut pending_password) {
+			debug!("askpass shim: failed to handle request: {e}");
+		}
+	}
+}
+
+/// Handle a single prompt request from the named pipe.
+#[cfg(windows)]
+fn handle(stream: &std::fs::File, token: &str, prompter: &mut dyn Prompter, pending_password: &mut Option<(String, String)>) -> std::io::Result<()> {
+	use std::io::Read;
+	let mut reader = BufReader::new(stream.take(MAX_REQUEST_LEN));
+	let mut client_token = String::new();
+	reader.read_line(&mut client_token)?;
+	let mut stream = stream;
+	// Authenticate the client before reading anything else from it.
+	// The client waits for the answer before it sends the prompt, so no unread data is left when a client is rejected.
+	if !token_matches(token, client_token.trim_end()) {
+		warn!("askpass shim: rejected connection with invalid token");
+		writeln!(stream, "denied")?;
+		return stream.flush();
+	}
+	writeln!(stream, "ready")?;
+	stream.flush()?;
+	let mut prompt = String::new();
+	reader.read_line(&mut prompt)?;
+	write_answer(&mut stream, answer_prompt(prompter, prompt.trim_end(), pending_password))
+}
+
+/// Write the answer to a prompt for the shim.
+fn write_answer(stream: &mut impl Write, answer: Option<String>) -> std::io::Result<()> {
+	match answer {
+		Some(answer) if !answer.contains(['\r', '\n']) => write!(stream, "ok\n{answer}\n")?,
+		_ => writeln!(stream, "cancel")?,
+	}
+	stream.flush()
+}
+
+/// A prompt from `git` or `ssh`.
+#[derive(Debug, Eq, PartialEq)]
+enum AskpassPrompt<'a> {
+	/// A prompt for the username for a URL.
+	Username { url: &'a str },
+
+	/// A prompt for the password for a URL.
+	Password { url: &'a str },
+
+	/// A prompt for the passphrase of an SSH key.
+	Passphrase { private_key: &'a str },
+}
+
+/// Parse a prompt from `git` or `ssh`.
+fn parse_prompt(prompt: &str) -> Option<AskpassPrompt<'_>> {
+	let prompt = prompt.trim_end().trim_end_matches(':');
+	if let Some(url) = prompt.strip_prefix("Username for ") {
+		Some(AskpassPrompt::Username { url: unquote(url) })
+	} else if let Some(url) = prompt.strip_prefix("Password for ") {
+		Some(AskpassPrompt::Password { url: unquote(url) })
+	} else if let Some(private_key) = prompt.strip_prefix("Enter passphrase for key ") {
+		Some(AskpassPrompt::Passphrase { private_key: unquote(private_key) })
+	} else {
+		prompt.strip_prefix("Enter passphrase for ")
+			.map(|private_key| AskpassPrompt::Passphrase { private_key: unquote(private_key) })
+	}
+}
+
+/// Remove single quotes around a value.
+fn unquote(value: &str) -> &str {
+	value.strip_prefix('\'')
+		.and_then(|x| x.strip_suffix('\''))
+		.unwrap_or(value)
+}
+
+/// Answer a prompt using the prompter.
+///
+/// `git` asks for the username and password separately, but the prompter asks for both at once.
+/// The password from a username prompt is kept for the next password prompt for the same URL.
+fn answer_prompt(prompter: &mut dyn Prompter, prompt: &str, pending_password: &mut Option<(String, String)>) -> Option<String> {
+	let git_config = crate::env_config::open_default()
+		.map_err(|e| warn!("askpass shim: failed to open git config: {e}"))
+		.ok()?;
+	match parse_prompt(prompt) {
+		Some(AskpassPrompt::Username { url }) => {
+			let (username, password) = prompter.prompt_username_password(url, &git_config)?;
+			let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+			*pending_password = Some((format!("{scheme}://{username}@{rest}"), password));
+			Some(username)
+		},
+		Some(AskpassPrompt::Password { url }) => {
+			if let Some((pending_url, password)) = pending_password.take() {
+				if pending_url == url {
+					return Some(password);
+				}
+			}
+			let (_scheme, rest) = url.split_once("://").unwrap_or(("", url));
+			match rest.split_once('@') {
+				Some((username, _)) => prompter.prompt_password(username, url, &git_config),
+				None => prompter.prompt_username_password(url, &git_config).map(|(_, password)| password),
+			}
+		},
+		Some(AskpassPrompt::Passphrase { private_key }) => {
+			prompter.prompt_ssh_key_passphrase(Path::new(private_key), &git_config)
+		},
+		None => {
+			debug!("askpass shim: unsupported prompt: {prompt:?}");
+			None
+		},
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+	use crate::test_prompter::FixedPrompter;
+
+	#[test]
+	fn test_parse_prompt() {
+		assert!(let Some(AskpassPrompt::Username { url: "https://example.com" }) = parse_prompt("Username for 'https://example.com': "));
+		assert!(let Some(AskpassPrompt::Password { url: "https://user@example.com" }) = parse_prompt("Password for 'https://user@example.com': "));
+		assert!(let Some(AskpassPrompt::Passphrase { private_key: "/home/user/.ssh/id_ed25519" }) = parse_prompt("Enter passphrase for key '/home/user/.ssh/id_ed25519': "));
+		assert!(let None = parse_prompt("Are you sure you want to continue connecting (yes/no/[fingerprint])? "));
+	}
+
+	/// Run the askpass script like `git` does, and get the exit code and the output.
+	#[cfg(unix)]
+	fn run(shim: &AskpassShim, token: &str, prompt: &str) -> (Option<i32>, String) {
+		let_assert!(Ok(output) = std::process::Command::new(shim.program())
+			.arg(prompt)
+			.env(TOKEN_VAR, token)
+			.stdin(std::process::Stdio::null())
+			.output());
+		(output.status.code(), String::from_utf8_lossy(&output.stdout).into_owned())
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn test_shim() {
+		let_assert!(Ok(shim) = AskpassShim::start(crate::prompter::wrap_prompter(FixedPrompter)));
+		let token = shim.token.clone();
+		let dir = shim.dir.path().to_owned();
+
+		assert!(run(&shim, &token, "Username for 'https://example.com': ") == (Some(0), "alice\n".into()));
+		assert!(run(&shim, &token, "Password for 'https://alice@example.com': ") == (Some(0), "hunter2\n".into()));
+		assert!(run(&shim, &token, "Password for 'https://other@example.com': ") == (Some(0), "password-of-other\n".into()));
+		assert!(run(&shim, &token, "Enter passphrase for key '/id_ed25519': ") == (Some(1), String::new()));
+		assert!(run(&shim, "wrong", "Username for 'https://example.com': ") == (Some(2), String::new()));
+
+		drop(shim);
+		assert!(!dir.exists());
+	}
+}
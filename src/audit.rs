@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Receiver for audit events.
+///
+/// Audit events record when the user was prompted for credentials or passphrases, and how the prompt ended.
+/// They never contain passwords, passphrases or full URLs (which could contain tokens).
+///
+/// Audit events are independent of the normal log messages of the crate,
+/// and they are emitted even if the `log` feature is disabled.
+///
+/// You can set an audit sink with [`GitAuthenticator::set_audit_sink()`][crate::GitAuthenticator::set_audit_sink()].
+pub trait AuditSink: Send + Sync {
+	/// Record an audit event.
+	fn record(&self, event: &AuditEvent);
+}
+
+impl<F> AuditSink for F
+where
+	F: Fn(&AuditEvent) + Send + Sync,
+{
+	fn record(&self, event: &AuditEvent) {
+		self(event)
+	}
+}
+
+/// An audit event.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct AuditEvent {
+	/// The time of the event, according to the [`Clock`][crate::Clock] of the authenticator.
+	pub time: SystemTime,
+
+	/// What happened.
+	pub action: AuditAction,
+
+	/// The prompt that the event is about.
+	pub prompt: AuditPrompt,
+
+	/// The label of the configured credentials that the prompt is for, if any.
+	///
+	/// This is set for passphrase prompts of SSH keys added to the authenticator.
+	/// See [`GitAuthenticator::with_label()`][crate::GitAuthenticator::with_label()].
+	pub label: Option<String>,
+}
+
+/// The action recorded by an [`AuditEvent`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum AuditAction {
+	/// A prompt is about to be shown to the user.
+	PromptStarted,
+
+	/// The user provided the requested information.
+	PromptCompleted,
+
+	/// The user cancelled the prompt, or the prompt failed.
+	PromptCancelled,
+}
+
+/// The prompt that an [`AuditEvent`] is about.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum AuditPrompt {
+	/// A prompt for a username and password.
+	UsernamePassword {
+		/// The host that the credentials are for.
+		host: String,
+	},
+
+	/// A prompt for a password for a known username.
+	Password {
+		/// The host that the credentials are for.
+		host: String,
+
+		/// The username that the password is for.
+		username: String,
+	},
+
+	/// A prompt for the passphrase of an encrypted SSH key.
+	SshKeyPassphrase {
+		/// The path of the private key file.
+		private_key: PathBuf,
+	},
+}
@@ -21,7 +21,7 @@ pub fn base64_decode(input: &[u8]) -> Result<Vec<u8>, Error> {
 		None => return Ok(Vec::new()),
 	};
 
-	let mut output = Vec::with_capacity((input.len() + 3) / 4 * 3);
+	let mut output = Vec::with_capacity(input.len().div_ceil(4) * 3);
 	let mut decoder = Base64Decoder::new();
 
 	for &byte in input {
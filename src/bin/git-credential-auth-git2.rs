@@ -0,0 +1,26 @@
+//! Git credential helper that answers requests with the default configuration of `auth_git2::GitAuthenticator`.
+//!
+//! Install it in your `PATH` and enable it with `git config --global credential.helper auth-git2`.
+
+fn main() {
+	if let Err(e) = do_main() {
+		eprintln!("git-credential-auth-git2: {e}");
+		std::process::exit(1);
+	}
+}
+
+fn do_main() -> Result<(), String> {
+	let action = std::env::args()
+		.nth(1)
+		.ok_or("usage: git-credential-auth-git2 <get|store|erase>")?;
+	let mut git_config = git2::Config::open_default()
+		.map_err(|e| format!("failed to open git configuration: {}", e.message()))?;
+	let git_config = auth_git2::apply_env_config(&mut git_config)
+		.map_err(|e| format!("failed to apply git configuration from the environment: {}", e.message()))?;
+
+	// This helper is configured in `credential.helper` itself, so do not run the helpers again.
+	let auth = auth_git2::GitAuthenticator::default()
+		.try_cred_helper(false);
+	auth.serve_credential_helper(&git_config, &action, std::io::stdin().lock(), std::io::stdout().lock())
+		.map_err(|e| format!("failed to answer {action:?} request: {e}"))
+}
@@ -0,0 +1,299 @@
+//! Credentials from the Bitwarden CLI (`bw`).
+//!
+//! The login item with the host of the URL as name is used.
+//! If the vault is locked, the user is asked for the master password with the [`Prompter`] to unlock it.
+
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+
+use zeroize::Zeroizing;
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::{PlaintextCredentials, PlannedAttempt, Prompter};
+
+/// Environment variable used to pass the master password to `bw unlock`.
+const PASSWORD_VAR: &str = "AUTH_GIT2_BW_PASSWORD";
+
+/// The session key of an unlocked Bitwarden vault.
+///
+/// The session is shared between all clones of an authenticator,
+/// so the user is only asked for the master password once.
+#[derive(Clone, Default)]
+pub(crate) struct SessionCache {
+	/// The session key from `bw unlock`, if the vault was unlocked.
+	session: Arc<Mutex<Option<Zeroizing<String>>>>,
+}
+
+impl SessionCache {
+	/// Get the session key, if the vault was unlocked.
+	fn get(&self) -> Option<Zeroizing<String>> {
+		self.lock().clone()
+	}
+
+	/// Remember the session key of the unlocked vault.
+	fn set(&self, session: Zeroizing<String>) {
+		*self.lock() = Some(session);
+	}
+
+	/// Forget the session key.
+	pub(crate) fn purge(&self) {
+		*self.lock() = None;
+	}
+
+	/// Lock the session, ignoring poisoning.
+	///
+	/// The session is always in a valid state, even if a thread panicked while holding the lock.
+	fn lock(&self) -> std::sync::MutexGuard<'_, Option<Zeroizing<String>>> {
+		self.session.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl std::fmt::Debug for SessionCache {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("SessionCache")
+			.field("unlocked", &self.lock().is_some())
+			.finish()
+	}
+}
+
+/// Get credentials for a URL from the Bitwarden CLI.
+///
+/// The session key in the cache is used if there is one.
+/// If the vault is locked and a prompter is given, the user is asked for the master password,
+/// and the new session key is stored in the cache.
+/// Returns `None` if there is no login item for the host, if it has a different username,
+/// or if the vault could not be unlocked.
+///
+/// Returns an error if more than one item is named after the host.
+pub(crate) fn get_credentials(
+	url: &str,
+	username: Option<&str>,
+	prompter: Option<&mut dyn Prompter>,
+	session: &SessionCache,
+	git_config: &git2::Config,
+) -> Result<Option<PlaintextCredentials>, String> {
+	let Some(host) = crate::domain_from_url(url) else {
+		return Ok(None);
+	};
+	let Some(output) = get_item(host, session.get().as_deref().map(String::as_str)) else {
+		return Ok(None);
+	};
+	let output = if output.status.success() {
+		output
+	} else if is_locked(&output) {
+		// The cached session key is no longer valid if the vault was locked in the meantime.
+		session.purge();
+		let prompter = match prompter {
+			Some(x) => x,
+			None => {
+				debug!("Bitwarden vault is locked, and there is no prompter to ask for the master password");
+				return Ok(None);
+			},
+		};
+		let Some(new_session) = unlock(prompter, git_config) else {
+			return Ok(None);
+		};
+		session.set(new_session.clone());
+		let Some(output) = get_item(host, Some(&new_session)) else {
+			return Ok(None);
+		};
+		output
+	} else {
+		output
+	};
+	if !output.status.success() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		if is_ambiguous(&stderr) {
+			return Err(format!("more than one Bitwarden item is named {host:?}"));
+		}
+		debug!("bw get item {host:?} failed: {}", stderr.trim());
+		return Ok(None);
+	}
+
+	let Ok(body) = String::from_utf8(output.stdout) else {
+		warn!("Invalid UTF-8 in the output of bw get item");
+		return Ok(None);
+	};
+	let credentials = match parse_item(&body) {
+		Ok(x) => x,
+		Err(e) => {
+			warn!("Invalid Bitwarden item {host:?}: {e}");
+			return Ok(None);
+		},
+	};
+	match username {
+		Some(username) if username != credentials.username => {
+			debug!("Bitwarden item {host:?} is for username {:?}, not {username:?}", credentials.username);
+			Ok(None)
+		},
+		_ => Ok(Some(credentials)),
+	}
+}
+
+/// Run `bw get item` for an item name.
+fn get_item(name: &str, session: Option<&str>) -> Option<Output> {
+	let mut command = Command::new(bw_program());
+	command.args(["get", "item", name, "--nointeraction"]);
+	if let Some(session) = session {
+		command.args(["--session", session]);
+	}
+	command
+		.stdin(Stdio::null())
+		.output()
+		.map_err(|e| debug!("Failed to run bw: {e}"))
+		.ok()
+}
+
+/// Check if `bw` failed because the vault is locked.
+fn is_locked(output: &Output) -> bool {
+	String::from_utf8_lossy(&output.stderr).contains("Vault is locked")
+}
+
+/// Check if `bw get item` failed because more than one item has the name.
+fn is_ambiguous(stderr: &str) -> bool {
+	stderr.contains("More than one result was found")
+}
+
+/// Ask the user for the master password and unlock the vault.
+///
+/// Returns the session key.
+fn unlock(prompter: &mut dyn Prompter, git_config: &git2::Config) -> Option<Zeroizing<String>> {
+	let password = zeroize::Zeroizing::new(prompter.prompt_password_manager_unlock("Bitwarden", git_config)?);
+	let output = Command::new(bw_program())
+		.args(["unlock", "--raw", "--nointeraction", "--passwordenv", PASSWORD_VAR])
+		.env(PASSWORD_VAR, password.as_str())
+		.stdin(Stdio::null())
+		.output()
+		.map_err(|e| debug!("Failed to run bw: {e}"))
+		.ok()?;
+	if !output.status.success() {
+		warn!("Failed to unlock the Bitwarden vault: {}", String::from_utf8_lossy(&output.stderr).trim());
+		return None;
+	}
+	let session = Zeroizing::new(String::from_utf8(output.stdout).ok()?);
+	let session = session.trim();
+	if session.is_empty() {
+		return None;
+	}
+	Some(Zeroizing::new(session.into()))
+}
+
+/// Parse a login item from the output of `bw get item`.
+pub(crate) fn parse_item(body: &str) -> Result<PlaintextCredentials, String> {
+	let value: serde_json::Value = serde_json::from_str(body).map_err(|e| format!("invalid JSON: {e}"))?;
+	let login = value.get("login").ok_or("not a login item")?;
+	let field = |name: &str| login.get(name).and_then(|x| x.as_str()).filter(|x| !x.is_empty()).map(String::from);
+	Ok(PlaintextCredentials {
+		username: field("username").ok_or("missing username")?,
+		password: field("password").ok_or("missing password")?,
+	})
+}
+
+/// Get the path of the `bw` program.
+///
+/// On Windows, npm installs `bw` as a `.cmd` script, which can not be run without the extension,
+/// and the standalone version is `bw.exe`.
+/// So the directories in `PATH` are searched with each extension in `PATHEXT`, like the Windows shell does.
+fn bw_program() -> PathBuf {
+	if cfg!(windows) {
+		let path = std::env::var_os("PATH").unwrap_or_default();
+		let extensions = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+		if let Some(program) = find_program("bw", &path, &extensions) {
+			return program;
+		}
+	}
+	PathBuf::from("bw")
+}
+
+/// Find a program in the directories of a `PATH` value, trying each of the semicolon separated extensions.
+fn find_program(name: &str, path: &std::ffi::OsStr, extensions: &str) -> Option<PathBuf> {
+	std::env::split_paths(path).find_map(|dir| {
+		extensions.split(';')
+			.filter(|extension| !extension.is_empty())
+			.map(|extension| dir.join(format!("{name}{extension}")))
+			.find(|candidate| candidate.is_file())
+	})
+}
+
+/// The Bitwarden CLI as source of credentials.
+pub(crate) struct Bitwarden;
+
+impl CredentialSource for Bitwarden {
+	fn mechanism(&self) -> &'static str {
+		"bitwarden"
+	}
+
+	fn label(&self) -> String {
+		"bitwarden".into()
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::Bitwarden
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		let session = &request.authenticator.sources.bitwarden_session;
+		get_credentials(request.url, request.username, request.prompter, session, request.git_config)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_parse_item() {
+		let_assert!(Ok(credentials) = parse_item(r#"{
+			"object": "item",
+			"name": "github.com",
+			"login": {
+				"uris": [{"match": null, "uri": "https://github.com"}],
+				"username": "octocat",
+				"password": "hunter2",
+				"totp": null
+			}
+		}"#));
+		assert!(credentials.username == "octocat");
+		assert!(credentials.password == "hunter2");
+
+		assert!(let Err(_) = parse_item(r#"{"object": "item", "name": "note", "secureNote": {"type": 0}}"#));
+		assert!(let Err(_) = parse_item(r#"{"login": {"username": "octocat", "password": null}}"#));
+	}
+
+	#[test]
+	fn test_is_ambiguous() {
+		assert!(is_ambiguous("More than one result was found. Try getting a specific object by `id` instead."));
+		assert!(!is_ambiguous("Not found."));
+	}
+
+	#[test]
+	fn test_find_program() {
+		let_assert!(Ok(npm) = crate::temp_dir::TempDir::create("auth-git2-test-bw-npm"));
+		let_assert!(Ok(standalone) = crate::temp_dir::TempDir::create("auth-git2-test-bw-standalone"));
+		assert!(let Ok(()) = std::fs::write(npm.path().join("bw.cmd"), ""));
+		assert!(let Ok(()) = std::fs::write(standalone.path().join("bw.exe"), ""));
+		let_assert!(Ok(path) = std::env::join_paths([npm.path(), standalone.path()]));
+
+		assert!(find_program("bw", &path, ".exe;.cmd") == Some(npm.path().join("bw.cmd")));
+		let_assert!(Ok(path) = std::env::join_paths([standalone.path(), npm.path()]));
+		assert!(find_program("bw", &path, ".exe;.cmd") == Some(standalone.path().join("bw.exe")));
+		assert!(let None = find_program("bw", &path, ".com;"));
+	}
+
+	#[test]
+	fn test_session_cache() {
+		let cache = SessionCache::default();
+		let clone = cache.clone();
+		assert!(let None = cache.get());
+		cache.set(Zeroizing::new("session-key".into()));
+		assert!(clone.get().as_deref().map(String::as_str) == Some("session-key"));
+		assert!(!format!("{cache:?}").contains("session-key"));
+		clone.purge();
+		assert!(let None = cache.get());
+	}
+}
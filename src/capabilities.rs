@@ -0,0 +1,135 @@
+//! Detection of the features of the linked libgit2 that are relevant for authentication.
+
+use crate::ErrorKind;
+
+/// Features of the linked libgit2 that are relevant for authentication.
+///
+/// Use [`capabilities()`] to get the capabilities of the linked libgit2.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Capabilities {
+	/// The version of libgit2 as `(major, minor, patch)`.
+	pub libgit2_version: (u32, u32, u32),
+
+	/// Was libgit2 built with support for SSH URLs?
+	pub ssh: bool,
+
+	/// Was libgit2 built with support for HTTPS URLs?
+	pub https: bool,
+
+	/// Does libgit2 support SSH keys from memory?
+	///
+	/// If not, in-memory keys can only be used with [`GitAuthenticator::ssh_key_temp_file_fallback()`][crate::GitAuthenticator::ssh_key_temp_file_fallback()].
+	pub ssh_memory_keys: bool,
+
+	/// The TLS backend used for HTTPS, or `None` if libgit2 was built without HTTPS support.
+	///
+	/// libgit2 does not report the backend it was built with.
+	/// The backend is only known if the vendored libgit2 is used, because it always uses the default backend of the platform.
+	/// For a system libgit2, this is [`TlsBackend::Unknown`].
+	pub tls_backend: Option<TlsBackend>,
+}
+
+/// A TLS backend used by libgit2 for HTTPS.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum TlsBackend {
+	/// OpenSSL, used on Linux and other Unix platforms.
+	OpenSsl,
+
+	/// The Security framework of macOS and iOS.
+	SecureTransport,
+
+	/// WinHTTP, used on Windows.
+	WinHttp,
+
+	/// The backend could not be detected, because libgit2 is not the vendored version.
+	Unknown,
+}
+
+impl std::fmt::Display for TlsBackend {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::OpenSsl => f.write_str("OpenSSL"),
+			Self::SecureTransport => f.write_str("SecureTransport"),
+			Self::WinHttp => f.write_str("WinHTTP"),
+			Self::Unknown => f.write_str("unknown"),
+		}
+	}
+}
+
+/// Get the features of the linked libgit2 that are relevant for authentication.
+pub fn capabilities() -> Capabilities {
+	let version = git2::Version::get();
+	let tls_backend = if !version.https() {
+		None
+	} else if !version.vendored() {
+		Some(TlsBackend::Unknown)
+	} else if cfg!(windows) {
+		Some(TlsBackend::WinHttp)
+	} else if cfg!(any(target_os = "macos", target_os = "ios")) {
+		Some(TlsBackend::SecureTransport)
+	} else {
+		Some(TlsBackend::OpenSsl)
+	};
+	Capabilities {
+		libgit2_version: version.libgit2_version(),
+		ssh: version.ssh(),
+		https: version.https(),
+		ssh_memory_keys: crate::temp_key::memory_keys_supported(),
+		tls_backend,
+	}
+}
+
+/// Check if a URL uses SSH.
+///
+/// This is true for `ssh://` URLs and for SCP-like URLs (`[user@]host:path`).
+pub(crate) fn is_ssh_url(url: &str) -> bool {
+	match url.split_once("://") {
+		Some((scheme, _)) => matches!(scheme, "ssh" | "git+ssh" | "ssh+git"),
+		None => crate::domain_from_url(url).is_some(),
+	}
+}
+
+/// Check if the linked libgit2 supports the transport of a URL.
+///
+/// Returns an error with [`ErrorKind::Unsupported`] if it does not.
+pub(crate) fn check_url(url: &str) -> Result<(), crate::Error> {
+	let capabilities = capabilities();
+	if is_ssh_url(url) && !capabilities.ssh {
+		return Err(unsupported(&format!("can not use {url}: libgit2 was built without SSH support")));
+	}
+	if url.starts_with("https://") && !capabilities.https {
+		return Err(unsupported(&format!("can not use {url}: libgit2 was built without HTTPS support")));
+	}
+	Ok(())
+}
+
+/// Create an error with [`ErrorKind::Unsupported`].
+fn unsupported(message: &str) -> crate::Error {
+	crate::Error::new(ErrorKind::Unsupported, git2::Error::from_str(message))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_is_ssh_url() {
+		assert!(is_ssh_url("git@example.com:repo.git"));
+		assert!(is_ssh_url("ssh://git@example.com/repo.git"));
+		assert!(is_ssh_url("git+ssh://example.com/repo.git"));
+		assert!(!is_ssh_url("https://example.com/repo.git"));
+		assert!(!is_ssh_url("some/relative/path"));
+	}
+
+	#[test]
+	fn test_capabilities() {
+		let capabilities = capabilities();
+		assert!(capabilities.https == capabilities.tls_backend.is_some());
+		if !git2::Version::get().vendored() {
+			assert!(let None | Some(TlsBackend::Unknown) = capabilities.tls_backend);
+		}
+	}
+}
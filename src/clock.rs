@@ -0,0 +1,108 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Source of the current time for time-based features of the authenticator.
+///
+/// By default, the authenticator uses the [`SystemClock`].
+/// You can set a different clock with [`GitAuthenticator::set_clock()`][crate::GitAuthenticator::set_clock()].
+/// This is mostly useful for tests, where a [`ManualClock`] allows you to fast-forward time without sleeping.
+pub trait Clock: Send + Sync {
+	/// Get the current monotonic time.
+	///
+	/// This is used for timeouts and durations.
+	fn now(&self) -> Instant;
+
+	/// Get the current wall-clock time.
+	///
+	/// This is used to compare against absolute expiry times, like the ones reported by credential helpers.
+	fn system_now(&self) -> SystemTime;
+
+	/// Wait for some time.
+	///
+	/// This is used to wait before retrying an operation that was rate limited by the server.
+	/// The default implementation puts the current thread to sleep.
+	fn sleep(&self, duration: Duration) {
+		std::thread::sleep(duration)
+	}
+}
+
+/// The default [`Clock`], which uses the real time of the system.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Instant {
+		Instant::now()
+	}
+
+	fn system_now(&self) -> SystemTime {
+		SystemTime::now()
+	}
+}
+
+/// A [`Clock`] that only moves forward when you tell it to.
+///
+/// All clones of a manual clock share the same time.
+///
+/// # Example
+/// ```
+/// # use auth_git2::{Clock, ManualClock};
+/// # use std::time::Duration;
+/// let clock = ManualClock::new();
+/// let start = clock.now();
+/// clock.advance(Duration::from_secs(60));
+/// assert_eq!(clock.now() - start, Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+	/// The monotonic time when the clock was created.
+	start: Instant,
+
+	/// The wall-clock time when the clock was created.
+	system_start: SystemTime,
+
+	/// The amount of time that the clock has been advanced.
+	elapsed: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+	/// Create a new manual clock that starts at the current time.
+	pub fn new() -> Self {
+		Self {
+			start: Instant::now(),
+			system_start: SystemTime::now(),
+			elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+		}
+	}
+
+	/// Move the clock forward.
+	pub fn advance(&self, duration: Duration) {
+		*self.lock() += duration;
+	}
+
+	/// Lock the elapsed time, ignoring poisoning.
+	fn lock(&self) -> std::sync::MutexGuard<'_, Duration> {
+		self.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl Default for ManualClock {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Clock for ManualClock {
+	fn now(&self) -> Instant {
+		self.start + *self.lock()
+	}
+
+	fn system_now(&self) -> SystemTime {
+		self.system_start + *self.lock()
+	}
+
+	/// Advance the clock instead of sleeping.
+	fn sleep(&self, duration: Duration) {
+		self.advance(duration)
+	}
+}
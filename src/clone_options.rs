@@ -0,0 +1,179 @@
+//! Options for cloning a repository with custom settings.
+
+use std::path::Path;
+
+/// Options for [`GitAuthenticator::clone_repo_with()`][crate::GitAuthenticator::clone_repo_with()].
+///
+/// By default, the default branch of the remote is cloned and fully checked out,
+/// just like [`GitAuthenticator::clone_repo()`][crate::GitAuthenticator::clone_repo()] does.
+///
+/// # Example: Clone and check out only part of a large repository
+/// ```no_run
+/// # fn main() -> Result<(), auth_git2::Error> {
+/// use auth_git2::{CloneOptions, GitAuthenticator};
+///
+/// let options = CloneOptions::new()
+///     .branch("main")
+///     .sparse_path("docs/")
+///     .sparse_path("services/billing/")
+///     .progress(|path, done, total| eprintln!("checkout {done}/{total}: {path:?}"));
+/// let repo = GitAuthenticator::default()
+///     .clone_repo_with("https://github.com/de-vri-es/auth-git2-rs", "/tmp/auth-git2-rs", options)?;
+/// # let _ = repo;
+/// # Ok(())
+/// # }
+/// ```
+pub struct CloneOptions<'cb> {
+	/// The checkout options, if they were customized.
+	checkout: Option<git2::build::CheckoutBuilder<'cb>>,
+
+	/// The branch to check out instead of the default branch of the remote.
+	branch: Option<String>,
+
+	/// Create a bare repository without a working tree.
+	bare: bool,
+}
+
+impl Default for CloneOptions<'_> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl std::fmt::Debug for CloneOptions<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("CloneOptions")
+			.field("checkout", &self.checkout.is_some())
+			.field("branch", &self.branch)
+			.field("bare", &self.bare)
+			.finish()
+	}
+}
+
+impl<'cb> CloneOptions<'cb> {
+	/// Create new clone options with the default behavior.
+	pub fn new() -> Self {
+		Self {
+			checkout: None,
+			branch: None,
+			bare: false,
+		}
+	}
+
+	/// Check out the given branch instead of the default branch of the remote.
+	pub fn branch(mut self, branch: impl Into<String>) -> Self {
+		self.branch = Some(branch.into());
+		self
+	}
+
+	/// Create a bare repository without a working tree.
+	pub fn bare(mut self, bare: bool) -> Self {
+		self.bare = bare;
+		self
+	}
+
+	/// Only check out paths that match the given pattern.
+	///
+	/// The pattern is a pathspec, like `"docs/"` or `"*.md"`.
+	/// If this is called multiple times, all paths that match any of the patterns are checked out.
+	///
+	/// libgit2 does not support the sparse checkout feature of git.
+	/// The other files are not checked out, but they are also not marked as skipped in the index,
+	/// so `git status` reports them as deleted.
+	pub fn sparse_path(self, pattern: impl Into<String>) -> Self {
+		let pattern = pattern.into();
+		self.checkout(|checkout| {
+			checkout.path(pattern);
+		})
+	}
+
+	/// Call a function for every file that is checked out.
+	///
+	/// The function is called with the path of the file, the number of completed steps and the total number of steps.
+	pub fn progress<F>(self, progress: F) -> Self
+	where
+		F: FnMut(Option<&Path>, usize, usize) + 'cb,
+	{
+		self.checkout(|checkout| {
+			checkout.progress(progress);
+		})
+	}
+
+	/// Overwrite existing files in the target directory.
+	pub fn force(self) -> Self {
+		self.checkout(|checkout| {
+			checkout.force();
+		})
+	}
+
+	/// Set the mode for new directories in the working tree.
+	///
+	/// The default is `0o755`.
+	pub fn dir_perm(self, mode: i32) -> Self {
+		self.checkout(|checkout| {
+			checkout.dir_perm(mode);
+		})
+	}
+
+	/// Set the mode for new files in the working tree.
+	///
+	/// The default is `0o644` or `0o755`, depending on the file mode in the repository.
+	pub fn file_perm(self, mode: i32) -> Self {
+		self.checkout(|checkout| {
+			checkout.file_perm(mode);
+		})
+	}
+
+	/// Customize the checkout options directly.
+	///
+	/// This gives access to all options of [`git2::build::CheckoutBuilder`].
+	/// The function can be used multiple times, and it is combined with the other options.
+	pub fn checkout<F>(mut self, customize: F) -> Self
+	where
+		F: FnOnce(&mut git2::build::CheckoutBuilder<'cb>),
+	{
+		customize(self.checkout.get_or_insert_with(git2::build::CheckoutBuilder::new));
+		self
+	}
+
+	/// Apply the options to a [`git2::build::RepoBuilder`].
+	pub(crate) fn apply(self, repo_builder: &mut git2::build::RepoBuilder<'cb>) {
+		if let Some(checkout) = self.checkout {
+			repo_builder.with_checkout(checkout);
+		}
+		if let Some(branch) = &self.branch {
+			repo_builder.branch(branch);
+		}
+		repo_builder.bare(self.bare);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+	use crate::GitAuthenticator;
+
+	#[test]
+	fn test_sparse_clone() {
+		let_assert!(Ok(temp_dir) = crate::temp_dir::TempDir::create("auth-git2-test-sparse-clone"));
+		let dir = temp_dir.path();
+		let source = dir.join("source");
+		let_assert!(Ok(repo) = git2::Repository::init(&source));
+		assert!(let Ok(()) = std::fs::create_dir_all(source.join("docs")));
+		assert!(let Ok(()) = std::fs::write(source.join("docs/index.md"), "docs"));
+		assert!(let Ok(()) = std::fs::write(source.join("big.bin"), "big"));
+
+		let_assert!(Ok(mut index) = repo.index());
+		assert!(let Ok(()) = index.add_all(["*"], git2::IndexAddOption::DEFAULT, None));
+		let_assert!(Ok(tree) = index.write_tree().and_then(|x| repo.find_tree(x)));
+		let_assert!(Ok(signature) = git2::Signature::now("Test", "test@example.com"));
+		assert!(let Ok(_) = repo.commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[]));
+
+		let clone = dir.join("clone");
+		let options = CloneOptions::new().sparse_path("docs/");
+		let_assert!(Ok(_) = GitAuthenticator::new_empty().clone_repo_with(source.to_str().unwrap(), &clone, options));
+		assert!(clone.join("docs/index.md").is_file());
+		assert!(!clone.join("big.bin").exists());
+	}
+}
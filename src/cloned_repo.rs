@@ -0,0 +1,140 @@
+use crate::scoped::Overrides;
+use crate::{Error, GitAuthenticator, ScopedAuthenticator};
+
+/// A freshly cloned repository, together with the authenticator that was used to clone it.
+///
+/// This allows you to fetch from and push to the remote of the clone without passing the remote and the authenticator around.
+/// The fetches and pushes use the configuration of the repository, and the same overridden settings as the clone (see [`ScopedAuthenticator`]).
+///
+/// Create one with [`GitAuthenticator::clone_repo_authenticated()`] or [`ScopedAuthenticator::clone_repo_authenticated()`].
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), auth_git2::Error> {
+/// use auth_git2::{CloneOptions, GitAuthenticator};
+///
+/// let auth = GitAuthenticator::default();
+/// let cloned = auth.clone_repo_authenticated("https://github.com/de-vri-es/auth-git2-rs", "/tmp/auth-git2-rs", CloneOptions::new())?;
+///
+/// // Later: update the main branch.
+/// cloned.fetch(&["main"], None)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ClonedRepo {
+	/// The cloned repository.
+	repo: git2::Repository,
+
+	/// The name of the remote that was cloned from.
+	remote_name: String,
+
+	/// The authenticator that was used for the clone.
+	authenticator: GitAuthenticator,
+
+	/// The settings that were overridden for the clone.
+	overrides: Overrides,
+}
+
+impl std::fmt::Debug for ClonedRepo {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ClonedRepo")
+			.field("path", &self.repo.path())
+			.field("remote_name", &self.remote_name)
+			.field("authenticator", &self.authenticator)
+			.finish_non_exhaustive()
+	}
+}
+
+impl ClonedRepo {
+	/// Wrap a freshly cloned repository.
+	pub(crate) fn new(repo: git2::Repository, authenticator: GitAuthenticator, overrides: Overrides) -> Self {
+		Self {
+			repo,
+			remote_name: "origin".into(),
+			authenticator,
+			overrides,
+		}
+	}
+
+	/// Get the cloned repository.
+	pub fn repo(&self) -> &git2::Repository {
+		&self.repo
+	}
+
+	/// Get the cloned repository, discarding the authenticator.
+	pub fn into_repo(self) -> git2::Repository {
+		self.repo
+	}
+
+	/// Get the authenticator that was used for the clone.
+	///
+	/// This is a clone of the original authenticator:
+	/// changes to the original authenticator after the clone do not affect it.
+	/// Caches of short-lived credentials are still shared with the original authenticator.
+	pub fn authenticator(&self) -> &GitAuthenticator {
+		&self.authenticator
+	}
+
+	/// Get the name of the remote that was cloned from.
+	pub fn remote_name(&self) -> &str {
+		&self.remote_name
+	}
+
+	/// Get the remote that was cloned from.
+	pub fn remote(&self) -> Result<git2::Remote<'_>, git2::Error> {
+		self.repo.find_remote(&self.remote_name)
+	}
+
+	/// Fetch from the remote that was cloned from.
+	///
+	/// See [`GitAuthenticator::fetch()`] for more details.
+	pub fn fetch(&self, refspecs: &[&str], reflog_msg: Option<&str>) -> Result<(), Error> {
+		let mut remote = self.remote()?;
+		self.scoped().fetch(&self.repo, &mut remote, refspecs, reflog_msg)
+	}
+
+	/// Push to the remote that was cloned from.
+	///
+	/// See [`GitAuthenticator::push()`] for more details.
+	pub fn push(&self, refspecs: &[&str]) -> Result<(), Error> {
+		let mut remote = self.remote()?;
+		self.scoped().push(&self.repo, &mut remote, refspecs)
+	}
+
+	/// Get the authenticator with the same overridden settings as the clone.
+	fn scoped(&self) -> ScopedAuthenticator<'_> {
+		ScopedAuthenticator::with_overrides(&self.authenticator, self.overrides.clone())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+	use crate::CloneOptions;
+
+	#[test]
+	fn test_fetch_after_clone() {
+		let_assert!(Ok(temp_dir) = crate::temp_dir::TempDir::create("auth-git2-test-cloned-repo"));
+		let dir = temp_dir.path();
+		let source = dir.join("source");
+		let_assert!(Ok(repo) = git2::Repository::init(&source));
+		let_assert!(Ok(signature) = git2::Signature::now("Test", "test@example.com"));
+		let_assert!(Ok(tree) = repo.index().and_then(|mut x| x.write_tree()).and_then(|x| repo.find_tree(x)));
+		let_assert!(Ok(first) = repo.commit(Some("HEAD"), &signature, &signature, "First commit", &tree, &[]));
+
+		let clone = dir.join("clone");
+		let auth = GitAuthenticator::new_empty();
+		let_assert!(Ok(cloned) = auth.clone_repo_authenticated(source.to_str().unwrap(), &clone, CloneOptions::new()));
+		assert!(cloned.remote_name() == "origin");
+		let_assert!(Ok(head) = cloned.repo().refname_to_id("HEAD"));
+		assert!(head == first);
+
+		let_assert!(Ok(parent) = repo.find_commit(first));
+		let_assert!(Ok(second) = repo.commit(Some("HEAD"), &signature, &signature, "Second commit", &tree, &[&parent]));
+		let_assert!(Ok(branch) = repo.head().map(|x| x.shorthand().unwrap_or_default().to_owned()));
+		assert!(let Ok(()) = cloned.fetch(&[&branch], None));
+		let_assert!(Ok(fetched) = cloned.repo().refname_to_id(&format!("refs/remotes/origin/{branch}")));
+		assert!(fetched == second);
+	}
+}
@@ -0,0 +1,353 @@
+//! Configuration of an authenticator that can be loaded from a configuration file.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::{ConfigPrecedence, DefaultCredentialsOrder, GitAuthenticator, InsecureTransportPolicy};
+
+/// Configuration of a [`GitAuthenticator`] that can be deserialized with `serde`.
+///
+/// This allows applications to let users configure authentication in their own configuration file,
+/// in any format supported by `serde`.
+/// Use [`GitAuthenticator::from_config()`] to create an authenticator from the configuration.
+///
+/// All fields are optional.
+/// Missing toggles keep the value of the [preset][ConfigPreset], which is the same as [`GitAuthenticator::new()`] by default.
+/// Unknown fields are rejected, so typos are reported instead of silently ignored.
+///
+/// The configuration can not contain passwords or tokens.
+/// Use a credential helper, environment variables or another credential store for those.
+///
+/// This type is only available with the `serde` feature.
+///
+/// # Example
+/// ```toml
+/// preset = "empty"
+/// try_ssh_agent = true
+/// try_cred_helper = true
+/// ssh_keys = ["/etc/mirror/deploy_key"]
+///
+/// [usernames]
+/// "git.example.com" = "mirror"
+///
+/// [[credentials_from_env]]
+/// username_var = "MIRROR_USERNAME"
+/// password_var = "MIRROR_TOKEN"
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct GitAuthenticatorConfig {
+	/// The authenticator to start from.
+	pub preset: ConfigPreset,
+
+	/// See [`GitAuthenticator::ignore_environment()`].
+	pub ignore_environment: Option<bool>,
+
+	/// The home directory of the user, see [`GitAuthenticator::home_dir()`].
+	pub home_dir: Option<PathBuf>,
+
+	/// The directory with SSH keys and configuration, see [`GitAuthenticator::ssh_dir()`].
+	pub ssh_dir: Option<PathBuf>,
+
+	/// Add the default SSH keys, see [`GitAuthenticator::add_default_ssh_keys()`].
+	///
+	/// If not set, the default SSH keys are only added by the `default` preset.
+	pub default_ssh_keys: Option<bool>,
+
+	/// Private key files to add, see [`GitAuthenticator::add_ssh_key_from_file()`].
+	pub ssh_keys: Vec<PathBuf>,
+
+	/// Private key files to add by host pattern, see [`GitAuthenticator::add_ssh_key_from_file_for_host()`].
+	pub host_ssh_keys: BTreeMap<String, Vec<PathBuf>>,
+
+	/// Usernames by domain, see [`GitAuthenticator::add_username()`].
+	pub usernames: BTreeMap<String, String>,
+
+	/// Environment variables to read usernames and passwords from, see [`GitAuthenticator::add_credentials_from_env()`].
+	pub credentials_from_env: Vec<EnvCredentialsConfig>,
+
+	/// Names of systemd credentials with the password by domain, see [`GitAuthenticator::add_credentials_from_systemd()`].
+	pub systemd_credentials: BTreeMap<String, String>,
+
+	/// See [`GitAuthenticator::try_ssh_agent()`].
+	pub try_ssh_agent: Option<bool>,
+
+	/// Fingerprints or comments of the keys of the SSH agent to use, see [`GitAuthenticator::try_ssh_agent_key()`].
+	pub ssh_agent_keys: Vec<String>,
+
+	/// The socket of the SSH agent, see [`GitAuthenticator::ssh_agent_socket()`].
+	pub ssh_agent_socket: Option<PathBuf>,
+
+	/// See [`GitAuthenticator::use_ssh_config()`].
+	pub use_ssh_config: Option<bool>,
+
+	/// See [`GitAuthenticator::try_cred_helper()`].
+	pub try_cred_helper: Option<bool>,
+
+	/// See [`GitAuthenticator::cache_helper_credentials()`].
+	pub cache_helper_credentials: Option<bool>,
+
+	/// See [`GitAuthenticator::try_default_credentials()`].
+	pub try_default_credentials: Option<bool>,
+
+	/// See [`GitAuthenticator::default_credentials_order()`].
+	pub default_credentials_order: Option<DefaultCredentialsOrder>,
+
+	/// See [`GitAuthenticator::try_env_credentials()`].
+	pub try_env_credentials: Option<bool>,
+
+	/// See [`GitAuthenticator::try_netrc()`].
+	pub try_netrc: Option<bool>,
+
+	/// See [`GitAuthenticator::try_credential_store()`].
+	pub try_credential_store: Option<bool>,
+
+	/// See [`GitAuthenticator::try_pass()`].
+	pub try_pass: Option<bool>,
+
+	/// See [`GitAuthenticator::try_macos_keychain()`].
+	pub try_macos_keychain: Option<bool>,
+
+	/// See [`GitAuthenticator::try_gh_cli()`].
+	pub try_gh_cli: Option<bool>,
+
+	/// See [`GitAuthenticator::try_glab_cli()`].
+	pub try_glab_cli: Option<bool>,
+
+	/// See [`GitAuthenticator::try_gcloud()`].
+	pub try_gcloud: Option<bool>,
+
+	/// See [`GitAuthenticator::try_password_prompt()`].
+	pub try_password_prompt: Option<u32>,
+
+	/// See [`GitAuthenticator::store_prompted_credentials()`].
+	pub store_prompted_credentials: Option<bool>,
+
+	/// See [`GitAuthenticator::erase_rejected_credentials()`].
+	pub erase_rejected_credentials: Option<bool>,
+
+	/// See [`GitAuthenticator::prompt_ssh_key_password()`].
+	pub prompt_ssh_key_password: Option<bool>,
+
+	/// See [`GitAuthenticator::cache_passphrases()`].
+	pub cache_passphrases: Option<bool>,
+
+	/// See [`GitAuthenticator::retry_ssh_usernames()`].
+	pub retry_ssh_usernames: Option<bool>,
+
+	/// See [`GitAuthenticator::ssh_username_from_identity()`].
+	pub ssh_username_from_identity: Option<bool>,
+
+	/// See [`GitAuthenticator::use_http_path()`].
+	pub use_http_path: Option<bool>,
+
+	/// Alternative host names, mapped to the host they are an alias of, see [`GitAuthenticator::alias()`].
+	pub host_aliases: BTreeMap<String, String>,
+
+	/// Host patterns of redirect targets that may use the credentials of the original host, see [`GitAuthenticator::allow_redirect_credentials()`].
+	pub redirect_credentials: Vec<String>,
+
+	/// See [`GitAuthenticator::insecure_transport_policy()`].
+	pub insecure_transport_policy: Option<InsecureTransportPolicy>,
+
+	/// Host patterns that may receive passwords over plain HTTP, see [`GitAuthenticator::allow_insecure_transport()`].
+	pub insecure_hosts: Vec<String>,
+
+	/// See [`GitAuthenticator::config_precedence()`].
+	pub config_precedence: Option<ConfigPrecedence>,
+
+	/// See [`GitAuthenticator::max_username_requests()`].
+	pub max_username_requests: Option<u32>,
+
+	/// See [`GitAuthenticator::max_callback_invocations()`].
+	pub max_callback_invocations: Option<u32>,
+
+	/// See [`GitAuthenticator::max_ssh_key_file_size()`].
+	pub max_ssh_key_file_size: Option<u64>,
+
+	/// See [`GitAuthenticator::max_ssh_keys_per_host()`].
+	pub max_ssh_keys_per_host: Option<u32>,
+
+	/// See [`GitAuthenticator::rate_limit_retries()`].
+	pub rate_limit_retries: Option<u32>,
+}
+
+/// The authenticator that a [`GitAuthenticatorConfig`] starts from.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigPreset {
+	/// Start from [`GitAuthenticator::new()`].
+	#[default]
+	Default,
+
+	/// Start from [`GitAuthenticator::new_empty()`].
+	Empty,
+
+	/// Start from [`GitAuthenticator::cargo_compatible()`].
+	Cargo,
+}
+
+/// Environment variables to read a username and password from.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvCredentialsConfig {
+	/// The name of the environment variable with the username.
+	pub username_var: String,
+
+	/// The name of the environment variable with the password.
+	pub password_var: String,
+}
+
+/// Create an authenticator from a configuration.
+pub(crate) fn build(config: &GitAuthenticatorConfig) -> GitAuthenticator {
+	let mut auth = GitAuthenticator::new_empty();
+
+	// Set the directories first, so the default SSH keys are found in the right place.
+	auth = apply(auth, config.ignore_environment, GitAuthenticator::ignore_environment);
+	if let Some(home_dir) = &config.home_dir {
+		auth = auth.home_dir(home_dir);
+	}
+	if let Some(ssh_dir) = &config.ssh_dir {
+		auth = auth.ssh_dir(ssh_dir);
+	}
+
+	auth = match config.preset {
+		ConfigPreset::Default => auth.enable_defaults(config.default_ssh_keys.unwrap_or(true)),
+		ConfigPreset::Empty => auth,
+		ConfigPreset::Cargo => auth.enable_cargo_defaults(),
+	};
+	if config.default_ssh_keys == Some(true) && config.preset != ConfigPreset::Default {
+		auth = auth.add_default_ssh_keys();
+	}
+
+	for private_key in &config.ssh_keys {
+		auth = auth.add_ssh_key_from_file(private_key, None);
+	}
+	for (host_pattern, private_keys) in &config.host_ssh_keys {
+		for private_key in private_keys {
+			auth = auth.add_ssh_key_from_file_for_host(host_pattern, private_key, None);
+		}
+	}
+	for (alias, host) in &config.host_aliases {
+		auth = auth.alias(alias, host);
+	}
+	for pattern in &config.redirect_credentials {
+		auth = auth.allow_redirect_credentials(pattern);
+	}
+	for pattern in &config.insecure_hosts {
+		auth = auth.allow_insecure_transport(pattern);
+	}
+	for selector in &config.ssh_agent_keys {
+		auth = auth.try_ssh_agent_key(selector);
+	}
+	if let Some(socket) = &config.ssh_agent_socket {
+		auth = auth.ssh_agent_socket(socket);
+	}
+	for (domain, username) in &config.usernames {
+		auth = auth.add_username(domain, username);
+	}
+	for (domain, name) in &config.systemd_credentials {
+		auth = auth.add_credentials_from_systemd(name, domain);
+	}
+	for variables in &config.credentials_from_env {
+		auth = auth.add_credentials_from_env(&variables.username_var, &variables.password_var);
+	}
+
+	auth = apply(auth, config.try_ssh_agent, GitAuthenticator::try_ssh_agent);
+	auth = apply(auth, config.use_ssh_config, GitAuthenticator::use_ssh_config);
+	auth = apply(auth, config.try_cred_helper, GitAuthenticator::try_cred_helper);
+	auth = apply(auth, config.cache_helper_credentials, GitAuthenticator::cache_helper_credentials);
+	auth = apply(auth, config.try_default_credentials, GitAuthenticator::try_default_credentials);
+	auth = apply(auth, config.default_credentials_order, GitAuthenticator::default_credentials_order);
+	auth = apply(auth, config.try_env_credentials, GitAuthenticator::try_env_credentials);
+	auth = apply(auth, config.try_netrc, GitAuthenticator::try_netrc);
+	auth = apply(auth, config.try_credential_store, GitAuthenticator::try_credential_store);
+	auth = apply(auth, config.try_pass, GitAuthenticator::try_pass);
+	auth = apply(auth, config.try_macos_keychain, GitAuthenticator::try_macos_keychain);
+	auth = apply(auth, config.try_gh_cli, GitAuthenticator::try_gh_cli);
+	auth = apply(auth, config.try_glab_cli, GitAuthenticator::try_glab_cli);
+	auth = apply(auth, config.try_gcloud, GitAuthenticator::try_gcloud);
+	auth = apply(auth, config.prompt_ssh_key_password, GitAuthenticator::prompt_ssh_key_password);
+	auth = apply(auth, config.cache_passphrases, GitAuthenticator::cache_passphrases);
+	auth = apply(auth, config.retry_ssh_usernames, GitAuthenticator::retry_ssh_usernames);
+	auth = apply(auth, config.ssh_username_from_identity, GitAuthenticator::ssh_username_from_identity);
+	auth = apply(auth, config.use_http_path, GitAuthenticator::use_http_path);
+	auth = apply(auth, config.config_precedence, GitAuthenticator::config_precedence);
+	auth = apply(auth, config.insecure_transport_policy, GitAuthenticator::insecure_transport_policy);
+	auth = apply(auth, config.try_password_prompt, GitAuthenticator::try_password_prompt);
+	auth = apply(auth, config.store_prompted_credentials, GitAuthenticator::store_prompted_credentials);
+	auth = apply(auth, config.erase_rejected_credentials, GitAuthenticator::erase_rejected_credentials);
+	auth = apply(auth, config.max_username_requests, GitAuthenticator::max_username_requests);
+	auth = apply(auth, config.max_callback_invocations, GitAuthenticator::max_callback_invocations);
+	auth = apply(auth, config.max_ssh_key_file_size, GitAuthenticator::max_ssh_key_file_size);
+	auth = apply(auth, config.max_ssh_keys_per_host, GitAuthenticator::max_ssh_keys_per_host);
+	auth = apply(auth, config.rate_limit_retries, GitAuthenticator::rate_limit_retries);
+
+	auth
+}
+
+/// Apply a setting to an authenticator if it is set.
+fn apply<T>(auth: GitAuthenticator, value: Option<T>, set: fn(GitAuthenticator, T) -> GitAuthenticator) -> GitAuthenticator {
+	match value {
+		Some(value) => set(auth, value),
+		None => auth,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_deserialize() {
+		let_assert!(Ok(config) = toml::from_str::<GitAuthenticatorConfig>(r#"
+			preset = "empty"
+			try_cred_helper = true
+			try_password_prompt = 2
+			config_precedence = "prefer_global"
+			ssh_keys = ["/no/such/deploy_key"]
+
+			[usernames]
+			"git.example.com" = "mirror"
+
+			[[credentials_from_env]]
+			username_var = "MIRROR_USERNAME"
+			password_var = "MIRROR_TOKEN"
+
+			[systemd_credentials]
+			"github.com" = "git-token"
+		"#));
+		assert!(config.preset == ConfigPreset::Empty);
+		assert!(config.try_cred_helper == Some(true));
+		assert!(config.try_ssh_agent.is_none());
+
+		let auth = build(&config);
+		assert!(auth.try_cred_helper);
+		assert!(!auth.try_ssh_agent);
+		assert!(auth.try_password_prompt == 2);
+		assert!(auth.ssh_keys.len() == 1);
+		assert!(auth.usernames.get("git.example.com").map(String::as_str) == Some("mirror"));
+		assert!(auth.sources.env_credentials.len() == 1);
+		assert!(auth.sources.systemd_credentials.get("github.com").map(String::as_str) == Some("git-token"));
+		assert!(auth.config_precedence == ConfigPrecedence::PreferGlobal);
+
+		assert!(let Err(_) = toml::from_str::<GitAuthenticatorConfig>("try_ssh_agnet = true"));
+		assert!(let Err(_) = toml::from_str::<GitAuthenticatorConfig>("preset = \"unknown\""));
+	}
+
+	#[test]
+	fn test_presets() {
+		let_assert!(Ok(config) = toml::from_str::<GitAuthenticatorConfig>("try_ssh_agent = false"));
+		let auth = build(&config);
+		assert!(auth.try_cred_helper);
+		assert!(auth.try_password_prompt == 3);
+		assert!(!auth.try_ssh_agent);
+
+		let_assert!(Ok(config) = toml::from_str::<GitAuthenticatorConfig>("preset = \"cargo\""));
+		let auth = build(&config);
+		assert!(auth.retry_ssh_usernames);
+		assert!(auth.try_password_prompt == 0);
+	}
+}
@@ -0,0 +1,82 @@
+//! Precedence of values that are set in multiple git configuration files.
+
+/// Which value to use when both the repository configuration and the global configuration set the same key.
+///
+/// This applies to the `core.askPass` and `credential.helper` configuration values.
+/// Values from the `GIT_CONFIG_COUNT` environment variables (see [`apply_env_config()`][crate::apply_env_config()])
+/// and the `GIT_ASKPASS` environment variable always take precedence.
+///
+/// Use [`GitAuthenticator::config_precedence()`][crate::GitAuthenticator::config_precedence()] to change the precedence.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum ConfigPrecedence {
+	/// The most specific configuration wins, like it does for `git`.
+	///
+	/// The configuration of the repository overrides the global configuration of the user,
+	/// which overrides the system configuration.
+	#[default]
+	PreferLocal,
+
+	/// The global and system configuration override the configuration of the repository.
+	///
+	/// This is useful if you work with repositories that you do not fully trust,
+	/// since their configuration can not replace the askpass program or credential helpers of the user.
+	/// The configuration of the repository is still used for keys that are not set globally.
+	PreferGlobal,
+}
+
+impl ConfigPrecedence {
+	/// Get a path value from the configuration.
+	#[cfg(feature = "prompts")]
+	pub(crate) fn get_path(self, git_config: &git2::Config, key: &str) -> Option<std::path::PathBuf> {
+		self.get(git_config, key, |config, key| config.get_path(key))
+	}
+
+	/// Get a value from the configuration with the given getter.
+	#[cfg(feature = "prompts")]
+	fn get<T>(self, git_config: &git2::Config, key: &str, get: impl Fn(&git2::Config, &str) -> Result<T, git2::Error>) -> Option<T> {
+		if self == Self::PreferGlobal {
+			let levels = [
+				git2::ConfigLevel::App,
+				git2::ConfigLevel::Global,
+				git2::ConfigLevel::XDG,
+				git2::ConfigLevel::System,
+				git2::ConfigLevel::ProgramData,
+			];
+			for level in levels {
+				if let Some(value) = git_config.open_level(level).ok().and_then(|config| get(&config, key).ok()) {
+					return Some(value);
+				}
+			}
+		}
+		get(git_config, key).ok()
+	}
+}
+
+#[cfg(all(test, feature = "prompts"))]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_precedence() {
+		let_assert!(Ok(global) = crate::temp_key::TempKeyFile::create("[core]\n\taskPass = /usr/bin/global-askpass\n[credential]\n\thelper = global\n"));
+		let_assert!(Ok(local) = crate::temp_key::TempKeyFile::create("[core]\n\taskPass = /tmp/local-askpass\n[user]\n\tname = local\n"));
+
+		let_assert!(Ok(mut git_config) = git2::Config::new());
+		assert!(let Ok(()) = git_config.add_file(global.path(), git2::ConfigLevel::Global, true));
+		assert!(let Ok(()) = git_config.add_file(local.path(), git2::ConfigLevel::Local, true));
+		let_assert!(Ok(snapshot) = git_config.snapshot());
+		drop((global, local));
+
+		let prefer_local = ConfigPrecedence::PreferLocal;
+		let prefer_global = ConfigPrecedence::PreferGlobal;
+		assert!(let Some("/tmp/local-askpass") = prefer_local.get_path(&snapshot, "core.askPass").as_deref().and_then(|x| x.to_str()));
+		assert!(let Some("/usr/bin/global-askpass") = prefer_global.get_path(&snapshot, "core.askPass").as_deref().and_then(|x| x.to_str()));
+		let get_string = |config: &git2::Config, key: &str| config.get_string(key);
+		assert!(let Some("global") = prefer_global.get(&snapshot, "credential.helper", get_string).as_deref());
+		assert!(let Some("local") = prefer_global.get(&snapshot, "user.name", get_string).as_deref());
+		assert!(let None = prefer_global.get(&snapshot, "user.email", get_string));
+	}
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Record of hosts for which the user recently declined a prompt.
+///
+/// The record is shared between all clones of an authenticator.
+#[derive(Clone, Default)]
+pub(crate) struct PromptCooldowns {
+	/// Map of hosts to the time when the user declined the last prompt.
+	declined: Arc<Mutex<BTreeMap<String, Instant>>>,
+}
+
+impl PromptCooldowns {
+	/// Record that the user declined a prompt for a host at the given time.
+	pub(crate) fn start(&self, host: &str, now: Instant) {
+		self.lock().insert(host.to_owned(), now);
+	}
+
+	/// Check if prompts for a host are suppressed at the given time.
+	pub(crate) fn is_active(&self, host: &str, cooldown: Duration, now: Instant) -> bool {
+		let mut declined = self.lock();
+		match declined.get(host) {
+			None => false,
+			Some(&time) if now.saturating_duration_since(time) < cooldown => true,
+			Some(_) => {
+				declined.remove(host);
+				false
+			},
+		}
+	}
+
+	/// Clear the cool-down for a host.
+	pub(crate) fn clear(&self, host: &str) {
+		self.lock().remove(host);
+	}
+
+	/// Clear the cool-down for all hosts that match a host pattern.
+	pub(crate) fn clear_matching(&self, pattern: &str) {
+		self.lock().retain(|host, _| !crate::credential_helper::host_matches(pattern, host));
+	}
+
+	/// Clear the cool-down for all hosts.
+	pub(crate) fn clear_all(&self) {
+		self.lock().clear();
+	}
+
+	/// Lock the map, ignoring poisoning.
+	///
+	/// The map is always in a valid state, even if a thread panicked while holding the lock.
+	fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Instant>> {
+		self.declined.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl std::fmt::Debug for PromptCooldowns {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_set()
+			.entries(self.lock().keys())
+			.finish()
+	}
+}
@@ -0,0 +1,153 @@
+//! Server side of the git credential helper protocol, to answer requests of command-line git with an authenticator.
+//!
+//! Git runs a credential helper with the action as argument, writes the attributes of the request to its standard input,
+//! and reads the credentials from its standard output.
+
+use std::io::{BufRead, Write};
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::{GitAuthenticator, ResolvedCredential};
+
+/// The attributes of a request from git.
+#[derive(Debug, Default, Eq, PartialEq)]
+struct Request {
+	/// The protocol of the URL, like `https`.
+	protocol: Option<String>,
+
+	/// The host of the URL, including the port.
+	host: Option<String>,
+
+	/// The path of the URL, only sent if `credential.useHttpPath` is enabled.
+	path: Option<String>,
+
+	/// The username, if it is already known.
+	username: Option<String>,
+
+	/// The full URL, which git accepts instead of the other attributes.
+	url: Option<String>,
+}
+
+impl Request {
+	/// Read the request attributes up to the first empty line or the end of the input.
+	///
+	/// Unknown attributes, like `capability[]` and `wwwauth[]`, are ignored.
+	fn read(input: impl BufRead) -> std::io::Result<Self> {
+		let mut request = Self::default();
+		for line in input.lines() {
+			let line = line?;
+			if line.is_empty() {
+				break;
+			}
+			let Some((key, value)) = line.split_once('=') else {
+				continue;
+			};
+			match key {
+				"protocol" => request.protocol = Some(value.into()),
+				"host" => request.host = Some(value.into()),
+				"path" => request.path = Some(value.into()),
+				"username" => request.username = Some(value.into()),
+				"url" => request.url = Some(value.into()),
+				_ => (),
+			}
+		}
+		Ok(request)
+	}
+
+	/// Get the URL to find credentials for.
+	fn url(&self) -> Option<String> {
+		if let Some(url) = &self.url {
+			return Some(url.clone());
+		}
+		let protocol = self.protocol.as_deref()?;
+		let host = self.host.as_deref()?;
+		match self.path.as_deref() {
+			Some(path) => Some(format!("{protocol}://{host}/{path}")),
+			None => Some(format!("{protocol}://{host}")),
+		}
+	}
+}
+
+/// Answer a single credential helper request from git.
+pub(crate) fn serve(
+	authenticator: &GitAuthenticator,
+	git_config: &git2::Config,
+	action: &str,
+	input: impl BufRead,
+	mut output: impl Write,
+) -> std::io::Result<()> {
+	let request = Request::read(input)?;
+	if action != "get" {
+		// The authenticator does not store credentials itself, and git ignores the output of other actions.
+		debug!("credential helper: ignoring {action:?} request");
+		return Ok(());
+	}
+
+	let Some(url) = request.url() else {
+		debug!("credential helper: request without protocol and host, not answering");
+		return Ok(());
+	};
+
+	let mut resolver = authenticator.resolver(git_config);
+	match resolver(&url, request.username.as_deref(), git2::CredentialType::USER_PASS_PLAINTEXT) {
+		Ok(ResolvedCredential::UserPassPlaintext { username, password }) => {
+			let password = zeroize::Zeroizing::new(password);
+			if !is_valid_value(&username) || !is_valid_value(&password) {
+				warn!("credential helper: credentials for {url} contain a newline or NUL character, which can not be sent to git");
+				return Ok(());
+			}
+			write!(output, "username={username}\npassword={}\n", password.as_str())?;
+		},
+		Ok(_) => debug!("credential helper: no username and password found for {url}"),
+		Err(e) => debug!("credential helper: no credentials found for {url}: {e}"),
+	}
+	output.flush()
+}
+
+/// Check if a value can be sent to git, which does not allow newlines or NUL characters.
+fn is_valid_value(value: &str) -> bool {
+	!value.contains(['\n', '\0'])
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_read_request() {
+		let input = "capability[]=authtype\nprotocol=https\nhost=example.com:8443\npath=org/repo.git\nusername=alice\nwwwauth[]=Basic realm=\"x\"\n\nprotocol=ignored\n";
+		let_assert!(Ok(request) = Request::read(input.as_bytes()));
+		assert!(request.username.as_deref() == Some("alice"));
+		assert!(request.url().as_deref() == Some("https://example.com:8443/org/repo.git"));
+
+		let_assert!(Ok(request) = Request::read("protocol=https\nhost=example.com\n".as_bytes()));
+		assert!(request.url().as_deref() == Some("https://example.com"));
+
+		let_assert!(Ok(request) = Request::read("url=https://example.com/repo.git\n".as_bytes()));
+		assert!(request.url().as_deref() == Some("https://example.com/repo.git"));
+
+		let_assert!(Ok(request) = Request::read("host=example.com\n".as_bytes()));
+		assert!(let None = request.url());
+	}
+
+	#[test]
+	fn test_serve() {
+		let auth = GitAuthenticator::new_empty()
+			.add_plaintext_credentials("example.com", "alice", "hunter2");
+		let git_config = git2::Config::new().unwrap();
+
+		let mut output = Vec::new();
+		let_assert!(Ok(()) = serve(&auth, &git_config, "get", "protocol=https\nhost=example.com\n\n".as_bytes(), &mut output));
+		assert!(output == b"username=alice\npassword=hunter2\n");
+
+		let mut output = Vec::new();
+		let_assert!(Ok(()) = serve(&auth, &git_config, "get", "protocol=https\nhost=example.org\n\n".as_bytes(), &mut output));
+		assert!(output.is_empty());
+
+		let mut output = Vec::new();
+		let_assert!(Ok(()) = serve(&auth, &git_config, "store", "protocol=https\nhost=example.com\nusername=bob\npassword=x\n\n".as_bytes(), &mut output));
+		assert!(output.is_empty());
+	}
+}
@@ -0,0 +1,174 @@
+//! External sources of usernames and passwords, like password managers and the CLIs of git hosts.
+//!
+//! Each source lives in its own module and implements [`CredentialSource`].
+//! The credentials callback only decides when a source is tried,
+//! and it handles the labels, telemetry and failure tracking the same way for all sources.
+
+use std::collections::BTreeMap;
+
+use crate::label::LabelTarget;
+use crate::{EnvCredentials, GitAuthenticator, PlaintextCredentials, PlannedAttempt};
+#[cfg(feature = "bitwarden")]
+use crate::bitwarden;
+#[cfg(feature = "github-app")]
+use crate::GitHubApp;
+#[cfg(feature = "vault")]
+use crate::VaultSecret;
+
+/// The settings of the external sources of credentials of a [`GitAuthenticator`].
+#[derive(Debug, Clone)]
+pub(crate) struct SourceSettings {
+	/// Try getting passwords from the OS keyring.
+	///
+	/// This can only be enabled with the `keyring` feature.
+	pub try_keyring: bool,
+
+	/// Try getting internet passwords from the macOS Keychain.
+	pub try_macos_keychain: bool,
+
+	/// Environment variables to read usernames and passwords from.
+	pub env_credentials: Vec<EnvCredentials>,
+
+	/// Try getting username/password from the `GIT_USERNAME` and `GIT_PASSWORD` environment variables.
+	pub try_env_credentials: bool,
+
+	/// Map of domain names to the names of systemd credentials with the password.
+	pub systemd_credentials: BTreeMap<String, String>,
+
+	/// Try getting username/password from the `.netrc` file.
+	pub try_netrc: bool,
+
+	/// Try getting username/password from the files of the `store` credential helper.
+	pub try_credential_store: bool,
+
+	/// Try getting username/password from `pass`.
+	pub try_pass: bool,
+
+	/// The template for the name of the `pass` entry for a URL.
+	pub pass_path_template: String,
+
+	/// Map of domain names to HashiCorp Vault secrets.
+	#[cfg(feature = "vault")]
+	pub vault_entries: BTreeMap<String, VaultSecret>,
+
+	/// Try getting username/password from the Bitwarden CLI.
+	#[cfg(feature = "bitwarden")]
+	pub try_bitwarden: bool,
+
+	/// The session key of the Bitwarden vault, after it was unlocked with the master password.
+	#[cfg(feature = "bitwarden")]
+	pub bitwarden_session: bitwarden::SessionCache,
+
+	/// Map of domain names to docker credential helpers.
+	#[cfg(feature = "docker-credential-helper")]
+	pub docker_credential_helpers: BTreeMap<String, String>,
+
+	/// Map of domain names to GitHub Apps to get installation access tokens for.
+	#[cfg(feature = "github-app")]
+	pub github_apps: BTreeMap<String, GitHubApp>,
+
+	/// Try getting tokens for GitHub hosts from the `gh` CLI.
+	pub try_gh_cli: bool,
+
+	/// Try getting tokens for GitLab hosts from the `glab` CLI.
+	pub try_glab_cli: bool,
+
+	/// Try getting access tokens for Google Cloud Source Repositories from the `gcloud` CLI.
+	pub try_gcloud: bool,
+}
+
+impl Default for SourceSettings {
+	fn default() -> Self {
+		Self {
+			try_keyring: false,
+			try_macos_keychain: false,
+			env_credentials: Vec::new(),
+			try_env_credentials: false,
+			systemd_credentials: BTreeMap::new(),
+			try_netrc: false,
+			try_credential_store: false,
+			try_pass: false,
+			pass_path_template: crate::pass::DEFAULT_PATH_TEMPLATE.into(),
+			#[cfg(feature = "vault")]
+			vault_entries: BTreeMap::new(),
+			#[cfg(feature = "bitwarden")]
+			try_bitwarden: false,
+			#[cfg(feature = "bitwarden")]
+			bitwarden_session: bitwarden::SessionCache::default(),
+			#[cfg(feature = "docker-credential-helper")]
+			docker_credential_helpers: BTreeMap::new(),
+			#[cfg(feature = "github-app")]
+			github_apps: BTreeMap::new(),
+			try_gh_cli: false,
+			try_glab_cli: false,
+			try_gcloud: false,
+		}
+	}
+}
+
+/// A request for credentials from a [`CredentialSource`].
+pub(crate) struct SourceRequest<'a> {
+	/// The authenticator that the credentials callback was created from.
+	pub authenticator: &'a GitAuthenticator,
+
+	/// The URL to get the credentials for.
+	pub url: &'a str,
+
+	/// The username from the URL, if any.
+	pub username: Option<&'a str>,
+
+	/// Include the path of the URL when matching the configured domains.
+	pub use_http_path: bool,
+
+	/// The git configuration.
+	pub git_config: &'a git2::Config,
+
+	/// The prompter of the operation, if the user can be prompted.
+	#[cfg(feature = "bitwarden")]
+	pub prompter: Option<&'a mut dyn crate::Prompter>,
+}
+
+/// A source of a username and password that the credentials callback tries once per request.
+pub(crate) trait CredentialSource {
+	/// Get the name of the mechanism without identifying details, for telemetry.
+	fn mechanism(&self) -> &'static str;
+
+	/// Get the default label of the credentials from the source.
+	fn label(&self) -> String;
+
+	/// Get the target of a custom label set with [`GitAuthenticator::with_label()`], if the source can have one.
+	fn label_target(&self) -> Option<LabelTarget> {
+		None
+	}
+
+	/// Describe the source as a [`PlannedAttempt`].
+	fn to_planned(&self) -> PlannedAttempt;
+
+	/// Get the credentials for a request.
+	///
+	/// Returns `Ok(None)` if the source has no credentials, so the next mechanism is tried.
+	/// Returns an error if the source can not be used at all, which fails the credentials callback.
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String>;
+}
+
+impl<T: CredentialSource + ?Sized> CredentialSource for &T {
+	fn mechanism(&self) -> &'static str {
+		(**self).mechanism()
+	}
+
+	fn label(&self) -> String {
+		(**self).label()
+	}
+
+	fn label_target(&self) -> Option<LabelTarget> {
+		(**self).label_target()
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		(**self).to_planned()
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		(**self).get_credentials(request)
+	}
+}
@@ -0,0 +1,22 @@
+//! Position of the default credentials in the order of authentication mechanisms.
+
+/// When the default credentials of the current user are tried, relative to the other username/password mechanisms.
+///
+/// Use [`GitAuthenticator::default_credentials_order()`][crate::GitAuthenticator::default_credentials_order()] to set the order.
+/// It has no effect unless [`GitAuthenticator::try_default_credentials()`][crate::GitAuthenticator::try_default_credentials()] is enabled.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum DefaultCredentialsOrder {
+	/// Try the default credentials before all other username/password mechanisms.
+	///
+	/// This avoids running credential helpers and other programs when single sign-on works.
+	#[default]
+	First,
+
+	/// Try the default credentials right after the git credential helper.
+	///
+	/// The OAuth device flow, remembered credentials and password prompts are still tried after the default credentials.
+	/// This is the order that `cargo` uses.
+	AfterCredentialHelper,
+}
@@ -0,0 +1,122 @@
+//! Credentials from docker credential helpers (`docker-credential-*`).
+//!
+//! The helper is run with the `get` action, and the host of the URL is written to its standard input.
+//! It prints the credentials as a JSON object with `ServerURL`, `Username` and `Secret` fields.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::{PlaintextCredentials, PlannedAttempt};
+
+/// Get credentials for a URL from a docker credential helper.
+///
+/// The `helper` is the name of the helper without the `docker-credential-` prefix, like `pass` or `ecr-login`.
+/// Returns `None` if the helper failed, if it has no credentials for the host or if they are for a different username.
+pub(crate) fn get_credentials(helper: &str, url: &str, username: Option<&str>) -> Option<PlaintextCredentials> {
+	let host = crate::domain_from_url(url)?;
+	let program = program_name(helper);
+	let mut child = crate::recursion::mark(&mut Command::new(&program))
+		.arg("get")
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(|e| debug!("Failed to run {program}: {e}"))
+		.ok()?;
+	if let Some(mut stdin) = child.stdin.take() {
+		if let Err(e) = stdin.write_all(host.as_bytes()) {
+			debug!("Failed to write to the standard input of {program}: {e}");
+		}
+	}
+	let output = child.wait_with_output()
+		.map_err(|e| debug!("Failed to run {program}: {e}"))
+		.ok()?;
+	if !output.status.success() {
+		// The helpers report missing credentials on standard output, other errors on standard error.
+		let stdout = String::from_utf8_lossy(&output.stdout);
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		debug!("{program} get {host:?} failed: {} {}", stdout.trim(), stderr.trim());
+		return None;
+	}
+
+	let body = String::from_utf8(output.stdout)
+		.map_err(|_| warn!("Invalid UTF-8 in the output of {program}"))
+		.ok()?;
+	let credentials = parse_credentials(&body)
+		.map_err(|e| warn!("Invalid output of {program} for {host:?}: {e}"))
+		.ok()?;
+	match username {
+		Some(username) if username != credentials.username => {
+			debug!("{program} has credentials for {host:?} with username {:?}, not {username:?}", credentials.username);
+			None
+		},
+		_ => Some(credentials),
+	}
+}
+
+/// Get the name of the program for a helper.
+fn program_name(helper: &str) -> String {
+	format!("docker-credential-{helper}")
+}
+
+/// Parse the output of a docker credential helper.
+pub(crate) fn parse_credentials(body: &str) -> Result<PlaintextCredentials, String> {
+	let value: serde_json::Value = serde_json::from_str(body).map_err(|e| format!("invalid JSON: {e}"))?;
+	let field = |name: &str| value.get(name).and_then(|x| x.as_str()).filter(|x| !x.is_empty()).map(String::from);
+	let username = field("Username").ok_or("missing username")?;
+	if username == "<token>" {
+		// Docker uses this username for identity tokens, which only work for container registries.
+		return Err("identity tokens are not supported".into());
+	}
+	Ok(PlaintextCredentials {
+		username,
+		password: field("Secret").ok_or("missing secret")?,
+	})
+}
+
+/// A docker credential helper as source of credentials.
+pub(crate) struct DockerCredentialHelper<'a> {
+	/// The name of the helper without the `docker-credential-` prefix.
+	pub helper: &'a str,
+}
+
+impl CredentialSource for DockerCredentialHelper<'_> {
+	fn mechanism(&self) -> &'static str {
+		"docker-credential"
+	}
+
+	fn label(&self) -> String {
+		format!("docker-credential:{}", self.helper)
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::DockerCredentialHelper {
+			helper: self.helper.into(),
+		}
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		Ok(get_credentials(self.helper, request.url, request.username))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_parse_credentials() {
+		let_assert!(Ok(credentials) = parse_credentials(r#"{"ServerURL": "git.example.com", "Username": "mirror", "Secret": "hunter2"}"#));
+		assert!(credentials.username == "mirror");
+		assert!(credentials.password == "hunter2");
+
+		assert!(let Err(_) = parse_credentials(r#"{"ServerURL": "git.example.com", "Username": "<token>", "Secret": "identity"}"#));
+		assert!(let Err(_) = parse_credentials(r#"{"ServerURL": "git.example.com", "Username": "mirror"}"#));
+		assert!(let Err(_) = parse_credentials("credentials not found in native keychain"));
+	}
+}
@@ -0,0 +1,162 @@
+//! Configuration entries from the `GIT_CONFIG_COUNT` environment variables.
+//!
+//! Git reads extra configuration entries from `GIT_CONFIG_KEY_<n>` and `GIT_CONFIG_VALUE_<n>` for `n` from `0` to `GIT_CONFIG_COUNT - 1`.
+//! These entries take precedence over all configuration files.
+//! CI systems use this to inject configuration without touching any files.
+//!
+//! libgit2 does not read these variables, so the entries are written to a private temporary file,
+//! which is added to the configuration with the highest priority.
+//! A snapshot of the combined configuration is kept in memory, and the temporary file is deleted immediately.
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+/// Apply the configuration entries from the `GIT_CONFIG_COUNT` environment variables to a git configuration.
+///
+/// This returns a read-only snapshot of `git_config` with the entries from
+/// `GIT_CONFIG_KEY_<n>` and `GIT_CONFIG_VALUE_<n>` added with the highest priority, like `git` does.
+/// The original configuration is not modified.
+///
+/// The convenience functions of [`GitAuthenticator`][crate::GitAuthenticator] already do this.
+/// If you use [`GitAuthenticator::credentials()`][crate::GitAuthenticator::credentials()] directly,
+/// you can use this function to get the same configuration.
+///
+/// Returns an error if `GIT_CONFIG_COUNT` is set, but it is not a number or one of the variables is missing.
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), git2::Error> {
+/// let mut git_config = auth_git2::apply_env_config(&mut git2::Config::open_default()?)?;
+/// # let _ = git_config;
+/// # Ok(())
+/// # }
+/// ```
+pub fn apply_env_config(git_config: &mut git2::Config) -> Result<git2::Config, git2::Error> {
+	let entries = read_entries(|name| std::env::var(name).ok())
+		.map_err(|e| git2::Error::from_str(&e))?;
+	if entries.is_empty() {
+		return git_config.snapshot();
+	}
+
+	let contents = to_config_file(&entries)?;
+	let file = crate::temp_key::TempKeyFile::create(&contents)
+		.map_err(|e| git2::Error::from_str(&format!("failed to write configuration from environment to temporary file: {e}")))?;
+	debug!("adding {} configuration entries from GIT_CONFIG_COUNT", entries.len());
+	git_config.add_file(file.path(), git2::ConfigLevel::App, true)?;
+	git_config.snapshot()
+}
+
+/// Open the default git configuration with the entries from the `GIT_CONFIG_COUNT` environment variables applied.
+pub(crate) fn open_default() -> Result<git2::Config, git2::Error> {
+	apply_env_config(&mut git2::Config::open_default()?)
+}
+
+/// Read the configuration entries from the environment.
+///
+/// The `var` function gets the value of an environment variable.
+pub(crate) fn read_entries(var: impl Fn(&str) -> Option<String>) -> Result<Vec<(String, String)>, String> {
+	let count = match var("GIT_CONFIG_COUNT") {
+		None => return Ok(Vec::new()),
+		Some(x) => x,
+	};
+	// Like git, an empty count means there are no entries.
+	let count: usize = match count.trim() {
+		"" => 0,
+		trimmed => trimmed.parse().map_err(|_| format!("bogus count in GIT_CONFIG_COUNT: {count:?}"))?,
+	};
+	// Do not allocate up front, the count comes from the environment and can be anything.
+	let mut entries = Vec::new();
+	for i in 0..count {
+		let key = var(&format!("GIT_CONFIG_KEY_{i}"))
+			.ok_or_else(|| format!("missing config key GIT_CONFIG_KEY_{i}"))?;
+		let value = var(&format!("GIT_CONFIG_VALUE_{i}"))
+			.ok_or_else(|| format!("missing config value GIT_CONFIG_VALUE_{i}"))?;
+		entries.push((key, value));
+	}
+	Ok(entries)
+}
+
+/// Format configuration entries as the contents of a configuration file.
+pub(crate) fn to_config_file(entries: &[(String, String)]) -> Result<String, git2::Error> {
+	let mut contents = String::new();
+	for (key, value) in entries {
+		let (section, rest) = key.split_once('.')
+			.ok_or_else(|| git2::Error::from_str(&format!("invalid config key in GIT_CONFIG_COUNT entries: {key:?}")))?;
+		let (subsection, name) = match rest.rsplit_once('.') {
+			Some((subsection, name)) => (Some(subsection), name),
+			None => (None, rest),
+		};
+		if section.is_empty() || name.is_empty() {
+			return Err(git2::Error::from_str(&format!("invalid config key in GIT_CONFIG_COUNT entries: {key:?}")));
+		}
+		match subsection {
+			Some(subsection) => contents += &format!("[{section} \"{}\"]\n", escape(subsection)),
+			None => contents += &format!("[{section}]\n"),
+		}
+		contents += &format!("\t{name} = \"{}\"\n", escape(value));
+	}
+	Ok(contents)
+}
+
+/// Escape a string for use between double quotes in a configuration file.
+fn escape(value: &str) -> String {
+	let mut escaped = String::with_capacity(value.len());
+	for c in value.chars() {
+		match c {
+			'\\' => escaped.push_str("\\\\"),
+			'"' => escaped.push_str("\\\""),
+			'\n' => escaped.push_str("\\n"),
+			'\t' => escaped.push_str("\\t"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_read_entries() {
+		let env = |vars: &'static [(&'static str, &'static str)]| {
+			move |name: &str| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+		};
+		assert!(let Ok([]) = read_entries(env(&[])).as_deref());
+		let_assert!(Ok(entries) = read_entries(env(&[
+			("GIT_CONFIG_COUNT", "2"),
+			("GIT_CONFIG_KEY_0", "credential.helper"),
+			("GIT_CONFIG_VALUE_0", "store"),
+			("GIT_CONFIG_KEY_1", "http.https://example.com.extraHeader"),
+			("GIT_CONFIG_VALUE_1", "Authorization: Bearer token"),
+		])));
+		assert!(entries == [
+			("credential.helper".to_owned(), "store".to_owned()),
+			("http.https://example.com.extraHeader".to_owned(), "Authorization: Bearer token".to_owned()),
+		]);
+		assert!(let Err(_) = read_entries(env(&[("GIT_CONFIG_COUNT", "1")])));
+		assert!(let Err(_) = read_entries(env(&[("GIT_CONFIG_COUNT", "one")])));
+		assert!(let Ok([]) = read_entries(env(&[("GIT_CONFIG_COUNT", "")])).as_deref());
+		assert!(let Err(_) = read_entries(env(&[("GIT_CONFIG_COUNT", "999999999999999999999999999999")])));
+		assert!(let Err(_) = read_entries(env(&[("GIT_CONFIG_COUNT", "18446744073709551615")])));
+	}
+
+	#[test]
+	fn test_overlay() {
+		let entries = [
+			("credential.https://example.com.username".to_owned(), "alice".to_owned()),
+			("core.askPass".to_owned(), "/usr/bin/\"quoted\" \\ askpass".to_owned()),
+		];
+		let_assert!(Ok(contents) = to_config_file(&entries));
+		let_assert!(Ok(file) = crate::temp_key::TempKeyFile::create(&contents));
+		let_assert!(Ok(mut git_config) = git2::Config::new());
+		assert!(let Ok(()) = git_config.add_file(file.path(), git2::ConfigLevel::App, true));
+		let_assert!(Ok(snapshot) = git_config.snapshot());
+		drop(file);
+		assert!(let Ok("alice") = snapshot.get_str("credential.https://example.com.username"));
+		assert!(let Ok("/usr/bin/\"quoted\" \\ askpass") = snapshot.get_str("core.askpass"));
+
+		assert!(let Err(_) = to_config_file(&[("nodot".to_owned(), "x".to_owned())]));
+	}
+}
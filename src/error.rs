@@ -0,0 +1,442 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+use zeroize::Zeroizing;
+
+/// Error returned by the convenience functions of [`GitAuthenticator`][crate::GitAuthenticator].
+///
+/// The error wraps the [`git2::Error`] of the failed operation,
+/// and adds an [`ErrorKind`] to tell why authentication failed.
+///
+/// The error can be converted into a [`git2::Error`], so you can use the `?` operator in functions that return a [`git2::Error`].
+///
+/// # Example
+/// ```no_run
+/// fn update(repo: &git2::Repository, remote: &mut git2::Remote) -> Result<(), git2::Error> {
+///     let auth = auth_git2::GitAuthenticator::default();
+///     auth.fetch(repo, remote, &["main"], None)?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Error {
+	kind: ErrorKind,
+	inner: git2::Error,
+	label: Option<String>,
+	retry_after: Option<Duration>,
+	hint: Option<String>,
+	code: ErrorCode,
+	panic: Option<Box<PromptPanic>>,
+}
+
+/// The kind of an [`Error`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+	/// The user cancelled a prompt for credentials or for the passphrase of an SSH key.
+	///
+	/// This happens when a [`Prompter`][crate::Prompter] returns `None` or [`PromptError::Declined`][crate::PromptError::Declined],
+	/// and no other authentication mechanism succeeded.
+	/// If the user could not be prompted at all, the error has a different kind.
+	Cancelled,
+
+	/// The user was not prompted for credentials, because they declined a prompt for the same host recently.
+	///
+	/// See [`GitAuthenticator::prompt_cooldown()`][crate::GitAuthenticator::prompt_cooldown()].
+	PromptSuppressed,
+
+	/// The server kept asking for a username, even though one was provided.
+	///
+	/// See [`GitAuthenticator::max_username_requests()`][crate::GitAuthenticator::max_username_requests()].
+	UsernameLoop,
+
+	/// The credentials callback was called too many times for a single operation.
+	///
+	/// See [`GitAuthenticator::max_callback_invocations()`][crate::GitAuthenticator::max_callback_invocations()].
+	TooManyAttempts,
+
+	/// The linked libgit2 does not support the transport or the authentication mechanism that is needed.
+	///
+	/// For example, libgit2 may have been built without SSH support.
+	/// See [`capabilities()`][crate::capabilities()].
+	Unsupported,
+
+	/// The server rate limited the operation (HTTP status 429).
+	///
+	/// The operation is retried with exponential backoff if [`GitAuthenticator::rate_limit_retries()`][crate::GitAuthenticator::rate_limit_retries()] is set.
+	/// This error is returned when the server still rate limited the last retry.
+	/// If the server asked for a delay, it is available with [`Error::retry_after()`].
+	RateLimited,
+
+	/// A custom [`Prompter`][crate::Prompter] panicked.
+	///
+	/// The panic is caught to prevent it from unwinding into libgit2, and no further prompts are shown for the operation.
+	/// The panic message is available as the [`source()`][std::error::Error::source()] of the error, as a [`PromptPanic`].
+	PromptPanicked,
+
+	/// The credentials callback runs inside a credential helper or askpass program that is nested too deep.
+	///
+	/// This happens when a credential helper uses git itself, and git asks the same helper for credentials again.
+	/// The recursion is detected with the `AUTH_GIT2_HELPER_DEPTH` environment variable that is set for every helper process started by this crate.
+	Recursion,
+
+	/// The passphrase of an SSH key is wrong.
+	///
+	/// This is only detected for keys that are decrypted by this crate, which requires the `decrypt-ssh-keys` feature.
+	/// Other keys are decrypted by libssh2, which reports a wrong passphrase as [`ErrorKind::Other`].
+	/// The operation can be retried to prompt for the passphrase again.
+	WrongPassphrase,
+
+	/// A password was not sent, because the URL uses plain HTTP without TLS.
+	///
+	/// See [`GitAuthenticator::insecure_transport_policy()`][crate::GitAuthenticator::insecure_transport_policy()].
+	InsecureTransport,
+
+	/// Any other error.
+	Other,
+}
+
+/// The panic of a [`Prompter`][crate::Prompter] that caused an [`Error`] with [`ErrorKind::PromptPanicked`].
+#[derive(Debug, Clone)]
+pub struct PromptPanic {
+	message: String,
+}
+
+impl PromptPanic {
+	/// Get the message of the panic.
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+}
+
+impl std::fmt::Display for PromptPanic {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "the prompter panicked: {}", self.message)
+	}
+}
+
+impl std::error::Error for PromptPanic {}
+
+/// A stable, machine-readable code for the cause of an [`Error`].
+///
+/// The codes are more specific than the [`ErrorKind`], and they are derived from the error message where needed.
+/// Use them to map failures to your own documentation or telemetry without matching on error messages.
+/// The string form of a code (see [`ErrorCode::as_str()`]) will not change in future versions.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+	/// `AUTH_CANCELLED`: the user cancelled a prompt.
+	Cancelled,
+
+	/// `AUTH_PROMPT_SUPPRESSED`: the user was not prompted, because they declined a prompt for the same host recently.
+	PromptSuppressed,
+
+	/// `AUTH_PROMPT_DISABLED`: no credentials were found, and prompting for a username and password is disabled.
+	PromptDisabled,
+
+	/// `AUTH_PROMPT_UNAVAILABLE`: no credentials were found, and the user could not be prompted, for example because there is no terminal.
+	PromptUnavailable,
+
+	/// `AUTH_PROMPT_PANICKED`: a custom prompter panicked.
+	PromptPanicked,
+
+	/// `AUTH_RECURSION`: a credential helper or askpass program recursively used git to authenticate.
+	Recursion,
+
+	/// `AUTH_ASKPASS_NOT_EXECUTABLE`: the prompt failed, because the configured askpass program can not be run.
+	AskpassNotExecutable,
+
+	/// `AUTH_SSH_NO_KEYS`: the server asked for an SSH key, but no keys were found.
+	SshNoKeys,
+
+	/// `AUTH_SSH_KEY_UNUSABLE`: an SSH key could not be used, because the passphrase is wrong or libssh2 does not support its format.
+	SshKeyUnusable,
+
+	/// `AUTH_SSH_UNSUPPORTED`: the linked libgit2 was built without SSH support.
+	SshUnsupported,
+
+	/// `AUTH_HTTPS_UNSUPPORTED`: the linked libgit2 was built without HTTPS support.
+	HttpsUnsupported,
+
+	/// `AUTH_NO_CREDENTIALS`: no authentication mechanism had credentials for the URL.
+	NoCredentials,
+
+	/// `AUTH_CREDENTIALS_REJECTED`: the server rejected all offered credentials.
+	CredentialsRejected,
+
+	/// `AUTH_ACCESS_DENIED`: the server accepted the credentials, but denied access to the repository.
+	AccessDenied,
+
+	/// `AUTH_USERNAME_LOOP`: the server kept asking for a username.
+	UsernameLoop,
+
+	/// `AUTH_TOO_MANY_ATTEMPTS`: the credentials callback was called too many times.
+	TooManyAttempts,
+
+	/// `AUTH_RATE_LIMITED`: the server rate limited the operation.
+	RateLimited,
+
+	/// `AUTH_INSECURE_TRANSPORT`: a password was not sent, because the URL uses plain HTTP.
+	InsecureTransport,
+
+	/// `AUTH_OTHER`: the cause of the error is not recognized.
+	Other,
+}
+
+impl ErrorCode {
+	/// Get the stable string form of the code, like `"AUTH_SSH_NO_KEYS"`.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Cancelled => "AUTH_CANCELLED",
+			Self::PromptSuppressed => "AUTH_PROMPT_SUPPRESSED",
+			Self::PromptDisabled => "AUTH_PROMPT_DISABLED",
+			Self::PromptUnavailable => "AUTH_PROMPT_UNAVAILABLE",
+			Self::PromptPanicked => "AUTH_PROMPT_PANICKED",
+			Self::Recursion => "AUTH_RECURSION",
+			Self::AskpassNotExecutable => "AUTH_ASKPASS_NOT_EXECUTABLE",
+			Self::SshNoKeys => "AUTH_SSH_NO_KEYS",
+			Self::SshKeyUnusable => "AUTH_SSH_KEY_UNUSABLE",
+			Self::SshUnsupported => "AUTH_SSH_UNSUPPORTED",
+			Self::HttpsUnsupported => "AUTH_HTTPS_UNSUPPORTED",
+			Self::NoCredentials => "AUTH_NO_CREDENTIALS",
+			Self::CredentialsRejected => "AUTH_CREDENTIALS_REJECTED",
+			Self::AccessDenied => "AUTH_ACCESS_DENIED",
+			Self::UsernameLoop => "AUTH_USERNAME_LOOP",
+			Self::TooManyAttempts => "AUTH_TOO_MANY_ATTEMPTS",
+			Self::RateLimited => "AUTH_RATE_LIMITED",
+			Self::InsecureTransport => "AUTH_INSECURE_TRANSPORT",
+			Self::Other => "AUTH_OTHER",
+		}
+	}
+}
+
+impl std::fmt::Display for ErrorCode {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+impl Error {
+	/// Create a new error from a kind and a [`git2::Error`].
+	pub fn new(kind: ErrorKind, inner: git2::Error) -> Self {
+		let code = crate::hints::code_for(kind, &inner);
+		let hint = crate::hints::hint_for(code);
+		Self { kind, inner, label: None, retry_after: None, hint, code, panic: None }
+	}
+
+	/// Get the kind of the error.
+	pub fn kind(&self) -> ErrorKind {
+		self.kind
+	}
+
+	/// Check if the error was caused by the user cancelling a prompt.
+	///
+	/// This can be used to decide if an error should be shown to the user:
+	/// they probably do not want to see an error dialog for an action they cancelled themselves.
+	pub fn is_cancelled(&self) -> bool {
+		self.kind == ErrorKind::Cancelled
+	}
+
+	/// Get the label of the last credentials that were offered to the server before the operation failed.
+	///
+	/// This tells you which credentials were rejected if the server denied access.
+	/// The label is set with [`GitAuthenticator::with_label()`][crate::GitAuthenticator::with_label()],
+	/// or it describes the authentication mechanism if no label was set (like `"ssh-agent"` or `"credential-helper"`).
+	///
+	/// Returns `None` if no credentials were offered.
+	pub fn label(&self) -> Option<&str> {
+		self.label.as_deref()
+	}
+
+	/// Get the delay that the server asked for before retrying a rate limited operation.
+	///
+	/// libgit2 does not give access to the headers of HTTP responses,
+	/// so this is only available if the server included the delay in the error message, like `Retry-After: 30`.
+	pub fn retry_after(&self) -> Option<Duration> {
+		self.retry_after
+	}
+
+	/// Get a human-readable hint to fix the cause of the error, if it is recognized.
+	///
+	/// Hints are available for some frequent problems,
+	/// like a wrong passphrase for an SSH key, a libgit2 without SSH support, an askpass program that can not be run,
+	/// or an access token without the required scopes.
+	/// The hint is not included in the [`Display`][std::fmt::Display] output of the error,
+	/// so you can decide if and how to show it to the user.
+	pub fn hint(&self) -> Option<&str> {
+		self.hint.as_deref()
+	}
+
+	/// Get the stable, machine-readable code for the cause of the error.
+	///
+	/// See [`ErrorCode`] for the possible codes.
+	pub fn code(&self) -> ErrorCode {
+		self.code
+	}
+
+	/// Get the wrapped [`git2::Error`].
+	pub fn git_error(&self) -> &git2::Error {
+		&self.inner
+	}
+
+	/// Convert the error into the wrapped [`git2::Error`].
+	pub fn into_git_error(self) -> git2::Error {
+		self.inner
+	}
+}
+
+impl From<git2::Error> for Error {
+	fn from(inner: git2::Error) -> Self {
+		Self::new(ErrorKind::Other, inner)
+	}
+}
+
+impl From<Error> for git2::Error {
+	fn from(error: Error) -> Self {
+		error.inner
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		self.inner.fmt(f)
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		self.panic.as_deref().map(|x| x as _)
+	}
+}
+
+/// Shared record of the reason why a credentials callback gave up.
+///
+/// The credentials callback can only return a [`git2::Error`] to libgit2.
+/// The convenience functions use this to recover the [`ErrorKind`] and the label of the last offered credentials after the operation failed.
+/// It also holds the prompted credentials to approve after the operation succeeded, and the counters for telemetry.
+#[derive(Clone, Default)]
+pub(crate) struct FailureTracker {
+	kind: Rc<Cell<Option<ErrorKind>>>,
+	label: Rc<RefCell<Option<String>>>,
+	username_requested: Rc<Cell<bool>>,
+	exhausted: Rc<Cell<bool>>,
+	hint: Rc<RefCell<Option<String>>>,
+	code: Rc<Cell<Option<ErrorCode>>>,
+	approval: crate::CredentialApproval,
+	telemetry: crate::telemetry::Counters,
+	panic: Rc<RefCell<Option<PromptPanic>>>,
+	authorization: Rc<RefCell<Option<Zeroizing<String>>>>,
+}
+
+impl FailureTracker {
+	/// Create a new failure tracker.
+	pub(crate) fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a new failure tracker that adds to the telemetry counters of an operation.
+	pub(crate) fn with_telemetry(telemetry: crate::telemetry::Counters) -> Self {
+		Self {
+			telemetry,
+			..Self::default()
+		}
+	}
+
+	/// Record a failure of the credentials callback and make a [`git2::Error`] to return to libgit2.
+	///
+	/// This clears the code that was recorded for an earlier failure, since the code of the new failure is derived from its kind.
+	pub(crate) fn fail(&self, kind: ErrorKind, message: &str) -> git2::Error {
+		self.kind.set(Some(kind));
+		self.code.set(None);
+		git2::Error::new(git2::ErrorCode::User, git2::ErrorClass::Callback, message)
+	}
+
+	/// Record that the prompter panicked and make a [`git2::Error`] to return to libgit2.
+	pub(crate) fn panicked(&self, message: &str) -> git2::Error {
+		let panic = PromptPanic { message: message.into() };
+		let error = self.fail(ErrorKind::PromptPanicked, &panic.to_string());
+		*self.panic.borrow_mut() = Some(panic);
+		error
+	}
+
+	/// Record the label of the credentials that the credentials callback returned to libgit2.
+	pub(crate) fn offered(&self, label: String) {
+		*self.label.borrow_mut() = Some(label);
+		self.telemetry.offered();
+	}
+
+	/// Record a hint to fix the cause of a failure, that can not be derived from the error message.
+	#[cfg(any(feature = "prompts", feature = "decrypt-ssh-keys"))]
+	pub(crate) fn hint(&self, hint: String) {
+		*self.hint.borrow_mut() = Some(hint);
+	}
+
+	/// Record the code for the cause of a failure, if it can not be derived from the error message.
+	pub(crate) fn code(&self, code: ErrorCode) {
+		self.code.set(Some(code));
+	}
+
+	/// Get the handle to approve the credentials that the user entered in a prompt.
+	pub(crate) fn approval(&self) -> &crate::CredentialApproval {
+		&self.approval
+	}
+
+	/// Ask to run the operation again with an `Authorization` header, and make a [`git2::Error`] to stop the current run.
+	pub(crate) fn retry_with_authorization(&self, authorization: Zeroizing<String>) -> git2::Error {
+		*self.authorization.borrow_mut() = Some(authorization);
+		self.fail(ErrorKind::Other, "retrying with the credential from the credential helper")
+	}
+
+	/// Take the `Authorization` header to run the operation again with, if the credentials callback asked for it.
+	pub(crate) fn take_authorization(&self) -> Option<Zeroizing<String>> {
+		self.authorization.borrow_mut().take()
+	}
+
+	/// Get the telemetry counters of the operation.
+	pub(crate) fn telemetry(&self) -> &crate::telemetry::Counters {
+		&self.telemetry
+	}
+
+	/// Check if the credentials callback offered any credentials to libgit2.
+	pub(crate) fn has_offered(&self) -> bool {
+		self.label.borrow().is_some()
+	}
+
+	/// Record that libgit2 asked the credentials callback for a username.
+	pub(crate) fn username_requested(&self) {
+		self.username_requested.set(true);
+	}
+
+	/// Record that the credentials callback had nothing left to try.
+	pub(crate) fn exhausted(&self) {
+		self.exhausted.set(true);
+	}
+
+	/// Check if the operation may succeed with a different SSH username.
+	///
+	/// This is the case if libgit2 asked for a username, and the credentials callback tried everything it had for that username.
+	pub(crate) fn username_rejected(&self) -> bool {
+		self.username_requested.get() && self.exhausted.get() && self.kind.get().is_none()
+	}
+
+	/// Wrap the [`git2::Error`] of a failed operation in an [`Error`] with the recorded kind, label, hint and code.
+	///
+	/// If the credentials callback did not record a failure, the error is checked for rate limiting by the server.
+	pub(crate) fn wrap(&self, error: git2::Error) -> Error {
+		let (kind, retry_after) = match self.kind.get() {
+			Some(kind) => (kind, None),
+			None if crate::rate_limit::is_rate_limited(&error) => (ErrorKind::RateLimited, crate::rate_limit::retry_after(error.message())),
+			None => (ErrorKind::Other, None),
+		};
+		let error = Error::new(kind, error);
+		Error {
+			label: self.label.borrow().clone(),
+			retry_after,
+			hint: self.hint.borrow().clone().or(error.hint),
+			code: self.code.get().unwrap_or(error.code),
+			panic: self.panic.borrow().clone().map(Box::new),
+			..error
+		}
+	}
+}
@@ -0,0 +1,86 @@
+/// What [`GitAuthenticator::forget_host()`][crate::GitAuthenticator::forget_host()] removes for a host.
+///
+/// Use [`Self::memory()`] to remove only what the authenticator remembers in memory,
+/// or [`Self::all()`] to also delete stored credentials.
+///
+/// # Example
+/// ```
+/// # use auth_git2::ForgetScope;
+/// // Forget everything, except the credentials stored by the git credential helpers.
+/// let scope = ForgetScope::all().credential_helpers(false);
+/// # let _ = scope;
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ForgetScope {
+	/// Remove cached credentials and tokens.
+	pub(crate) cached_credentials: bool,
+
+	/// Clear prompt cool-downs.
+	pub(crate) prompt_cooldowns: bool,
+
+	/// Delete entries from the OS keyring.
+	pub(crate) keyring: bool,
+
+	/// Erase credentials from the git credential helpers.
+	pub(crate) credential_helpers: bool,
+}
+
+impl ForgetScope {
+	/// Forget only what the authenticator remembers in memory: cached credentials and prompt cool-downs.
+	pub fn memory() -> Self {
+		Self {
+			cached_credentials: true,
+			prompt_cooldowns: true,
+			keyring: false,
+			credential_helpers: false,
+		}
+	}
+
+	/// Forget everything: cached credentials, prompt cool-downs, entries in the OS keyring and credentials stored by the git credential helpers.
+	pub fn all() -> Self {
+		Self {
+			cached_credentials: true,
+			prompt_cooldowns: true,
+			keyring: true,
+			credential_helpers: true,
+		}
+	}
+
+	/// Configure if cached credentials and tokens should be removed.
+	///
+	/// This removes the credentials cached for [`GitAuthenticator::add_refreshing_credentials()`][crate::GitAuthenticator::add_refreshing_credentials()],
+	/// the installation access tokens cached for GitHub Apps,
+	/// the credentials remembered with [`GitAuthenticator::remember_prompted_credentials()`][crate::GitAuthenticator::remember_prompted_credentials()]
+	/// and the credentials cached with [`GitAuthenticator::cache_helper_credentials()`][crate::GitAuthenticator::cache_helper_credentials()].
+	pub fn cached_credentials(mut self, enable: bool) -> Self {
+		self.cached_credentials = enable;
+		self
+	}
+
+	/// Configure if prompt cool-downs should be cleared (see [`GitAuthenticator::prompt_cooldown()`][crate::GitAuthenticator::prompt_cooldown()]).
+	pub fn prompt_cooldowns(mut self, enable: bool) -> Self {
+		self.prompt_cooldowns = enable;
+		self
+	}
+
+	/// Configure if entries should be deleted from the OS keyring.
+	///
+	/// This has no effect without the `keyring` feature.
+	pub fn keyring(mut self, enable: bool) -> Self {
+		self.keyring = enable;
+		self
+	}
+
+	/// Configure if credentials should be erased from the git credential helpers.
+	pub fn credential_helpers(mut self, enable: bool) -> Self {
+		self.credential_helpers = enable;
+		self
+	}
+}
+
+impl Default for ForgetScope {
+	/// Forget only what the authenticator remembers in memory, see [`Self::memory()`].
+	fn default() -> Self {
+		Self::memory()
+	}
+}
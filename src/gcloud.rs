@@ -0,0 +1,115 @@
+//! Access tokens for Google Cloud Source Repositories from the Google Cloud CLI (`gcloud`).
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::{PlaintextCredentials, PlannedAttempt};
+
+/// The host of Google Cloud Source Repositories.
+const SOURCE_REPOSITORIES_HOST: &str = "source.developers.google.com";
+
+/// The username to use with an access token if the URL does not contain a username.
+///
+/// Cloud Source Repositories ignores the username when an access token is used as password.
+const TOKEN_USERNAME: &str = "oauth2accesstoken";
+
+/// Check if a host is the host of Google Cloud Source Repositories.
+pub(crate) fn is_source_repositories_host(host: &str) -> bool {
+	let host = host.rsplit_once(':')
+		.filter(|(_, port)| port.bytes().all(|b| b.is_ascii_digit()))
+		.map(|(host, _)| host)
+		.unwrap_or(host);
+	host.eq_ignore_ascii_case(SOURCE_REPOSITORIES_HOST)
+}
+
+/// Get credentials for a URL from the `gcloud` CLI.
+///
+/// This first asks for an access token of the active `gcloud` account,
+/// and falls back to an access token from the application default credentials.
+///
+/// The caller checks that the (canonical) host of the URL is a Cloud Source Repositories host with [`is_source_repositories_host()`].
+///
+/// Returns `None` if the URL is not an HTTPS URL, or if no access token could be obtained.
+pub(crate) fn get_credentials(url: &str, username: Option<&str>) -> Option<PlaintextCredentials> {
+	if !url.starts_with("https://") {
+		return None;
+	}
+	let password = get_token(&["auth", "print-access-token"])
+		.or_else(|| get_token(&["auth", "application-default", "print-access-token"]))?;
+	Some(PlaintextCredentials {
+		username: username.unwrap_or(TOKEN_USERNAME).into(),
+		password,
+	})
+}
+
+/// Run `gcloud` with the given arguments to get an access token.
+fn get_token(args: &[&str]) -> Option<String> {
+	let output = std::process::Command::new(gcloud_program())
+		.args(args)
+		.stdin(std::process::Stdio::null())
+		.stderr(std::process::Stdio::null())
+		.output()
+		.map_err(|e| debug!("Failed to run gcloud: {e}"))
+		.ok()?;
+	if !output.status.success() {
+		debug!("gcloud {} failed: {}", args.join(" "), output.status);
+		return None;
+	}
+	let token = String::from_utf8(output.stdout)
+		.map_err(|_| warn!("Invalid UTF-8 in the output of gcloud {}", args.join(" ")))
+		.ok()?;
+	let token = token.trim();
+	if token.is_empty() {
+		debug!("gcloud {} did not print an access token", args.join(" "));
+		return None;
+	}
+	Some(token.into())
+}
+
+/// Get the name of the `gcloud` program.
+///
+/// On Windows, `gcloud` is a batch file, which is not found without the extension.
+fn gcloud_program() -> &'static str {
+	if cfg!(windows) {
+		"gcloud.cmd"
+	} else {
+		"gcloud"
+	}
+}
+
+/// The `gcloud` CLI as source of credentials.
+pub(crate) struct Gcloud;
+
+impl CredentialSource for Gcloud {
+	fn mechanism(&self) -> &'static str {
+		"gcloud"
+	}
+
+	fn label(&self) -> String {
+		"gcloud".into()
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::Gcloud
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		Ok(get_credentials(request.url, request.username))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_is_source_repositories_host() {
+		assert!(is_source_repositories_host("source.developers.google.com"));
+		assert!(is_source_repositories_host("Source.Developers.Google.com"));
+		assert!(is_source_repositories_host("source.developers.google.com:443"));
+		assert!(!is_source_repositories_host("developers.google.com"));
+		assert!(!is_source_repositories_host("github.com"));
+	}
+}
@@ -0,0 +1,106 @@
+//! Tokens from the GitHub CLI (`gh`).
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::{PlaintextCredentials, PlannedAttempt};
+
+/// The username to use with a token if the URL does not contain a username.
+///
+/// GitHub accepts any username with a token, this is the same username as used by GitHub Actions.
+const TOKEN_USERNAME: &str = "x-access-token";
+
+/// Check if a host is a GitHub host that the `gh` CLI may have a token for.
+///
+/// This is `github.com`, GitHub Enterprise Cloud with data residency (`*.ghe.com`)
+/// and the host in the `GH_HOST` environment variable (for GitHub Enterprise Server).
+///
+/// The `env` function gets the value of an environment variable.
+pub(crate) fn is_github_host(host: &str, env: impl Fn(&str) -> Option<String>) -> bool {
+	let host = host.to_ascii_lowercase();
+	if host == "github.com" || host.ends_with(".ghe.com") {
+		return true;
+	}
+	match env("GH_HOST") {
+		Some(gh_host) => gh_host.eq_ignore_ascii_case(&host),
+		None => false,
+	}
+}
+
+/// Get credentials for a URL from the `gh` CLI.
+///
+/// The caller checks that the (canonical) host of the URL is a GitHub host with [`is_github_host()`],
+/// but `gh` is asked for a token for the original host.
+///
+/// Returns `None` if the URL is not an HTTPS URL, or if `gh` has no token for the host.
+pub(crate) fn get_credentials(url: &str, username: Option<&str>) -> Option<PlaintextCredentials> {
+	if !url.starts_with("https://") {
+		return None;
+	}
+	let host = crate::domain_from_url(url)?;
+	let password = get_token(host)?;
+	Some(PlaintextCredentials {
+		username: username.unwrap_or(TOKEN_USERNAME).into(),
+		password,
+	})
+}
+
+/// Run `gh auth token` to get the token for a host.
+fn get_token(host: &str) -> Option<String> {
+	let output = std::process::Command::new("gh")
+		.args(["auth", "token", "--hostname", host])
+		.stdin(std::process::Stdio::null())
+		.stderr(std::process::Stdio::null())
+		.output()
+		.map_err(|e| debug!("Failed to run gh: {e}"))
+		.ok()?;
+	if !output.status.success() {
+		debug!("gh has no token for {host:?}");
+		return None;
+	}
+	let token = String::from_utf8(output.stdout)
+		.map_err(|_| warn!("Invalid UTF-8 in the output of gh auth token"))
+		.ok()?;
+	let token = token.trim();
+	if token.is_empty() {
+		return None;
+	}
+	Some(token.into())
+}
+
+/// The `gh` CLI as source of credentials.
+pub(crate) struct GhCli;
+
+impl CredentialSource for GhCli {
+	fn mechanism(&self) -> &'static str {
+		"gh-cli"
+	}
+
+	fn label(&self) -> String {
+		"gh-cli".into()
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::GhCli
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		Ok(get_credentials(request.url, request.username))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_is_github_host() {
+		assert!(is_github_host("github.com", |_| None));
+		assert!(is_github_host("GitHub.com", |_| None));
+		assert!(is_github_host("octocorp.ghe.com", |_| None));
+		assert!(!is_github_host("gitlab.com", |_| None));
+		assert!(!is_github_host("github.com.example.com", |_| None));
+	}
+}
@@ -0,0 +1,391 @@
+//! Installation access tokens for GitHub Apps.
+//!
+//! A GitHub App authenticates as itself with a short-lived JWT signed with its private key.
+//! The JWT is exchanged for an installation access token, which can be used as password for git over HTTPS.
+//! Installation access tokens expire after one hour, so they are cached until shortly before they expire.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use zeroize::Zeroize;
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::{PlaintextCredentials, PlannedAttempt};
+
+/// The username to use with an installation access token.
+const TOKEN_USERNAME: &str = "x-access-token";
+
+/// The version of the GitHub REST API to request.
+const API_VERSION: &str = "2022-11-28";
+
+/// Refresh cached tokens if they expire within this time.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// A GitHub App to get installation access tokens for.
+///
+/// Use [`GitAuthenticator::add_github_app()`][crate::GitAuthenticator::add_github_app()] to use the app for a domain.
+///
+/// The installation access token is cached until shortly before it expires.
+/// The cache is shared between all clones of the app, and cached tokens are overwritten with zeros when they are dropped.
+///
+/// This is only available with the `github-app` feature.
+#[derive(Clone)]
+pub struct GitHubApp {
+	/// The app ID or client ID of the app.
+	app_id: String,
+
+	/// The private key of the app.
+	key: jsonwebtoken::EncodingKey,
+
+	/// The ID of the installation to get a token for.
+	///
+	/// If this is `None`, the installation is looked up for the repository.
+	installation_id: Option<u64>,
+
+	/// The base URL of the REST API, overriding the default for the host.
+	api_url: Option<String>,
+
+	/// Cached tokens and installation IDs.
+	cache: Arc<Mutex<Cache>>,
+}
+
+/// Cached tokens and installation IDs of a [`GitHubApp`].
+#[derive(Default)]
+struct Cache {
+	/// Installation IDs by API URL and account name.
+	installations: BTreeMap<(String, String), u64>,
+
+	/// Installation access tokens by API URL and installation ID.
+	tokens: BTreeMap<(String, u64), InstallationToken>,
+}
+
+/// An installation access token.
+#[derive(Clone, Eq, PartialEq)]
+pub(crate) struct InstallationToken {
+	/// The token.
+	pub token: String,
+
+	/// The time at which the token expires.
+	pub expires_at: SystemTime,
+}
+
+impl std::fmt::Debug for GitHubApp {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("GitHubApp")
+			.field("app_id", &self.app_id)
+			.field("installation_id", &self.installation_id)
+			.field("api_url", &self.api_url)
+			.finish_non_exhaustive()
+	}
+}
+
+impl Drop for InstallationToken {
+	fn drop(&mut self) {
+		self.token.zeroize();
+	}
+}
+
+impl std::fmt::Debug for InstallationToken {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("InstallationToken")
+			.field("expires_at", &self.expires_at)
+			.finish_non_exhaustive()
+	}
+}
+
+impl GitHubApp {
+	/// Create a GitHub App from its app ID (or client ID) and its private key in PEM format.
+	///
+	/// The private key is the key that you can generate in the settings of the app.
+	/// Returns an error if the private key is not a valid RSA private key.
+	///
+	/// By default, the installation of the app is looked up for the owner of each repository.
+	/// Use [`Self::installation_id()`] to always use the same installation.
+	pub fn new(app_id: impl Into<String>, private_key_pem: &[u8]) -> Result<Self, jsonwebtoken::errors::Error> {
+		Ok(Self {
+			app_id: app_id.into(),
+			key: jsonwebtoken::EncodingKey::from_rsa_pem(private_key_pem)?,
+			installation_id: None,
+			api_url: None,
+			cache: Default::default(),
+		})
+	}
+
+	/// Set the ID of the installation to get tokens for.
+	pub fn installation_id(mut self, installation_id: u64) -> Self {
+		self.installation_id = Some(installation_id);
+		self
+	}
+
+	/// Set the base URL of the REST API.
+	///
+	/// By default, `https://api.github.com` is used for `github.com`,
+	/// and `https://<host>/api/v3` for other hosts (GitHub Enterprise Server).
+	pub fn api_url(mut self, api_url: impl Into<String>) -> Self {
+		self.api_url = Some(api_url.into());
+		self
+	}
+
+	/// Get the app ID of the app.
+	pub fn app_id(&self) -> &str {
+		&self.app_id
+	}
+
+	/// Get the base URL of the REST API for a host.
+	fn api_url_for(&self, host: &str) -> String {
+		match &self.api_url {
+			Some(api_url) => api_url.trim_end_matches('/').into(),
+			None if host.eq_ignore_ascii_case("github.com") => "https://api.github.com".into(),
+			None => format!("https://{host}/api/v3"),
+		}
+	}
+
+	/// Make a JWT to authenticate as the app.
+	///
+	/// The JWT is valid from one minute before `now` to allow for clock drift,
+	/// and it expires after nine minutes, just under the limit of ten minutes.
+	fn make_jwt(&self, now: SystemTime) -> Result<String, String> {
+		let now = now.duration_since(SystemTime::UNIX_EPOCH)
+			.map_err(|_| "system time is before the unix epoch")?
+			.as_secs();
+		let claims = serde_json::json!({
+			"iat": now - 60,
+			"exp": now + 9 * 60,
+			"iss": self.app_id,
+		});
+		jsonwebtoken::encode(&jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256), &claims, &self.key)
+			.map_err(|e| format!("failed to sign JWT: {e}"))
+	}
+
+	/// Get an installation access token for a repository, from the cache if possible.
+	fn get_token(&self, api_url: &str, owner: &str, repo: &str, now: SystemTime) -> Result<String, String> {
+		let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+		let installation_id = match self.installation_id {
+			Some(x) => x,
+			None => match cache.installations.get(&(api_url.into(), owner.into())) {
+				Some(x) => *x,
+				None => {
+					let jwt = self.make_jwt(now)?;
+					let body = api_request(ureq::get(&format!("{api_url}/repos/{owner}/{repo}/installation")), &jwt)?;
+					let installation_id = parse_installation(&body)?;
+					cache.installations.insert((api_url.into(), owner.into()), installation_id);
+					installation_id
+				},
+			},
+		};
+
+		let key = (api_url.to_owned(), installation_id);
+		if let Some(token) = cache.tokens.get(&key) {
+			if now + EXPIRY_MARGIN < token.expires_at {
+				debug!("Using cached GitHub App installation token for installation {installation_id}");
+				return Ok(token.token.clone());
+			}
+		}
+
+		let jwt = self.make_jwt(now)?;
+		let body = api_request(ureq::post(&format!("{api_url}/app/installations/{installation_id}/access_tokens")), &jwt)?;
+		let token = parse_installation_token(&body)?;
+		let password = token.token.clone();
+		cache.tokens.insert(key, token);
+		Ok(password)
+	}
+
+	/// Remove the cached installation IDs and tokens for all hosts that match a host pattern.
+	pub(crate) fn forget_host(&self, pattern: &str) {
+		let pattern_api_url = self.api_url_for(pattern);
+		let matches = |api_url: &str| {
+			api_url == pattern_api_url
+				|| crate::domain_from_url(api_url).is_some_and(|host| crate::credential_helper::host_matches(pattern, host))
+		};
+		let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+		cache.installations.retain(|(api_url, _), _| !matches(api_url));
+		cache.tokens.retain(|(api_url, _), _| !matches(api_url));
+	}
+
+	/// Remove all cached installation access tokens.
+	pub(crate) fn purge(&self) {
+		let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+		cache.tokens.clear();
+	}
+}
+
+/// Get credentials for a URL with a GitHub App.
+///
+/// Returns `None` if the URL is not an HTTPS URL of a repository, or if no token could be obtained.
+pub(crate) fn get_credentials(app: &GitHubApp, url: &str, username: Option<&str>, now: SystemTime) -> Option<PlaintextCredentials> {
+	let (host, owner, repo) = repository_from_url(url)?;
+	let api_url = app.api_url_for(host);
+	let password = app.get_token(&api_url, owner, repo, now)
+		.map_err(|e| warn!("Failed to get an installation access token for GitHub App {:?}: {e}", app.app_id))
+		.ok()?;
+	Some(PlaintextCredentials {
+		username: username.unwrap_or(TOKEN_USERNAME).into(),
+		password,
+	})
+}
+
+/// Send a request to the GitHub REST API, authenticated as the app.
+fn api_request(request: ureq::Request, jwt: &str) -> Result<String, String> {
+	let response = request
+		.set("Accept", "application/vnd.github+json")
+		.set("Authorization", &format!("Bearer {jwt}"))
+		.set("X-GitHub-Api-Version", API_VERSION)
+		.call();
+	match response {
+		Ok(response) => response.into_string().map_err(|e| e.to_string()),
+		Err(ureq::Error::Status(code, response)) => {
+			let body = response.into_string().unwrap_or_default();
+			let message = serde_json::from_str::<serde_json::Value>(&body).ok()
+				.and_then(|x| x.get("message")?.as_str().map(String::from))
+				.unwrap_or_default();
+			Err(format!("GitHub API returned status {code}: {message}"))
+		},
+		Err(e) => Err(e.to_string()),
+	}
+}
+
+/// Get the host, owner and repository name from an HTTPS URL.
+pub(crate) fn repository_from_url(url: &str) -> Option<(&str, &str, &str)> {
+	let rest = url.strip_prefix("https://")?;
+	let (authority, path) = rest.split_once('/')?;
+	let host = authority.rsplit_once('@').map(|(_, host)| host).unwrap_or(authority);
+	let mut parts = path.split('/').filter(|x| !x.is_empty());
+	let owner = parts.next()?;
+	let repo = parts.next()?;
+	let repo = repo.strip_suffix(".git").unwrap_or(repo);
+	Some((host, owner, repo))
+}
+
+/// Parse the response of the endpoint to get the installation of a repository.
+pub(crate) fn parse_installation(body: &str) -> Result<u64, String> {
+	let value: serde_json::Value = serde_json::from_str(body).map_err(|e| format!("invalid JSON response: {e}"))?;
+	value.get("id")
+		.and_then(|x| x.as_u64())
+		.ok_or_else(|| "missing id in response".into())
+}
+
+/// Parse the response of the endpoint to create an installation access token.
+pub(crate) fn parse_installation_token(body: &str) -> Result<InstallationToken, String> {
+	let value: serde_json::Value = serde_json::from_str(body).map_err(|e| format!("invalid JSON response: {e}"))?;
+	let token = value.get("token")
+		.and_then(|x| x.as_str())
+		.ok_or("missing token in response")?;
+	let expires_at = value.get("expires_at")
+		.and_then(|x| x.as_str())
+		.ok_or("missing expires_at in response")?;
+	let expires_at = parse_timestamp(expires_at)
+		.ok_or_else(|| format!("invalid expires_at in response: {expires_at:?}"))?;
+	Ok(InstallationToken {
+		token: token.into(),
+		expires_at,
+	})
+}
+
+/// Parse a UTC timestamp in the format `YYYY-MM-DDTHH:MM:SSZ`.
+pub(crate) fn parse_timestamp(timestamp: &str) -> Option<SystemTime> {
+	let (date, time) = timestamp.strip_suffix('Z')?.split_once('T')?;
+	let mut date = date.splitn(3, '-').map(|x| x.parse::<u64>().ok());
+	let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+	let mut time = time.splitn(3, ':').map(|x| x.parse::<u64>().ok());
+	let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+	if !(1970..10000).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+		return None;
+	}
+
+	// Days since the unix epoch for a date in the proleptic Gregorian calendar.
+	let (year, month) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+	let era = year / 400;
+	let year_of_era = year % 400;
+	let day_of_year = (153 * month + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	let days = era * 146_097 + day_of_era - 719_468;
+
+	let seconds = days * 86_400 + hour * 3600 + minute * 60 + second;
+	Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+impl CredentialSource for GitHubApp {
+	fn mechanism(&self) -> &'static str {
+		"github-app"
+	}
+
+	fn label(&self) -> String {
+		format!("github-app:{}", self.app_id())
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::GitHubApp {
+			app_id: self.app_id().into(),
+		}
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		Ok(get_credentials(self, request.url, request.username, request.authenticator.clock.system_now()))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_repository_from_url() {
+		assert!(let Some(("github.com", "de-vri-es", "auth-git2-rs")) = repository_from_url("https://github.com/de-vri-es/auth-git2-rs.git"));
+		assert!(let Some(("ghe.example.com", "org", "repo")) = repository_from_url("https://x-access-token@ghe.example.com/org/repo"));
+		assert!(let None = repository_from_url("https://github.com/de-vri-es"));
+		assert!(let None = repository_from_url("git@github.com:de-vri-es/auth-git2-rs.git"));
+	}
+
+	#[test]
+	fn test_cached_token() {
+		let app = GitHubApp {
+			app_id: "12345".into(),
+			key: jsonwebtoken::EncodingKey::from_secret(b"not-used"),
+			installation_id: Some(42),
+			api_url: None,
+			cache: Default::default(),
+		};
+		let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+		app.cache.lock().unwrap().tokens.insert(("https://api.github.com".into(), 42), InstallationToken {
+			token: "ghs_cached".into(),
+			expires_at: now + Duration::from_secs(3600),
+		});
+		let_assert!(Some(credentials) = get_credentials(&app, "https://github.com/org/repo.git", None, now));
+		assert!(credentials.username == "x-access-token");
+		assert!(credentials.password == "ghs_cached");
+		assert!(app.api_url_for("ghe.example.com") == "https://ghe.example.com/api/v3");
+
+		app.forget_host("*.example.com");
+		assert!(app.cache.lock().unwrap().tokens.len() == 1);
+		app.forget_host("github.com");
+		assert!(app.cache.lock().unwrap().tokens.is_empty());
+
+		app.clone().purge();
+		assert!(app.cache.lock().unwrap().tokens.is_empty());
+	}
+
+	#[test]
+	fn test_parse_timestamp() {
+		assert!(parse_timestamp("1970-01-01T00:00:00Z") == Some(SystemTime::UNIX_EPOCH));
+		assert!(parse_timestamp("2016-07-11T22:14:10Z") == Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_468_275_250)));
+		assert!(parse_timestamp("2024-02-29T12:00:00Z") == Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_208_000)));
+		assert!(let None = parse_timestamp("2016-07-11 22:14:10"));
+		assert!(let None = parse_timestamp("2016-13-11T22:14:10Z"));
+	}
+
+	#[test]
+	fn test_parse_responses() {
+		assert!(let Ok(42) = parse_installation(r#"{"id": 42, "account": {"login": "octocat"}}"#));
+		assert!(let Err(_) = parse_installation(r#"{"message": "Not Found"}"#));
+
+		let_assert!(Ok(token) = parse_installation_token(r#"{"token": "ghs_16C7e42F292c", "expires_at": "2016-07-11T22:14:10Z"}"#));
+		assert!(token.token == "ghs_16C7e42F292c");
+		assert!(token.expires_at == SystemTime::UNIX_EPOCH + Duration::from_secs(1_468_275_250));
+		assert!(!format!("{token:?}").contains("ghs_"));
+		assert!(let Err(_) = parse_installation_token(r#"{"token": "ghs_16C7e42F292c"}"#));
+	}
+}
@@ -0,0 +1,118 @@
+//! Tokens from the GitLab CLI (`glab`).
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::{PlaintextCredentials, PlannedAttempt};
+
+/// The username to use with a token if the URL does not contain a username.
+///
+/// GitLab requires this username for OAuth tokens, and accepts any username for personal access tokens.
+const TOKEN_USERNAME: &str = "oauth2";
+
+/// Check if a host is a GitLab host that the `glab` CLI may have a token for.
+///
+/// This is `gitlab.com`, hosts with a `gitlab.` prefix,
+/// and the host in the `GITLAB_HOST` or `GL_HOST` environment variable (for self-hosted instances).
+///
+/// The `env` function gets the value of an environment variable.
+pub(crate) fn is_gitlab_host(host: &str, env: impl Fn(&str) -> Option<String>) -> bool {
+	let host = host.to_ascii_lowercase();
+	if host == "gitlab.com" || host.starts_with("gitlab.") {
+		return true;
+	}
+	["GITLAB_HOST", "GL_HOST"].iter()
+		.filter_map(|name| env(name))
+		.any(|gitlab_host| strip_scheme(&gitlab_host).eq_ignore_ascii_case(&host))
+}
+
+/// Strip the scheme and trailing slash from a host as configured in `GITLAB_HOST`.
+fn strip_scheme(host: &str) -> &str {
+	let host = host.split_once("://").map(|(_, host)| host).unwrap_or(host);
+	host.trim_end_matches('/')
+}
+
+/// Get credentials for a URL from the `glab` CLI.
+///
+/// The caller checks that the (canonical) host of the URL is a GitLab host with [`is_gitlab_host()`],
+/// but `glab` is asked for a token for the original host.
+///
+/// Returns `None` if the URL is not an HTTPS URL, or if `glab` has no token for the host.
+pub(crate) fn get_credentials(url: &str, username: Option<&str>) -> Option<PlaintextCredentials> {
+	if !url.starts_with("https://") {
+		return None;
+	}
+	let host = crate::domain_from_url(url)?;
+	let password = get_token(host)?;
+	Some(PlaintextCredentials {
+		username: username.unwrap_or(TOKEN_USERNAME).into(),
+		password,
+	})
+}
+
+/// Run `glab config get token` to get the token for a host.
+fn get_token(host: &str) -> Option<String> {
+	let output = std::process::Command::new("glab")
+		.args(["config", "get", "token", "--host", host])
+		.stdin(std::process::Stdio::null())
+		.stderr(std::process::Stdio::null())
+		.output()
+		.map_err(|e| debug!("Failed to run glab: {e}"))
+		.ok()?;
+	if !output.status.success() {
+		debug!("glab has no token for {host:?}");
+		return None;
+	}
+	let token = String::from_utf8(output.stdout)
+		.map_err(|_| warn!("Invalid UTF-8 in the output of glab config get token"))
+		.ok()?;
+	let token = token.trim();
+	if token.is_empty() {
+		debug!("glab has no token for {host:?}");
+		return None;
+	}
+	Some(token.into())
+}
+
+/// The `glab` CLI as source of credentials.
+pub(crate) struct GlabCli;
+
+impl CredentialSource for GlabCli {
+	fn mechanism(&self) -> &'static str {
+		"glab-cli"
+	}
+
+	fn label(&self) -> String {
+		"glab-cli".into()
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::GlabCli
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		Ok(get_credentials(request.url, request.username))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_is_gitlab_host() {
+		assert!(is_gitlab_host("gitlab.com", |_| None));
+		assert!(is_gitlab_host("GitLab.com", |_| None));
+		assert!(is_gitlab_host("gitlab.example.com", |_| None));
+		assert!(!is_gitlab_host("github.com", |_| None));
+		assert!(!is_gitlab_host("notgitlab.com", |_| None));
+	}
+
+	#[test]
+	fn test_strip_scheme() {
+		assert!(strip_scheme("https://git.example.com/") == "git.example.com");
+		assert!(strip_scheme("git.example.com") == "git.example.com");
+	}
+}
@@ -0,0 +1,111 @@
+//! Error codes and human-readable hints for common causes of authentication failures.
+
+use crate::{ErrorCode, ErrorKind};
+
+/// Get the code for a failed operation, based on the kind of error and the error message.
+pub(crate) fn code_for(kind: ErrorKind, error: &git2::Error) -> ErrorCode {
+	let message = error.message().to_ascii_lowercase();
+	match kind {
+		ErrorKind::Cancelled => ErrorCode::Cancelled,
+		ErrorKind::PromptSuppressed => ErrorCode::PromptSuppressed,
+		ErrorKind::UsernameLoop => ErrorCode::UsernameLoop,
+		ErrorKind::TooManyAttempts => ErrorCode::TooManyAttempts,
+		ErrorKind::RateLimited => ErrorCode::RateLimited,
+		ErrorKind::PromptPanicked => ErrorCode::PromptPanicked,
+		ErrorKind::Recursion => ErrorCode::Recursion,
+		ErrorKind::WrongPassphrase => ErrorCode::SshKeyUnusable,
+		ErrorKind::InsecureTransport => ErrorCode::InsecureTransport,
+		ErrorKind::Unsupported => unsupported_code(&message),
+		ErrorKind::Other => {
+			if message.contains("unsupported url protocol") {
+				unsupported_code(&message)
+			} else if message.contains("wrong passphrase") || message.contains("unable to extract public key from private key") {
+				ErrorCode::SshKeyUnusable
+			} else if is_access_denied(&message) {
+				ErrorCode::AccessDenied
+			} else {
+				ErrorCode::Other
+			}
+		},
+	}
+}
+
+/// Get the code for an unsupported transport, based on the error message.
+fn unsupported_code(message: &str) -> ErrorCode {
+	if message.contains("https") {
+		ErrorCode::HttpsUnsupported
+	} else {
+		ErrorCode::SshUnsupported
+	}
+}
+
+/// Get a hint to fix a failed operation, based on the code of the error.
+pub(crate) fn hint_for(code: ErrorCode) -> Option<String> {
+	let hint = match code {
+		ErrorCode::HttpsUnsupported => "libgit2 was built without HTTPS support: enable the `https` feature of the `git2` crate",
+		ErrorCode::SshUnsupported => "libgit2 was built without SSH support: enable the `ssh` feature of the `git2` crate, or use an HTTPS URL",
+		ErrorCode::SshKeyUnusable => {
+			"the passphrase of the SSH key is wrong, or libssh2 does not support the format of the key: \
+			check the passphrase, or convert the key with `ssh-keygen -p -m PEM -f <key>`"
+		},
+		ErrorCode::AccessDenied => {
+			"the server accepted the credentials, but denied access: \
+			if you use an access token, check that it has the required scopes (for example `repo` on GitHub or `write_repository` on GitLab)"
+		},
+		ErrorCode::Recursion => "a credential helper or askpass program runs git, which asks the same helper for credentials again: make the helper use a different credential source",
+		ErrorCode::RateLimited => "the server is rate limiting requests: try again later, or enable retries with `GitAuthenticator::rate_limit_retries()`",
+		ErrorCode::InsecureTransport => {
+			"the URL uses plain HTTP, so the password would be sent without encryption: \
+			use an HTTPS URL, or allow the host with `GitAuthenticator::allow_insecure_transport()`"
+		},
+		_ => return None,
+	};
+	Some(hint.into())
+}
+
+/// Get a hint for a configured askpass program that can not be run.
+#[cfg(feature = "prompts")]
+pub(crate) fn askpass_not_executable(program: &std::path::Path) -> String {
+	format!("the askpass program {} does not exist or is not executable: fix `core.askPass` or the `GIT_ASKPASS` or `SSH_ASKPASS` environment variables", program.display())
+}
+
+/// Get a hint for an SSH key that was decrypted with a wrong passphrase.
+#[cfg(feature = "decrypt-ssh-keys")]
+pub(crate) fn wrong_passphrase(private_key: &std::path::Path) -> String {
+	format!("the passphrase of the SSH key {} is wrong: try again, or check the passphrase with `ssh-keygen -y -f <key>`", private_key.display())
+}
+
+/// Check if an error message says that the server denied access for otherwise valid credentials.
+fn is_access_denied(message: &str) -> bool {
+	message.contains("status code: 403")
+		|| message.contains("returned error: 403")
+		|| message.contains("insufficient_scope")
+		|| message.contains("insufficient scope")
+		|| (message.contains("permission to") && message.contains("denied to"))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_code_for() {
+		let error = |message| git2::Error::new(git2::ErrorCode::Auth, git2::ErrorClass::Ssh, message);
+		let code = code_for(ErrorKind::Other, &error("Failed to authenticate SSH session: Unable to extract public key from private key file: Wrong passphrase or invalid/unrecognized private key file format"));
+		assert!(code == ErrorCode::SshKeyUnusable);
+		let_assert!(Some(hint) = hint_for(code));
+		assert!(hint.contains("passphrase"));
+		let code = code_for(ErrorKind::Unsupported, &error("can not authenticate to ssh://example.com/repo: libgit2 was built without SSH support"));
+		assert!(code == ErrorCode::SshUnsupported);
+		let_assert!(Some(hint) = hint_for(code));
+		assert!(hint.contains("`ssh` feature"));
+		let code = code_for(ErrorKind::Other, &error("remote: Permission to org/repo.git denied to octocat."));
+		assert!(code == ErrorCode::AccessDenied);
+		let_assert!(Some(hint) = hint_for(code));
+		assert!(hint.contains("scopes"));
+		assert!(code_for(ErrorKind::Cancelled, &error("authentication cancelled by the user")) == ErrorCode::Cancelled);
+		assert!(code_for(ErrorKind::Other, &error("failed to resolve address for example.com")) == ErrorCode::Other);
+		assert!(let None = hint_for(ErrorCode::Other));
+	}
+}
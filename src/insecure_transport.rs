@@ -0,0 +1,26 @@
+//! Policy for sending passwords over transports without encryption.
+
+/// What to do when a password would be sent over plain HTTP, without TLS.
+///
+/// Over plain HTTP, passwords and tokens can be read by anyone on the network path to the server.
+/// The policy applies to all mechanisms that give a username and password to libgit2, including prompts.
+/// SSH and HTTPS URLs are not affected.
+///
+/// Use [`GitAuthenticator::insecure_transport_policy()`][crate::GitAuthenticator::insecure_transport_policy()] to set the policy,
+/// and [`GitAuthenticator::allow_insecure_transport()`][crate::GitAuthenticator::allow_insecure_transport()] to allow specific hosts.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum InsecureTransportPolicy {
+	/// Send passwords over plain HTTP without a warning.
+	Allow,
+
+	/// Send passwords over plain HTTP, but log a warning.
+	#[default]
+	Warn,
+
+	/// Never send passwords over plain HTTP.
+	///
+	/// The operation fails with [`ErrorKind::InsecureTransport`][crate::ErrorKind::InsecureTransport] if no other credentials are accepted.
+	Deny,
+}
@@ -0,0 +1,58 @@
+//! Labels for credentials, to describe them in log messages, audit events and errors.
+
+use std::path::PathBuf;
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+/// Credentials that can be given a label with [`GitAuthenticator::with_label()`][crate::GitAuthenticator::with_label()].
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum LabelTarget {
+	/// The username for a domain.
+	Username(String),
+
+	/// The plaintext credentials for a domain.
+	PlaintextCredentials(String),
+
+	/// The callback for short-lived credentials for a domain.
+	RefreshingCredentials(String),
+
+	/// Credentials from environment variables, identified by the password variable.
+	EnvCredentials(String),
+
+	/// The systemd credential for a domain.
+	SystemdCredentials(String),
+
+	/// An SSH key file.
+	SshKey(PathBuf),
+
+	/// An SSH key read from an environment variable, identified by the variable name.
+	SshKeyEnv(String),
+}
+
+/// Credentials with a label that describes where they came from.
+pub(crate) struct LabeledCred {
+	/// The label of the credentials.
+	pub label: String,
+
+	/// The credentials.
+	pub cred: crate::ResolvedCredential,
+
+	/// The credentials converted for libgit2.
+	pub git_cred: git2::Cred,
+}
+
+impl LabeledCred {
+	/// Attach a label to credentials, and convert them for libgit2.
+	///
+	/// Returns `None` if libgit2 does not accept the credentials, so the callback can try the next mechanism.
+	pub(crate) fn new(label: String, cred: crate::ResolvedCredential) -> Option<Self> {
+		match cred.to_cred() {
+			Ok(git_cred) => Some(Self { label, cred, git_cred }),
+			Err(e) => {
+				debug!("credentials_callback: failed to wrap credentials {label}: {e}");
+				None
+			},
+		}
+	}
+}
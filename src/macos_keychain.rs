@@ -0,0 +1,155 @@
+//! Lookup of internet passwords in the macOS Keychain.
+//!
+//! This uses the `security` command line tool that is part of macOS,
+//! so it finds the same entries as the `osxkeychain` credential helper of git.
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::{PlaintextCredentials, PlannedAttempt};
+
+/// The search parameters for an internet password.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Query<'a> {
+	/// The Keychain protocol code (`htps` or `http`).
+	protocol: &'static str,
+
+	/// The host name, without port.
+	server: &'a str,
+
+	/// The port number, if specified in the URL.
+	port: Option<&'a str>,
+}
+
+/// Get the Keychain query for a URL.
+///
+/// Returns `None` if the URL is not an HTTP(S) URL.
+pub(crate) fn query_for_url(url: &str) -> Option<Query<'_>> {
+	let (scheme, _) = url.split_once("://")?;
+	let protocol = match scheme {
+		"https" => "htps",
+		"http" => "http",
+		_ => return None,
+	};
+	let host = crate::domain_from_url(url)?;
+	let (server, port) = match host.rsplit_once(':') {
+		Some((server, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => (server, Some(port)),
+		_ => (host, None),
+	};
+	Some(Query { protocol, server, port })
+}
+
+/// Get credentials for a URL from the macOS Keychain.
+///
+/// If the username is not known, the account name of the first matching Keychain item is used.
+/// The account name is read from the attributes of the item, which does not need access to the password,
+/// so only the password lookup can make macOS ask the user to allow access to the item.
+pub(crate) fn get_credentials(url: &str, username: Option<&str>) -> Option<PlaintextCredentials> {
+	let query = query_for_url(url)?;
+	let username = match username {
+		Some(x) => x.to_owned(),
+		None => {
+			let attributes = run_security(&query, None, None)?;
+			parse_account(&attributes)?
+		},
+	};
+	let password = run_security(&query, Some(&username), Some("-w"))?;
+	let password = password.strip_suffix('\n').unwrap_or(&password);
+	Some(PlaintextCredentials {
+		username,
+		password: password.into(),
+	})
+}
+
+/// Run `security find-internet-password` with the given query and get the standard output.
+///
+/// Without output flag, `security` prints the attributes of the item, but not the password.
+fn run_security(query: &Query, account: Option<&str>, output_flag: Option<&str>) -> Option<String> {
+	let mut command = std::process::Command::new("/usr/bin/security");
+	command
+		.arg("find-internet-password")
+		.args(["-s", query.server])
+		.args(["-r", query.protocol]);
+	if let Some(port) = query.port {
+		command.args(["-P", port]);
+	}
+	if let Some(account) = account {
+		command.args(["-a", account]);
+	}
+	command.args(output_flag);
+	let output = command
+		.stdin(std::process::Stdio::null())
+		.stderr(std::process::Stdio::null())
+		.output()
+		.map_err(|e| debug!("Failed to run /usr/bin/security: {e}"))
+		.ok()?;
+	if !output.status.success() {
+		debug!("No internet password found in the macOS Keychain for {:?}", query.server);
+		return None;
+	}
+	String::from_utf8(output.stdout)
+		.map_err(|_| warn!("Invalid UTF-8 in the output of /usr/bin/security"))
+		.ok()
+}
+
+/// Parse the account name from the attributes printed by `security find-internet-password`.
+fn parse_account(attributes: &str) -> Option<String> {
+	attributes.lines()
+		.map(|line| line.trim())
+		.find_map(|line| line.strip_prefix("\"acct\"<blob>=\""))
+		.and_then(|value| value.strip_suffix('"'))
+		.filter(|value| !value.is_empty())
+		.map(String::from)
+}
+
+/// The macOS Keychain as source of credentials.
+pub(crate) struct MacosKeychain;
+
+impl CredentialSource for MacosKeychain {
+	fn mechanism(&self) -> &'static str {
+		"macos-keychain"
+	}
+
+	fn label(&self) -> String {
+		"macos-keychain".into()
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::MacosKeychain
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		Ok(get_credentials(request.url, request.username))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_query_for_url() {
+		assert!(let Some(Query { protocol: "htps", server: "github.com", port: None }) = query_for_url("https://github.com/de-vri-es/auth-git2-rs"));
+		assert!(let Some(Query { protocol: "http", server: "example.com", port: Some("8080") }) = query_for_url("http://user@example.com:8080/repo.git"));
+		assert!(let None = query_for_url("ssh://git@github.com/de-vri-es/auth-git2-rs"));
+		assert!(let None = query_for_url("git@github.com:de-vri-es/auth-git2-rs"));
+	}
+
+	#[test]
+	fn test_parse_account() {
+		let attributes = concat!(
+			"keychain: \"/Users/me/Library/Keychains/login.keychain-db\"\n",
+			"version: 512\n",
+			"class: \"inet\"\n",
+			"attributes:\n",
+			"    0x00000007 <blob>=\"github.com\"\n",
+			"    \"acct\"<blob>=\"octocat\"\n",
+			"    \"ptcl\"<uint32>=\"htps\"\n",
+			"    \"srvr\"<blob>=\"github.com\"\n",
+		);
+		assert!(let Some("octocat") = parse_account(attributes).as_deref());
+		assert!(let None = parse_account("    \"acct\"<blob>=<NULL>\n"));
+	}
+}
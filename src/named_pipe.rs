@@ -0,0 +1,118 @@
+//! Named pipes for local communication between processes on Windows.
+//!
+//! Named pipes can only be used by local processes, and the default security descriptor of a pipe
+//! only gives write access to the user that created it, to administrators and to the system account.
+
+use std::ffi::c_void;
+use std::os::windows::io::{AsRawHandle, FromRawHandle};
+
+type Handle = *mut c_void;
+
+const INVALID_HANDLE_VALUE: Handle = -1isize as Handle;
+const PIPE_ACCESS_DUPLEX: u32 = 0x0000_0003;
+const FILE_FLAG_FIRST_PIPE_INSTANCE: u32 = 0x0008_0000;
+const PIPE_TYPE_BYTE: u32 = 0x0000_0000;
+const PIPE_READMODE_BYTE: u32 = 0x0000_0000;
+const PIPE_WAIT: u32 = 0x0000_0000;
+const PIPE_REJECT_REMOTE_CLIENTS: u32 = 0x0000_0008;
+const PIPE_UNLIMITED_INSTANCES: u32 = 255;
+const BUFFER_SIZE: u32 = 4096;
+const ERROR_PIPE_BUSY: i32 = 231;
+const ERROR_PIPE_CONNECTED: i32 = 535;
+
+#[link(name = "kernel32")]
+extern "system" {
+	fn CreateNamedPipeW(
+		name: *const u16,
+		open_mode: u32,
+		pipe_mode: u32,
+		max_instances: u32,
+		out_buffer_size: u32,
+		in_buffer_size: u32,
+		default_timeout: u32,
+		security: *mut c_void,
+	) -> Handle;
+	fn ConnectNamedPipe(pipe: Handle, overlapped: *mut c_void) -> i32;
+}
+
+/// Get the full path of a named pipe.
+pub(crate) fn pipe_path(name: &str) -> String {
+	format!(r"\\.\pipe\{name}")
+}
+
+/// The server side of a named pipe, which accepts connections one at a time.
+pub(crate) struct PipeListener {
+	/// The full path of the pipe as null terminated UTF-16 string.
+	path: Vec<u16>,
+
+	/// The pipe instance that the next client connects to.
+	next: std::fs::File,
+}
+
+impl PipeListener {
+	/// Create a new named pipe.
+	///
+	/// This fails if a pipe with the same name already exists, so a pipe created by another process is never used.
+	pub(crate) fn bind(name: &str) -> std::io::Result<Self> {
+		let path: Vec<u16> = pipe_path(name).encode_utf16().chain([0]).collect();
+		let next = create_instance(&path, FILE_FLAG_FIRST_PIPE_INSTANCE)?;
+		Ok(Self { path, next })
+	}
+
+	/// Wait for a client to connect.
+	///
+	/// A new pipe instance is created before the connected one is returned,
+	/// so the name of the pipe is never free for another process to take.
+	pub(crate) fn accept(&mut self) -> std::io::Result<std::fs::File> {
+		let pipe = std::mem::replace(&mut self.next, create_instance(&self.path, 0)?);
+		// SAFETY: The handle is a valid pipe handle, and the overlapped pointer may be null for synchronous pipes.
+		let connected = unsafe { ConnectNamedPipe(pipe.as_raw_handle(), std::ptr::null_mut()) };
+		if connected == 0 {
+			// A client that connects between creating the instance and calling `ConnectNamedPipe()` is connected too.
+			let error = std::io::Error::last_os_error();
+			if error.raw_os_error() != Some(ERROR_PIPE_CONNECTED) {
+				return Err(error);
+			}
+		}
+		Ok(pipe)
+	}
+}
+
+/// Create a new instance of a named pipe.
+fn create_instance(path: &[u16], flags: u32) -> std::io::Result<std::fs::File> {
+	// SAFETY: The path is a null terminated UTF-16 string, and a null security descriptor means the default one.
+	let handle = unsafe {
+		CreateNamedPipeW(
+			path.as_ptr(),
+			PIPE_ACCESS_DUPLEX | flags,
+			PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+			PIPE_UNLIMITED_INSTANCES,
+			BUFFER_SIZE,
+			BUFFER_SIZE,
+			0,
+			std::ptr::null_mut(),
+		)
+	};
+	if handle == INVALID_HANDLE_VALUE {
+		return Err(std::io::Error::last_os_error());
+	}
+	// SAFETY: The handle is a new pipe handle that is owned by nothing else.
+	Ok(unsafe { std::fs::File::from_raw_handle(handle) })
+}
+
+/// Connect to a named pipe.
+///
+/// If all instances of the pipe are busy, this waits up to ten seconds for one to become available.
+pub(crate) fn connect(name: &str) -> std::io::Result<std::fs::File> {
+	let path = pipe_path(name);
+	let mut attempts = 0;
+	loop {
+		match std::fs::OpenOptions::new().read(true).write(true).open(&path) {
+			Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) && attempts < 200 => {
+				attempts += 1;
+				std::thread::sleep(std::time::Duration::from_millis(50));
+			},
+			result => return result,
+		}
+	}
+}
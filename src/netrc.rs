@@ -0,0 +1,274 @@
+//! Support for reading credentials from `.netrc` files.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::{Paths, PlaintextCredentials, PlannedAttempt};
+
+/// An entry from a `.netrc` file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Entry {
+	/// The machine name, or `None` for the `default` entry.
+	pub machine: Option<String>,
+
+	/// The login name.
+	pub login: Option<String>,
+
+	/// The password.
+	pub password: Option<String>,
+}
+
+/// Get the path of the `.netrc` file.
+///
+/// This is the `.netrc` file of the [`Paths`] if it is set (normally the `NETRC` environment variable),
+/// or `$HOME/.netrc` otherwise.
+/// On Windows, `$HOME/_netrc` is used if `$HOME/.netrc` does not exist.
+///
+/// The `home` function is only called if the home directory is needed.
+pub(crate) fn netrc_path(paths: &dyn Paths, home: impl FnOnce() -> Option<PathBuf>) -> Option<PathBuf> {
+	if let Some(path) = paths.netrc_file() {
+		return Some(path);
+	}
+	let home = home()?;
+	let path = home.join(".netrc");
+	if cfg!(windows) && !path.exists() {
+		return Some(home.join("_netrc"));
+	}
+	Some(path)
+}
+
+/// Get credentials for a URL from the `.netrc` file.
+pub(crate) fn get_credentials(url: &str, username: Option<&str>, paths: &dyn Paths, home: impl FnOnce() -> Option<PathBuf>) -> Option<PlaintextCredentials> {
+	let path = netrc_path(paths, home)?;
+	let entries = read_netrc(&path)?;
+	let host = crate::domain_from_url(url)?;
+	find_credentials(&entries, host, username)
+}
+
+/// Read and parse a `.netrc` file.
+fn read_netrc(path: &Path) -> Option<Vec<Entry>> {
+	match std::fs::read_to_string(path) {
+		Ok(data) => Some(parse_netrc(&data)),
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+			debug!("No netrc file at {}", path.display());
+			None
+		},
+		Err(e) => {
+			warn!("Failed to read {}: {e}", path.display());
+			None
+		},
+	}
+}
+
+/// Find the credentials for a host in a list of netrc entries.
+///
+/// If a username is given, only entries with a matching login (or without login) are considered.
+/// The first matching `machine` entry is used, and the `default` entry is used if no `machine` entry matches.
+pub(crate) fn find_credentials(entries: &[Entry], host: &str, username: Option<&str>) -> Option<PlaintextCredentials> {
+	// The machine names in a netrc file do not include a port.
+	let hostname = strip_port(host);
+	let matches_user = |entry: &&Entry| match (username, &entry.login) {
+		(Some(username), Some(login)) => username == login,
+		_ => true,
+	};
+	let entry = entries.iter()
+		.filter(matches_user)
+		.find(|entry| entry.machine.as_deref().is_some_and(|machine| machine == host || machine == hostname))
+		.or_else(|| entries.iter().filter(matches_user).find(|entry| entry.machine.is_none()))?;
+	let username = entry.login.as_deref().or(username)?;
+	let password = entry.password.as_deref()?;
+	Some(PlaintextCredentials {
+		username: username.into(),
+		password: password.into(),
+	})
+}
+
+/// Strip the port from a host, if it has one.
+fn strip_port(host: &str) -> &str {
+	match host.rsplit_once(':') {
+		Some((hostname, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => hostname,
+		_ => host,
+	}
+}
+
+/// Parse the contents of a `.netrc` file.
+pub(crate) fn parse_netrc(data: &str) -> Vec<Entry> {
+	let mut entries = Vec::new();
+	let mut tokens = Tokenizer::new(data);
+
+	while let Some(token) = tokens.next() {
+		match token.as_str() {
+			"machine" => {
+				let machine = tokens.next();
+				entries.push(Entry { machine, login: None, password: None });
+			},
+			"default" => {
+				entries.push(Entry { machine: None, login: None, password: None });
+			},
+			"login" => {
+				let value = tokens.next();
+				if let Some(entry) = entries.last_mut() {
+					entry.login = value;
+				}
+			},
+			"password" => {
+				let value = tokens.next();
+				if let Some(entry) = entries.last_mut() {
+					entry.password = value;
+				}
+			},
+			"account" => {
+				tokens.next();
+			},
+			"macdef" => {
+				tokens.next();
+				tokens.skip_macro();
+			},
+			other => {
+				debug!("Ignoring unknown token in netrc file: {other:?}");
+			},
+		}
+	}
+
+	entries
+}
+
+/// Tokenizer for `.netrc` files.
+struct Tokenizer<'a> {
+	data: &'a str,
+}
+
+impl<'a> Tokenizer<'a> {
+	fn new(data: &'a str) -> Self {
+		Self { data }
+	}
+
+	/// Skip the body of a macro definition, which ends at the first empty line.
+	fn skip_macro(&mut self) {
+		// Skip the rest of the macdef line.
+		let rest = match self.data.split_once('\n') {
+			Some((_, rest)) => rest,
+			None => "",
+		};
+		let mut lines = rest.split_inclusive('\n');
+		let mut consumed = self.data.len() - rest.len();
+		for line in &mut lines {
+			consumed += line.len();
+			if line.trim().is_empty() {
+				break;
+			}
+		}
+		self.data = &self.data[consumed.min(self.data.len())..];
+	}
+}
+
+impl Iterator for Tokenizer<'_> {
+	type Item = String;
+
+	fn next(&mut self) -> Option<String> {
+		let data = self.data.trim_start();
+		if data.is_empty() {
+			self.data = data;
+			return None;
+		}
+
+		if let Some(quoted) = data.strip_prefix('"') {
+			let mut token = String::new();
+			let mut chars = quoted.char_indices();
+			while let Some((i, c)) = chars.next() {
+				match c {
+					'"' => {
+						self.data = &quoted[i + 1..];
+						return Some(token);
+					},
+					'\\' => {
+						if let Some((_, c)) = chars.next() {
+							token.push(c);
+						}
+					},
+					c => token.push(c),
+				}
+			}
+			self.data = "";
+			Some(token)
+		} else {
+			let end = data.find(char::is_whitespace).unwrap_or(data.len());
+			self.data = &data[end..];
+			Some(data[..end].to_owned())
+		}
+	}
+}
+
+/// The `.netrc` file as source of credentials.
+pub(crate) struct Netrc;
+
+impl CredentialSource for Netrc {
+	fn mechanism(&self) -> &'static str {
+		"netrc"
+	}
+
+	fn label(&self) -> String {
+		"netrc".into()
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::Netrc
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		let authenticator = request.authenticator;
+		Ok(get_credentials(request.url, request.username, &*authenticator.paths, || authenticator.find_home_dir("the .netrc file")))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_parse_netrc() {
+		let entries = parse_netrc(concat!(
+			"machine example.com login alice password secret1\n",
+			"machine example.com\n",
+			"  login bob\n",
+			"  password \"with space\"\n",
+			"macdef init\n",
+			"cd /pub\n",
+			"\n",
+			"machine other.com login carol password secret3 account x\n",
+			"default login anonymous password guest\n",
+		));
+		assert!(entries == [
+			Entry { machine: Some("example.com".into()), login: Some("alice".into()), password: Some("secret1".into()) },
+			Entry { machine: Some("example.com".into()), login: Some("bob".into()), password: Some("with space".into()) },
+			Entry { machine: Some("other.com".into()), login: Some("carol".into()), password: Some("secret3".into()) },
+			Entry { machine: None, login: Some("anonymous".into()), password: Some("guest".into()) },
+		]);
+	}
+
+	#[test]
+	fn test_find_credentials() {
+		let entries = parse_netrc(concat!(
+			"machine example.com login alice password secret1\n",
+			"machine example.com login bob password secret2\n",
+			"default login anonymous password guest\n",
+		));
+
+		check_credentials(find_credentials(&entries, "example.com", None), "alice", "secret1");
+		check_credentials(find_credentials(&entries, "example.com:8443", None), "alice", "secret1");
+		check_credentials(find_credentials(&entries, "example.com", Some("bob")), "bob", "secret2");
+		check_credentials(find_credentials(&entries, "example.org", None), "anonymous", "guest");
+		assert!(let None = find_credentials(&entries, "example.org", Some("dave")));
+	}
+
+	#[track_caller]
+	fn check_credentials(credentials: Option<PlaintextCredentials>, username: &str, password: &str) {
+		let credentials = credentials.unwrap();
+		assert!(credentials.username == username);
+		assert!(credentials.password == password);
+	}
+}
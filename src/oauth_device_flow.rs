@@ -0,0 +1,338 @@
+//! OAuth 2.0 device authorization flow (RFC 8628).
+//!
+//! The user authorizes access in a browser, possibly on a different device,
+//! by entering a short code on a verification page of the server.
+//! Meanwhile, the token endpoint is polled until the user approves or denies the request.
+
+use std::time::Duration;
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::{Clock, ExpiringCredentials, Prompter};
+
+/// The grant type for polling the token endpoint.
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Cached access tokens are no longer used this long before they expire.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Configuration of the OAuth device authorization flow for a host.
+///
+/// Use [`GitAuthenticator::add_oauth_device_flow()`][crate::GitAuthenticator::add_oauth_device_flow()] to enable the flow for a domain.
+///
+/// This is only available with the `oauth-device-flow` feature.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DeviceFlowConfig {
+	/// The client ID of the OAuth application.
+	client_id: String,
+
+	/// The URL of the device authorization endpoint.
+	device_authorization_endpoint: String,
+
+	/// The URL of the token endpoint.
+	token_endpoint: String,
+
+	/// The scopes to request.
+	scopes: Vec<String>,
+
+	/// The username to use with the access token.
+	username: String,
+
+	/// The maximum time to wait for the user to approve the request.
+	timeout: Option<Duration>,
+}
+
+impl DeviceFlowConfig {
+	/// Create a new configuration for an OAuth application with custom endpoints.
+	///
+	/// No scopes are requested by default, and the username `oauth2` is used with the access token.
+	pub fn new(client_id: impl Into<String>, device_authorization_endpoint: impl Into<String>, token_endpoint: impl Into<String>) -> Self {
+		Self {
+			client_id: client_id.into(),
+			device_authorization_endpoint: device_authorization_endpoint.into(),
+			token_endpoint: token_endpoint.into(),
+			scopes: Vec::new(),
+			username: "oauth2".into(),
+			timeout: None,
+		}
+	}
+
+	/// Create a configuration for an OAuth application on `github.com`.
+	///
+	/// The device flow must be enabled in the settings of the OAuth application.
+	/// The `repo` scope is requested, and the username `x-access-token` is used with the access token.
+	pub fn github(client_id: impl Into<String>) -> Self {
+		Self::new(client_id, "https://github.com/login/device/code", "https://github.com/login/oauth/access_token")
+			.add_scope("repo")
+			.username("x-access-token")
+	}
+
+	/// Create a configuration for an OAuth application on `gitlab.com`.
+	///
+	/// The `read_repository` and `write_repository` scopes are requested, and the username `oauth2` is used with the access token.
+	pub fn gitlab(client_id: impl Into<String>) -> Self {
+		Self::new(client_id, "https://gitlab.com/oauth/authorize_device", "https://gitlab.com/oauth/token")
+			.add_scope("read_repository")
+			.add_scope("write_repository")
+	}
+
+	/// Add a scope to request.
+	pub fn add_scope(mut self, scope: impl Into<String>) -> Self {
+		self.scopes.push(scope.into());
+		self
+	}
+
+	/// Set the username to use with the access token.
+	///
+	/// If the URL contains a username, that username is used instead.
+	pub fn username(mut self, username: impl Into<String>) -> Self {
+		self.username = username.into();
+		self
+	}
+
+	/// Set the maximum time to wait for the user to approve the request.
+	///
+	/// The credentials callback of libgit2 blocks the operation while it waits for the user.
+	/// By default, it waits until the device code expires, which is often 15 minutes.
+	pub fn timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = Some(timeout);
+		self
+	}
+
+	/// Get the client ID of the OAuth application.
+	pub fn client_id(&self) -> &str {
+		&self.client_id
+	}
+}
+
+/// The response of the device authorization endpoint.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct DeviceAuthorization {
+	/// The code to poll the token endpoint with.
+	pub device_code: String,
+
+	/// The code that the user enters on the verification page.
+	pub user_code: String,
+
+	/// The URL of the verification page.
+	pub verification_uri: String,
+
+	/// The URL of the verification page with the user code filled in, if the server provides it.
+	pub verification_uri_complete: Option<String>,
+
+	/// The time until the device code expires.
+	pub expires_in: Duration,
+
+	/// The minimum time between polls of the token endpoint.
+	pub interval: Duration,
+}
+
+/// The result of polling the token endpoint.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum TokenPoll {
+	/// The user approved the request.
+	Token {
+		/// The access token.
+		access_token: String,
+
+		/// The time until the access token expires, if the server reports it.
+		expires_in: Option<Duration>,
+	},
+
+	/// The user did not respond yet.
+	Pending,
+
+	/// The client should poll less often.
+	SlowDown,
+
+	/// The request was denied, expired or failed for another reason.
+	Failed(String),
+}
+
+/// Run the device authorization flow to get credentials for a URL.
+///
+/// The credentials expire shortly before the access token does, if the server reports when the token expires.
+/// Returns `None` if the flow fails, or if the user does not approve the request in time.
+pub(crate) fn get_credentials(
+	config: &DeviceFlowConfig,
+	prompter: &mut dyn Prompter,
+	clock: &dyn Clock,
+	url: &str,
+	username: Option<&str>,
+	git_config: &git2::Config,
+) -> Option<ExpiringCredentials> {
+	let authorization = request_device_code(config)
+		.map_err(|e| warn!("Failed to start OAuth device authorization at {}: {e}", config.device_authorization_endpoint))
+		.ok()?;
+	let verification_uri = authorization.verification_uri_complete.as_deref().unwrap_or(&authorization.verification_uri);
+	if !prompter.show_device_code(url, verification_uri, &authorization.user_code, git_config) {
+		debug!("OAuth device authorization code for {url:?} was not shown to the user");
+		return None;
+	}
+
+	let wait = match config.timeout {
+		Some(timeout) => timeout.min(authorization.expires_in),
+		None => authorization.expires_in,
+	};
+	let deadline = clock.now() + wait;
+	let mut interval = authorization.interval;
+	while clock.now() + interval < deadline {
+		clock.sleep(interval);
+		match poll_token(config, &authorization.device_code) {
+			Ok(TokenPoll::Token { access_token, expires_in }) => {
+				let credentials = ExpiringCredentials::new(username.unwrap_or(&config.username), access_token);
+				return Some(match expires_in {
+					Some(expires_in) => credentials.expires_at(clock.system_now() + expires_in.saturating_sub(EXPIRY_MARGIN)),
+					None => credentials,
+				});
+			},
+			Ok(TokenPoll::Pending) => (),
+			Ok(TokenPoll::SlowDown) => interval += Duration::from_secs(5),
+			Ok(TokenPoll::Failed(error)) => {
+				warn!("OAuth device authorization for {url:?} failed: {error}");
+				return None;
+			},
+			Err(e) => {
+				warn!("Failed to poll OAuth token endpoint {}: {e}", config.token_endpoint);
+				return None;
+			},
+		}
+	}
+	warn!("OAuth device authorization for {url:?} was not approved in time");
+	None
+}
+
+/// Request a device code from the device authorization endpoint.
+fn request_device_code(config: &DeviceFlowConfig) -> Result<DeviceAuthorization, String> {
+	let scope = config.scopes.join(" ");
+	let mut form = vec![("client_id", config.client_id.as_str())];
+	if !scope.is_empty() {
+		form.push(("scope", &scope));
+	}
+	let body = post_form(&config.device_authorization_endpoint, &form)?;
+	parse_device_authorization(&body)
+}
+
+/// Poll the token endpoint.
+fn poll_token(config: &DeviceFlowConfig, device_code: &str) -> Result<TokenPoll, String> {
+	let body = post_form(&config.token_endpoint, &[
+		("client_id", config.client_id.as_str()),
+		("device_code", device_code),
+		("grant_type", DEVICE_CODE_GRANT_TYPE),
+	])?;
+	parse_token_poll(&body)
+}
+
+/// Send a form-encoded POST request, and get the response body.
+///
+/// OAuth servers report errors of the token endpoint with status 400 and a JSON body,
+/// so the body is also returned for error responses.
+fn post_form(url: &str, form: &[(&str, &str)]) -> Result<String, String> {
+	let response = ureq::post(url)
+		.set("Accept", "application/json")
+		.send_form(form);
+	let response = match response {
+		Ok(x) => x,
+		Err(ureq::Error::Status(_code, response)) => response,
+		Err(e) => return Err(e.to_string()),
+	};
+	response.into_string().map_err(|e| e.to_string())
+}
+
+/// Parse the response of the device authorization endpoint.
+pub(crate) fn parse_device_authorization(body: &str) -> Result<DeviceAuthorization, String> {
+	let value: serde_json::Value = serde_json::from_str(body).map_err(|e| format!("invalid JSON response: {e}"))?;
+	if let Some(error) = error_message(&value) {
+		return Err(error);
+	}
+	let string = |name: &str| value.get(name).and_then(|x| x.as_str()).map(String::from);
+	let seconds = |name: &str| value.get(name).and_then(|x| x.as_u64()).map(Duration::from_secs);
+	Ok(DeviceAuthorization {
+		device_code: string("device_code").ok_or("missing device_code in response")?,
+		user_code: string("user_code").ok_or("missing user_code in response")?,
+		// Some servers (like Google) use `verification_url` instead.
+		verification_uri: string("verification_uri")
+			.or_else(|| string("verification_url"))
+			.ok_or("missing verification_uri in response")?,
+		verification_uri_complete: string("verification_uri_complete"),
+		expires_in: seconds("expires_in").ok_or("missing expires_in in response")?,
+		interval: seconds("interval").unwrap_or(Duration::from_secs(5)),
+	})
+}
+
+/// Parse the response of the token endpoint.
+pub(crate) fn parse_token_poll(body: &str) -> Result<TokenPoll, String> {
+	let value: serde_json::Value = serde_json::from_str(body).map_err(|e| format!("invalid JSON response: {e}"))?;
+	if let Some(token) = value.get("access_token").and_then(|x| x.as_str()) {
+		return Ok(TokenPoll::Token {
+			access_token: token.into(),
+			expires_in: value.get("expires_in").and_then(|x| x.as_u64()).map(Duration::from_secs),
+		});
+	}
+	match value.get("error").and_then(|x| x.as_str()) {
+		Some("authorization_pending") => Ok(TokenPoll::Pending),
+		Some("slow_down") => Ok(TokenPoll::SlowDown),
+		Some(_) => Ok(TokenPoll::Failed(error_message(&value).unwrap_or_default())),
+		None => Err("response contains neither an access token nor an error".into()),
+	}
+}
+
+/// Get the error message from an OAuth error response.
+fn error_message(value: &serde_json::Value) -> Option<String> {
+	let error = value.get("error")?.as_str()?;
+	match value.get("error_description").and_then(|x| x.as_str()) {
+		Some(description) => Some(format!("{error}: {description}")),
+		None => Some(error.into()),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_parse_device_authorization() {
+		let_assert!(Ok(authorization) = parse_device_authorization(r#"{
+			"device_code": "3584d83530557fdd1f46af8289938c8ef79f9dc5",
+			"user_code": "WDJB-MJHT",
+			"verification_uri": "https://github.com/login/device",
+			"expires_in": 900,
+			"interval": 5
+		}"#));
+		assert!(authorization == DeviceAuthorization {
+			device_code: "3584d83530557fdd1f46af8289938c8ef79f9dc5".into(),
+			user_code: "WDJB-MJHT".into(),
+			verification_uri: "https://github.com/login/device".into(),
+			verification_uri_complete: None,
+			expires_in: Duration::from_secs(900),
+			interval: Duration::from_secs(5),
+		});
+
+		let_assert!(Ok(authorization) = parse_device_authorization(r#"{
+			"device_code": "abc",
+			"user_code": "XYZ",
+			"verification_url": "https://www.google.com/device",
+			"expires_in": 1800
+		}"#));
+		assert!(authorization.verification_uri == "https://www.google.com/device");
+		assert!(authorization.interval == Duration::from_secs(5));
+
+		let_assert!(Err(e) = parse_device_authorization(r#"{"error": "invalid_client", "error_description": "unknown client"}"#));
+		assert!(e == "invalid_client: unknown client");
+	}
+
+	#[test]
+	fn test_parse_token_poll() {
+		let_assert!(Ok(TokenPoll::Token { access_token, expires_in }) = parse_token_poll(r#"{"access_token": "gho_16C7e42F292c", "token_type": "bearer"}"#));
+		assert!(access_token == "gho_16C7e42F292c");
+		assert!(let None = expires_in);
+		let_assert!(Ok(TokenPoll::Token { expires_in, .. }) = parse_token_poll(r#"{"access_token": "ghu_16C7e42F292c", "expires_in": 28800}"#));
+		assert!(expires_in == Some(Duration::from_secs(28800)));
+		assert!(let Ok(TokenPoll::Pending) = parse_token_poll(r#"{"error": "authorization_pending"}"#));
+		assert!(let Ok(TokenPoll::SlowDown) = parse_token_poll(r#"{"error": "slow_down", "interval": 10}"#));
+		assert!(let Ok(TokenPoll::Failed(_)) = parse_token_poll(r#"{"error": "access_denied"}"#));
+		assert!(let Err(_) = parse_token_poll(r#"{}"#));
+	}
+}
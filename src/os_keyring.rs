@@ -0,0 +1,99 @@
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use std::borrow::Cow;
+
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::{PlaintextCredentials, PlannedAttempt};
+
+/// Get the keyring service name for a URL.
+///
+/// This uses the same service names as the Git Credential Manager: `git:<scheme>://<host>`.
+pub(crate) fn service_name(url: &str) -> Option<String> {
+	let (scheme, _) = url.split_once("://")?;
+	let host = crate::domain_from_url(url)?;
+	Some(format!("git:{scheme}://{host}"))
+}
+
+/// Get credentials for a URL and username from the OS keyring.
+pub(crate) fn get_credentials(url: &str, username: &str) -> Option<PlaintextCredentials> {
+	let service = service_name(url)?;
+	let entry = ::keyring::Entry::new(&service, username)
+		.map_err(|e| debug!("Failed to open keyring entry for {service:?} with username {username:?}: {e}"))
+		.ok()?;
+	match entry.get_password() {
+		Ok(password) => Some(PlaintextCredentials {
+			username: username.into(),
+			password,
+		}),
+		Err(::keyring::Error::NoEntry) => {
+			debug!("No keyring entry for {service:?} with username {username:?}");
+			None
+		},
+		Err(e) => {
+			warn!("Failed to read keyring entry for {service:?} with username {username:?}: {e}");
+			None
+		},
+	}
+}
+
+/// Store credentials for a URL in the OS keyring.
+pub(crate) fn store_credentials(url: &str, username: &str, password: &str) -> Result<(), ::keyring::Error> {
+	let service = service_name(url)
+		.ok_or_else(|| ::keyring::Error::Invalid("url".into(), format!("no scheme or host in URL: {url:?}")))?;
+	::keyring::Entry::new(&service, username)?
+		.set_password(password)
+}
+
+/// Delete credentials for a URL from the OS keyring.
+///
+/// Deleting credentials that do not exist is not an error.
+pub(crate) fn delete_credentials(url: &str, username: &str) -> Result<(), ::keyring::Error> {
+	let service = service_name(url)
+		.ok_or_else(|| ::keyring::Error::Invalid("url".into(), format!("no scheme or host in URL: {url:?}")))?;
+	match ::keyring::Entry::new(&service, username)?.delete_credential() {
+		Ok(()) | Err(::keyring::Error::NoEntry) => Ok(()),
+		Err(e) => Err(e),
+	}
+}
+
+/// The OS keyring as source of credentials.
+///
+/// The keyring can only be searched with a username, so the username is decided before the keyring is tried.
+pub(crate) struct Keyring<'a> {
+	/// The username to look up.
+	pub username: Cow<'a, str>,
+}
+
+impl CredentialSource for Keyring<'_> {
+	fn mechanism(&self) -> &'static str {
+		"keyring"
+	}
+
+	fn label(&self) -> String {
+		"keyring".into()
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::Keyring {
+			username: self.username.to_string(),
+		}
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		Ok(get_credentials(request.url, &self.username))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_service_name() {
+		assert!(let Some("git:https://github.com") = service_name("https://github.com/de-vri-es/auth-git2-rs").as_deref());
+		assert!(let Some("git:https://example.com:8443") = service_name("https://user@example.com:8443/repo.git").as_deref());
+		assert!(let None = service_name("git@github.com:de-vri-es/auth-git2-rs"));
+	}
+}
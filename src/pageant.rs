@@ -0,0 +1,224 @@
+//! Minimal client for Pageant, the SSH agent of PuTTY.
+//!
+//! Pageant does not listen on a socket or named pipe that can be found without knowing its exact name.
+//! Instead, a request is written to shared memory, and the name of the shared memory is sent to the window of Pageant in a `WM_COPYDATA` message.
+//! Pageant writes the response to the same shared memory.
+//!
+//! libssh2 talks to Pageant by itself, so this is only used to list the keys of the agent.
+//! Pageant only accepts shared memory that is owned by the same user as Pageant.
+//! Like PuTTY, the shared memory gets a random name and a security descriptor with the user of the process as owner,
+//! so other processes can not guess the name to take the shared memory first,
+//! and requests from elevated processes are accepted too.
+
+use std::ffi::c_void;
+
+type Handle = *mut c_void;
+type Hwnd = *mut c_void;
+
+const INVALID_HANDLE_VALUE: Handle = -1isize as Handle;
+const PAGE_READWRITE: u32 = 0x04;
+const FILE_MAP_WRITE: u32 = 0x0002;
+const WM_COPYDATA: u32 = 0x004A;
+const TOKEN_QUERY: u32 = 0x0008;
+const TOKEN_USER: u32 = 1;
+const SECURITY_DESCRIPTOR_REVISION: u32 = 1;
+const ERROR_ALREADY_EXISTS: i32 = 183;
+
+/// The identifier of agent requests in the `WM_COPYDATA` message.
+const AGENT_COPYDATA_ID: usize = 0x804e_50ba;
+
+/// The size of the shared memory, which limits the size of requests and responses.
+const AGENT_MAX_MSGLEN: usize = 8192;
+
+/// The data of a `WM_COPYDATA` message.
+#[repr(C)]
+struct CopyDataStruct {
+	data: usize,
+	size: u32,
+	ptr: *mut c_void,
+}
+
+/// The security attributes of a new object.
+#[repr(C)]
+struct SecurityAttributes {
+	length: u32,
+	security_descriptor: *mut c_void,
+	inherit_handle: i32,
+}
+
+/// An absolute security descriptor.
+#[repr(C)]
+#[derive(Default)]
+struct SecurityDescriptor {
+	revision: u8,
+	sbz1: u8,
+	control: u16,
+	owner: usize,
+	group: usize,
+	sacl: usize,
+	dacl: usize,
+}
+
+#[link(name = "user32")]
+extern "system" {
+	fn FindWindowW(class_name: *const u16, window_name: *const u16) -> Hwnd;
+	fn SendMessageW(window: Hwnd, message: u32, wparam: usize, lparam: isize) -> isize;
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+	fn CreateFileMappingW(file: Handle, security: *mut c_void, protect: u32, size_high: u32, size_low: u32, name: *const u16) -> Handle;
+	fn MapViewOfFile(mapping: Handle, access: u32, offset_high: u32, offset_low: u32, size: usize) -> *mut c_void;
+	fn UnmapViewOfFile(address: *const c_void) -> i32;
+	fn CloseHandle(handle: Handle) -> i32;
+	fn GetCurrentProcess() -> Handle;
+}
+
+#[link(name = "advapi32")]
+extern "system" {
+	fn OpenProcessToken(process: Handle, access: u32, token: *mut Handle) -> i32;
+	fn GetTokenInformation(token: Handle, class: u32, info: *mut c_void, length: u32, return_length: *mut u32) -> i32;
+	fn InitializeSecurityDescriptor(descriptor: *mut c_void, revision: u32) -> i32;
+	fn SetSecurityDescriptorOwner(descriptor: *mut c_void, owner: *mut c_void, defaulted: i32) -> i32;
+}
+
+/// A handle that is closed when it is dropped.
+struct OwnedHandle(Handle);
+
+impl Drop for OwnedHandle {
+	fn drop(&mut self) {
+		// SAFETY: The handle was created by us and it is not used after this.
+		unsafe {
+			CloseHandle(self.0);
+		}
+	}
+}
+
+/// A view of a file mapping that is unmapped when it is dropped.
+struct View(*mut u8);
+
+impl Drop for View {
+	fn drop(&mut self) {
+		// SAFETY: The view was mapped by us and it is not used after this.
+		unsafe {
+			UnmapViewOfFile(self.0.cast());
+		}
+	}
+}
+
+/// Send a request to Pageant and get the response.
+///
+/// The request and response are agent messages without the length prefix.
+pub(crate) fn request(message: &[u8]) -> std::io::Result<Vec<u8>> {
+	if message.len().checked_add(4).is_none_or(|x| x > AGENT_MAX_MSGLEN) {
+		return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "request for Pageant is too large"));
+	}
+
+	let class: Vec<u16> = "Pageant".encode_utf16().chain([0]).collect();
+	// SAFETY: The class and window name are null terminated UTF-16 strings.
+	let window = unsafe { FindWindowW(class.as_ptr(), class.as_ptr()) };
+	if window.is_null() {
+		return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "Pageant is not running"));
+	}
+
+	// The name is sent as a null terminated ANSI string, and it must be unique for concurrent requests.
+	let name = format!("PageantRequest{}\0", crate::token::random_token()?);
+	let wide_name: Vec<u16> = name.trim_end_matches('\0').encode_utf16().chain([0]).collect();
+	let mut user = current_user()?;
+	let mut descriptor = SecurityDescriptor::default();
+	// SAFETY: The descriptor is a valid absolute security descriptor, and the SID of the user outlives the mapping creation.
+	unsafe {
+		let descriptor = std::ptr::addr_of_mut!(descriptor).cast();
+		if InitializeSecurityDescriptor(descriptor, SECURITY_DESCRIPTOR_REVISION) == 0 || SetSecurityDescriptorOwner(descriptor, user.sid(), 0) == 0 {
+			return Err(std::io::Error::last_os_error());
+		}
+	}
+	let mut security = SecurityAttributes {
+		length: std::mem::size_of::<SecurityAttributes>() as u32,
+		security_descriptor: std::ptr::addr_of_mut!(descriptor).cast(),
+		inherit_handle: 0,
+	};
+	// SAFETY: The name is a null terminated UTF-16 string, and the security attributes are valid.
+	let mapping = unsafe {
+		CreateFileMappingW(INVALID_HANDLE_VALUE, std::ptr::addr_of_mut!(security).cast(), PAGE_READWRITE, 0, AGENT_MAX_MSGLEN as u32, wide_name.as_ptr())
+	};
+	if mapping.is_null() {
+		return Err(std::io::Error::last_os_error());
+	}
+	// Never use shared memory that another process created with the same name.
+	let error = std::io::Error::last_os_error();
+	let mapping = OwnedHandle(mapping);
+	if error.raw_os_error() == Some(ERROR_ALREADY_EXISTS) {
+		return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "shared memory for Pageant request already exists"));
+	}
+	// SAFETY: The mapping is valid, and a size of 0 maps the whole mapping.
+	let view = unsafe { MapViewOfFile(mapping.0, FILE_MAP_WRITE, 0, 0, 0) };
+	if view.is_null() {
+		return Err(std::io::Error::last_os_error());
+	}
+	let view = View(view.cast());
+
+	// SAFETY: The view is valid for `AGENT_MAX_MSGLEN` bytes, and nothing else accesses it until the message is sent.
+	let shared = unsafe { std::slice::from_raw_parts_mut(view.0, AGENT_MAX_MSGLEN) };
+	shared[..4].copy_from_slice(&(message.len() as u32).to_be_bytes());
+	shared[4..4 + message.len()].copy_from_slice(message);
+
+	let mut name = name.into_bytes();
+	let mut data = CopyDataStruct {
+		data: AGENT_COPYDATA_ID,
+		size: name.len() as u32,
+		ptr: name.as_mut_ptr().cast(),
+	};
+	// SAFETY: The window handle is valid or stale, which `SendMessageW` handles,
+	// and `data` points to the name of the mapping, which outlives the call.
+	let result = unsafe { SendMessageW(window, WM_COPYDATA, 0, std::ptr::addr_of_mut!(data) as isize) };
+	if result == 0 {
+		return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Pageant refused the request"));
+	}
+
+	// SAFETY: Pageant is done with the shared memory once `SendMessageW` returns.
+	let shared = unsafe { std::slice::from_raw_parts(view.0, AGENT_MAX_MSGLEN) };
+	let len = u32::from_be_bytes([shared[0], shared[1], shared[2], shared[3]]) as usize;
+	if len.checked_add(4).is_none_or(|x| x > AGENT_MAX_MSGLEN) {
+		return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("response of Pageant is too large ({len} bytes)")));
+	}
+	Ok(shared[4..4 + len].to_vec())
+}
+
+/// The user of a process token, with the SID of the user.
+struct TokenUser {
+	/// The buffer with the `TOKEN_USER` structure, aligned for the pointer at the start.
+	buffer: Vec<usize>,
+}
+
+impl TokenUser {
+	/// Get a pointer to the SID of the user.
+	fn sid(&mut self) -> *mut c_void {
+		// The `TOKEN_USER` structure starts with a pointer to the SID, which points into the same buffer.
+		self.buffer[0] as *mut c_void
+	}
+}
+
+/// Get the user of the current process.
+fn current_user() -> std::io::Result<TokenUser> {
+	let mut token = std::ptr::null_mut();
+	// SAFETY: The process handle is a pseudo handle that is always valid, and `token` is a valid pointer.
+	if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+		return Err(std::io::Error::last_os_error());
+	}
+	let token = OwnedHandle(token);
+
+	// The first call only gets the size of the information, so it fails.
+	let mut length = 0;
+	// SAFETY: A null buffer with length 0 is allowed to get the required length.
+	unsafe { GetTokenInformation(token.0, TOKEN_USER, std::ptr::null_mut(), 0, &mut length) };
+	if length == 0 {
+		return Err(std::io::Error::last_os_error());
+	}
+	let mut buffer = vec![0usize; (length as usize).div_ceil(std::mem::size_of::<usize>())];
+	// SAFETY: The buffer is valid for writes of `length` bytes and aligned for the `TOKEN_USER` structure.
+	if unsafe { GetTokenInformation(token.0, TOKEN_USER, buffer.as_mut_ptr().cast(), length, &mut length) } == 0 {
+		return Err(std::io::Error::last_os_error());
+	}
+	Ok(TokenUser { buffer })
+}
@@ -0,0 +1,138 @@
+//! Credentials from the standard unix password manager (`pass`).
+//!
+//! Entries are expected in the format used by `git-credential-pass` and most `pass` users:
+//! the first line is the password, and an optional `username: <name>` line holds the username.
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::credential_helper::UrlAttributes;
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::{PlaintextCredentials, PlannedAttempt};
+
+/// The default template for the name of the entry for a URL.
+pub(crate) const DEFAULT_PATH_TEMPLATE: &str = "git/{host}";
+
+/// Get the name of the `pass` entry for a URL.
+///
+/// The placeholders `{protocol}`, `{host}` and `{path}` in the template are replaced by the parts of the URL.
+/// Returns `None` for URLs without a scheme.
+pub(crate) fn entry_name(template: &str, url: &str) -> Option<String> {
+	let attributes = UrlAttributes::parse(url)?;
+	let path = attributes.path.trim_end_matches('/');
+	Some(template
+		.replace("{protocol}", &attributes.protocol)
+		.replace("{host}", &attributes.host)
+		.replace("{path}", path))
+}
+
+/// Get credentials from a `pass` entry.
+///
+/// Returns `None` if the entry does not exist, if the entry has a different username,
+/// or if the username is not known.
+pub(crate) fn get_credentials(name: &str, username: Option<&str>) -> Option<PlaintextCredentials> {
+	let output = std::process::Command::new("pass")
+		.args(["show", name])
+		.stdin(std::process::Stdio::null())
+		.stderr(std::process::Stdio::null())
+		.output()
+		.map_err(|e| debug!("Failed to run pass: {e}"))
+		.ok()?;
+	if !output.status.success() {
+		debug!("pass has no entry {name:?}");
+		return None;
+	}
+	let entry = String::from_utf8(output.stdout)
+		.map_err(|_| warn!("Invalid UTF-8 in pass entry {name:?}"))
+		.ok()?;
+	let entry = parse_entry(&entry)?;
+	match (username, entry.username) {
+		(Some(username), Some(entry_username)) if username != entry_username => {
+			debug!("pass entry {name:?} is for username {entry_username:?}, not {username:?}");
+			None
+		},
+		(Some(username), _) | (None, Some(username)) => Some(PlaintextCredentials {
+			username: username.into(),
+			password: entry.password.into(),
+		}),
+		(None, None) => {
+			debug!("pass entry {name:?} has no username line, and the URL has no username");
+			None
+		},
+	}
+}
+
+/// A parsed `pass` entry.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Entry<'a> {
+	/// The password on the first line.
+	pub password: &'a str,
+
+	/// The username from the `username:` line.
+	pub username: Option<&'a str>,
+}
+
+/// Parse a `pass` entry.
+///
+/// Returns `None` if the password is empty.
+pub(crate) fn parse_entry(entry: &str) -> Option<Entry<'_>> {
+	let mut lines = entry.lines();
+	let password = lines.next()?.trim_end_matches('\r');
+	if password.is_empty() {
+		return None;
+	}
+	let username = lines
+		.filter_map(|line| line.strip_prefix("username:"))
+		.map(|username| username.trim())
+		.find(|username| !username.is_empty());
+	Some(Entry { password, username })
+}
+
+/// A `pass` entry as source of credentials.
+pub(crate) struct PassEntry {
+	/// The name of the entry.
+	pub entry: String,
+}
+
+impl CredentialSource for PassEntry {
+	fn mechanism(&self) -> &'static str {
+		"pass"
+	}
+
+	fn label(&self) -> String {
+		format!("pass:{}", self.entry)
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::Pass {
+			entry: self.entry.clone(),
+		}
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		Ok(get_credentials(&self.entry, request.username))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_entry_name() {
+		assert!(let Some("git/example.com") = entry_name(DEFAULT_PATH_TEMPLATE, "https://example.com/group/repo.git").as_deref());
+		assert!(let Some("git/https/example.com:8443/group/repo.git") = entry_name("git/{protocol}/{host}/{path}", "https://example.com:8443/group/repo.git/").as_deref());
+		assert!(let None = entry_name(DEFAULT_PATH_TEMPLATE, "git@example.com:repo.git"));
+	}
+
+	#[test]
+	fn test_parse_entry() {
+		let_assert!(Some(entry) = parse_entry("hunter2\nurl: https://example.com\nusername: alice\n"));
+		assert!(entry == Entry { password: "hunter2", username: Some("alice") });
+		let_assert!(Some(entry) = parse_entry("hunter2\r\n"));
+		assert!(entry == Entry { password: "hunter2", username: None });
+		assert!(let None = parse_entry("\nusername: alice\n"));
+		assert!(let None = parse_entry(""));
+	}
+}
@@ -0,0 +1,53 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use zeroize::Zeroizing;
+
+/// Passphrases of SSH keys that unlocked the key in an earlier operation, by private key path.
+///
+/// The cache is shared between all clones of an authenticator.
+/// See [`GitAuthenticator::cache_passphrases()`][crate::GitAuthenticator::cache_passphrases()].
+#[derive(Clone, Default)]
+pub(crate) struct PassphraseCache {
+	/// Map of private key paths to their passphrase.
+	passphrases: Arc<Mutex<BTreeMap<PathBuf, Zeroizing<String>>>>,
+}
+
+impl PassphraseCache {
+	/// Get the cached passphrase of a private key.
+	pub(crate) fn get(&self, private_key: &Path) -> Option<Zeroizing<String>> {
+		self.lock().get(private_key).cloned()
+	}
+
+	/// Remember the passphrase of a private key.
+	pub(crate) fn insert(&self, private_key: &Path, passphrase: Zeroizing<String>) {
+		self.lock().insert(private_key.to_owned(), passphrase);
+	}
+
+	/// Forget the passphrase of a private key, because it did not unlock the key.
+	#[cfg(feature = "decrypt-ssh-keys")]
+	pub(crate) fn remove(&self, private_key: &Path) {
+		self.lock().remove(private_key);
+	}
+
+	/// Forget all passphrases.
+	pub(crate) fn purge(&self) {
+		self.lock().clear();
+	}
+
+	/// Lock the map, ignoring poisoning.
+	///
+	/// The map is always in a valid state, even if a thread panicked while holding the lock.
+	fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, Zeroizing<String>>> {
+		self.passphrases.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl std::fmt::Debug for PassphraseCache {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_set()
+			.entries(self.lock().keys())
+			.finish()
+	}
+}
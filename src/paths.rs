@@ -0,0 +1,99 @@
+//! Discovery of the directories and files that the authenticator reads.
+
+use std::path::PathBuf;
+
+/// Source of the well-known directories and files that the authenticator looks for.
+///
+/// All path discovery goes through this trait: the default SSH keys, the `.netrc` file, the files of the `store` credential helper and the socket of the SSH agent.
+/// By default, the authenticator uses [`OsPaths`], which reads the usual environment variables.
+/// You can set a different implementation with [`GitAuthenticator::set_paths()`][crate::GitAuthenticator::set_paths()],
+/// to redirect all lookups of sandboxed applications or tests in one place.
+///
+/// A home directory set with [`GitAuthenticator::home_dir()`][crate::GitAuthenticator::home_dir()]
+/// or an SSH directory set with [`GitAuthenticator::ssh_dir()`][crate::GitAuthenticator::ssh_dir()] takes precedence over this trait.
+/// Note that libgit2 finds the global git configuration by itself.
+pub trait Paths: Send + Sync {
+	/// Get the home directory of the user.
+	fn home_dir(&self) -> Option<PathBuf>;
+
+	/// Get the base directory for configuration files, normally `$XDG_CONFIG_HOME`.
+	///
+	/// If this returns `None`, the `.config` directory in the home directory is used.
+	fn config_home(&self) -> Option<PathBuf>;
+
+	/// Get the SSH directory if it is set explicitly, normally with the `SSH_HOME` environment variable.
+	///
+	/// If this returns `None`, the SSH directory is found in the home directory (see [`GitAuthenticator::ssh_dir()`][crate::GitAuthenticator::ssh_dir()]).
+	fn ssh_home(&self) -> Option<PathBuf>;
+
+	/// Get the path of the `.netrc` file if it is set explicitly, normally with the `NETRC` environment variable.
+	///
+	/// If this returns `None`, the `.netrc` file in the home directory is used.
+	fn netrc_file(&self) -> Option<PathBuf>;
+
+	/// Get the socket of the SSH agent, normally from the `SSH_AUTH_SOCK` environment variable.
+	///
+	/// This is only used to list the keys of the agent (see [`GitAuthenticator::try_ssh_agent_key()`][crate::GitAuthenticator::try_ssh_agent_key()]):
+	/// libgit2 finds the agent by itself.
+	/// On Windows, the named pipe of the Windows OpenSSH agent is used if this returns `None`.
+	fn ssh_auth_sock(&self) -> Option<PathBuf> {
+		non_empty_var("SSH_AUTH_SOCK")
+	}
+}
+
+/// The default [`Paths`], which uses the environment of the current process.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct OsPaths;
+
+impl Paths for OsPaths {
+	fn home_dir(&self) -> Option<PathBuf> {
+		dirs::home_dir()
+	}
+
+	fn config_home(&self) -> Option<PathBuf> {
+		non_empty_var("XDG_CONFIG_HOME")
+	}
+
+	fn ssh_home(&self) -> Option<PathBuf> {
+		non_empty_var("SSH_HOME")
+	}
+
+	fn netrc_file(&self) -> Option<PathBuf> {
+		std::env::var_os("NETRC").map(PathBuf::from)
+	}
+}
+
+/// [`Paths`] that finds nothing, used when the environment is ignored.
+///
+/// See [`GitAuthenticator::ignore_environment()`][crate::GitAuthenticator::ignore_environment()].
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct NoPaths;
+
+impl Paths for NoPaths {
+	fn home_dir(&self) -> Option<PathBuf> {
+		None
+	}
+
+	fn config_home(&self) -> Option<PathBuf> {
+		None
+	}
+
+	fn ssh_home(&self) -> Option<PathBuf> {
+		None
+	}
+
+	fn netrc_file(&self) -> Option<PathBuf> {
+		None
+	}
+
+	fn ssh_auth_sock(&self) -> Option<PathBuf> {
+		None
+	}
+}
+
+/// Get the value of an environment variable as path, if it is set and not empty.
+fn non_empty_var(name: &str) -> Option<PathBuf> {
+	std::env::var_os(name)
+		.filter(|x| !x.is_empty())
+		.map(PathBuf::from)
+}
@@ -0,0 +1,81 @@
+//! Detection of PuTTY's `plink` as configured SSH client.
+//!
+//! TortoiseGit and other Windows tools often configure `GIT_SSH` to point to `plink.exe` or `TortoisePlink.exe`.
+//! With that setup, keys are normally stored in PPK format and loaded into Pageant.
+//! libgit2 can not use PPK files directly, but it can use the keys from Pageant through the SSH agent.
+
+use std::path::{Path, PathBuf};
+
+/// Get the configured SSH program if it is `plink` or `TortoisePlink`.
+///
+/// This checks the `GIT_SSH_COMMAND` and `GIT_SSH` environment variables and the `core.sshCommand` configuration value,
+/// in the same order of precedence as git.
+/// The `env` function gets the value of an environment variable.
+pub(crate) fn configured_plink(git_config: Option<&git2::Config>, env: impl Fn(&str) -> Option<String>) -> Option<String> {
+	if let Some(command) = env("GIT_SSH_COMMAND") {
+		Some(command).filter(|x| is_plink_command(x))
+	} else if let Some(program) = env("GIT_SSH") {
+		Some(program).filter(|x| is_plink_program(x))
+	} else {
+		git_config?.get_string("core.sshCommand").ok()
+			.filter(|x| is_plink_command(x))
+	}
+}
+
+/// Check if a shell command for SSH runs `plink` or `TortoisePlink`.
+pub(crate) fn is_plink_command(command: &str) -> bool {
+	let command = command.trim_start();
+	let program = match command.strip_prefix('"') {
+		Some(quoted) => quoted.split('"').next().unwrap_or(quoted),
+		None => command.split(char::is_whitespace).next().unwrap_or(command),
+	};
+	is_plink_program(program)
+}
+
+/// Check if the path of an SSH program refers to `plink` or `TortoisePlink`.
+pub(crate) fn is_plink_program(program: &str) -> bool {
+	let name = program.rsplit(['/', '\\']).next().unwrap_or(program);
+	let name = name.to_ascii_lowercase();
+	let name = name.strip_suffix(".exe").unwrap_or(&name);
+	name == "plink" || name == "tortoiseplink"
+}
+
+/// Find PuTTY private key files in a directory.
+pub(crate) fn find_ppk_files(dir: &Path) -> Vec<PathBuf> {
+	let entries = match std::fs::read_dir(dir) {
+		Ok(x) => x,
+		Err(_) => return Vec::new(),
+	};
+	let mut files: Vec<_> = entries
+		.filter_map(|entry| entry.ok())
+		.map(|entry| entry.path())
+		.filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ppk")))
+		.filter(|path| path.is_file())
+		.collect();
+	files.sort();
+	files
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_is_plink_command() {
+		assert!(is_plink_command("plink"));
+		assert!(is_plink_command("plink.exe -batch"));
+		assert!(is_plink_command("\"C:\\Program Files\\PuTTY\\PLINK.EXE\" -ssh"));
+		assert!(is_plink_command("/usr/bin/plink"));
+		assert!(!is_plink_command("ssh"));
+		assert!(!is_plink_command("ssh -i plink"));
+		assert!(!is_plink_command("/opt/plink-tools/ssh"));
+	}
+
+	#[test]
+	fn test_is_plink_program() {
+		assert!(is_plink_program("C:\\Program Files\\TortoiseGit\\bin\\TortoisePlink.exe"));
+		assert!(is_plink_program("plink"));
+		assert!(!is_plink_program("C:\\Program Files\\Git\\usr\\bin\\ssh.exe"));
+	}
+}
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::{Error, GitAuthenticator};
+
+/// Function that picks the name of the profile to use for a URL.
+type Resolver = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+/// A set of named authenticator profiles, with a resolver that picks a profile for a URL.
+///
+/// This allows a single process to use different authentication configurations for different remotes.
+/// For example, an IDE could use a `"work"` profile for repositories of an employer and a `"personal"` profile for everything else.
+///
+/// # Example
+/// ```
+/// # use auth_git2::{GitAuthenticator, Profiles};
+/// let profiles = Profiles::new()
+///     .add_profile("work", GitAuthenticator::new_empty().add_username("*", "j.doe"))
+///     .add_profile("personal", GitAuthenticator::default())
+///     .set_resolver(|url: &str| {
+///         if url.contains("git.example.com") {
+///             Some("work".into())
+///         } else {
+///             None
+///         }
+///     })
+///     .default_profile("personal");
+///
+/// assert!(profiles.resolve("ssh://git.example.com/team/repo.git").unwrap().0 == "work");
+/// assert!(profiles.resolve("https://github.com/de-vri-es/auth-git2-rs").unwrap().0 == "personal");
+/// ```
+#[derive(Clone, Default)]
+pub struct Profiles {
+	/// The profiles by name.
+	profiles: BTreeMap<String, GitAuthenticator>,
+
+	/// Function that picks the name of the profile to use for a URL.
+	resolver: Option<Arc<Resolver>>,
+
+	/// The profile to use if the resolver does not pick one.
+	default_profile: Option<String>,
+}
+
+impl std::fmt::Debug for Profiles {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Profiles")
+			.field("profiles", &self.profiles)
+			.field("resolver", &self.resolver.is_some())
+			.field("default_profile", &self.default_profile)
+			.finish()
+	}
+}
+
+impl Profiles {
+	/// Create a new empty set of profiles.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add a named profile.
+	///
+	/// If a profile with the same name already exists, it is replaced.
+	pub fn add_profile(mut self, name: impl Into<String>, authenticator: GitAuthenticator) -> Self {
+		self.profiles.insert(name.into(), authenticator);
+		self
+	}
+
+	/// Set the function that picks the name of the profile to use for a URL.
+	///
+	/// If the resolver returns `None`, or the name of a profile that does not exist, the default profile is used.
+	/// The resolver is called with the URL as given by libgit2, or as passed to [`Self::resolve()`].
+	pub fn set_resolver<F>(mut self, resolver: F) -> Self
+	where
+		F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+	{
+		self.resolver = Some(Arc::new(resolver));
+		self
+	}
+
+	/// Set the name of the profile to use when the resolver does not pick one.
+	pub fn default_profile(mut self, name: impl Into<String>) -> Self {
+		self.default_profile = Some(name.into());
+		self
+	}
+
+	/// Get a profile by name.
+	pub fn profile(&self, name: &str) -> Option<&GitAuthenticator> {
+		self.profiles.get(name)
+	}
+
+	/// Get the name and the authenticator of the profile to use for a URL.
+	///
+	/// Returns `None` if the resolver does not pick an existing profile and there is no default profile.
+	pub fn resolve(&self, url: &str) -> Option<(&str, &GitAuthenticator)> {
+		if let Some(resolver) = &self.resolver {
+			if let Some(name) = resolver(url) {
+				match self.profiles.get_key_value(name.as_str()) {
+					Some((name, authenticator)) => return Some((name, authenticator)),
+					None => warn!("Authentication profile {name:?} picked for {url:?} does not exist, using the default profile"),
+				}
+			}
+		}
+		let name = self.default_profile.as_deref()?;
+		let (name, authenticator) = self.profiles.get_key_value(name)?;
+		Some((name, authenticator))
+	}
+
+	/// Get the authenticator of the profile to use for a remote.
+	///
+	/// Returns `None` if the remote has no valid UTF-8 URL, or if no profile applies to the URL.
+	pub fn for_remote(&self, remote: &git2::Remote) -> Option<&GitAuthenticator> {
+		let (_name, authenticator) = self.resolve(remote.url()?)?;
+		Some(authenticator)
+	}
+
+	/// Get a [`git2::Credentials`] callback that uses the profile picked for each URL.
+	///
+	/// The profile is resolved every time the callback is called,
+	/// so a single callback can be used for operations that involve remotes with different profiles.
+	pub fn credentials<'a>(
+		&'a self,
+		git_config: &'a git2::Config,
+	) -> impl 'a + FnMut(&str, Option<&str>, git2::CredentialType) -> Result<git2::Cred, git2::Error> {
+		let mut callbacks = BTreeMap::new();
+		move |url: &str, username: Option<&str>, allowed: git2::CredentialType| {
+			let (name, authenticator) = self.resolve(url)
+				.ok_or_else(|| git2::Error::from_str(&format!("no authentication profile applies to {url}")))?;
+			debug!("credentials_callback: using authentication profile {name:?} for {url:?}");
+			let callback = callbacks.entry(name)
+				.or_insert_with(|| authenticator.credentials(git_config));
+			callback(url, username, allowed)
+		}
+	}
+
+	/// Clone a repository using the profile picked for the URL.
+	///
+	/// See [`GitAuthenticator::clone_repo()`].
+	pub fn clone_repo(&self, url: impl AsRef<str>, into: impl AsRef<Path>) -> Result<git2::Repository, Error> {
+		let url = url.as_ref();
+		let (_name, authenticator) = self.resolve(url)
+			.ok_or_else(|| git2::Error::from_str(&format!("no authentication profile applies to {url}")))?;
+		authenticator.clone_repo(url, into)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_resolve() {
+		let profiles = Profiles::new()
+			.add_profile("work", GitAuthenticator::new_empty().add_username("*", "work"))
+			.add_profile("ci", GitAuthenticator::new_empty().add_username("*", "ci"))
+			.set_resolver(|url: &str| {
+				if url.contains("work.example.com") {
+					Some("work".into())
+				} else if url.contains("ci.example.com") {
+					Some("missing".into())
+				} else {
+					None
+				}
+			});
+
+		let_assert!(Some(("work", authenticator)) = profiles.resolve("ssh://work.example.com/repo.git"));
+		assert!(let Some("work") = authenticator.get_username("ssh://work.example.com/repo.git", false));
+		assert!(let None = profiles.resolve("ssh://ci.example.com/repo.git"));
+		assert!(let None = profiles.resolve("ssh://example.org/repo.git"));
+
+		let profiles = profiles.default_profile("ci");
+		assert!(let Some(("ci", _)) = profiles.resolve("ssh://ci.example.com/repo.git"));
+		assert!(let Some(("ci", _)) = profiles.resolve("ssh://example.org/repo.git"));
+		assert!(let Some(("work", _)) = profiles.resolve("ssh://work.example.com/repo.git"));
+	}
+}
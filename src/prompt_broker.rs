@@ -0,0 +1,490 @@
+//! Inter-process prompt broker.
+//!
+//! Applications with multiple worker processes can use a broker to show all prompts in a single UI process.
+//! The UI process runs a [`PromptBroker`] with its prompter,
+//! and the worker processes use a [`BrokerPrompter`] that forwards prompts to the broker.
+
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::{PromptContext, PromptError, Prompter};
+use crate::prompter::ClonePrompter;
+use crate::temp_dir::TempDir;
+use crate::token::token_matches;
+
+/// Environment variable with the address of the broker.
+const ADDRESS_VAR: &str = "AUTH_GIT2_PROMPT_BROKER";
+
+/// Environment variable with the secret token of the broker.
+const TOKEN_VAR: &str = "AUTH_GIT2_PROMPT_BROKER_TOKEN";
+
+/// Broker that shows prompts on behalf of other processes.
+///
+/// The broker listens on a Unix socket in a private temporary directory (on Unix),
+/// on a named pipe (on Windows), or on a TCP socket bound to the loopback interface (on other platforms).
+/// Clients must send a secret token with every request, so only processes that were given the token can use the broker.
+///
+/// Prompts are handled one at a time, in the order they arrive.
+/// The broker stops when it is dropped.
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use auth_git2::{BrokerPrompter, GitAuthenticator};
+///
+/// // In the UI process:
+/// let broker = GitAuthenticator::default().prompt_broker()?;
+/// let mut worker = std::process::Command::new("my-worker");
+/// worker.envs(broker.envs());
+/// worker.spawn()?;
+///
+/// // In the worker process:
+/// if let Some(prompter) = BrokerPrompter::from_env() {
+///     let auth = GitAuthenticator::default().set_prompter(prompter);
+/// #   let _ = auth;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct PromptBroker {
+	/// The address of the broker.
+	address: String,
+
+	/// The secret token that clients must send.
+	token: String,
+
+	/// Private directory with the socket, removed when the broker is dropped.
+	_socket_dir: Option<TempDir>,
+
+	/// Flag to tell the server thread to stop.
+	stop: Arc<AtomicBool>,
+
+	/// The server thread.
+	thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for PromptBroker {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("PromptBroker")
+			.field("address", &self.address)
+			.finish_non_exhaustive()
+	}
+}
+
+impl PromptBroker {
+	/// Start a broker that shows prompts with the given prompter.
+	pub fn start<P>(prompter: P) -> std::io::Result<Self>
+	where
+		P: Prompter + Clone + 'static,
+	{
+		Self::start_boxed(crate::prompter::wrap_prompter(prompter))
+	}
+
+	/// Start a broker with a boxed prompter.
+	pub(crate) fn start_boxed(prompter: Box<dyn ClonePrompter>) -> std::io::Result<Self> {
+		let (listener, address, socket_dir) = Listener::bind()?;
+		let token = crate::token::random_token()?;
+		let stop = Arc::new(AtomicBool::new(false));
+		let thread = std::thread::Builder::new()
+			.name("auth-git2-prompt-broker".into())
+			.spawn({
+				let token = token.clone();
+				let stop = stop.clone();
+				move || serve(listener, &token, &stop, prompter)
+			})?;
+		Ok(Self {
+			address,
+			token,
+			_socket_dir: socket_dir,
+			stop,
+			thread: Some(thread),
+		})
+	}
+
+	/// Get the address of the broker.
+	pub fn address(&self) -> &str {
+		&self.address
+	}
+
+	/// Get the secret token of the broker.
+	///
+	/// Pass the token to worker processes in a way that other users can not see,
+	/// like the environment variables from [`Self::envs()`].
+	pub fn token(&self) -> &str {
+		&self.token
+	}
+
+	/// Get the environment variables that tell [`BrokerPrompter::from_env()`] how to reach the broker.
+	pub fn envs(&self) -> Vec<(OsString, OsString)> {
+		vec![
+			(ADDRESS_VAR.into(), self.address.clone().into()),
+			(TOKEN_VAR.into(), self.token.clone().into()),
+		]
+	}
+
+	/// Make a prompter that forwards prompts to this broker.
+	pub fn prompter(&self) -> BrokerPrompter {
+		BrokerPrompter::new(self.address.clone(), self.token.clone())
+	}
+}
+
+impl Drop for PromptBroker {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::SeqCst);
+		// Wake up the server thread.
+		let _ = connect(&self.address);
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Prompter that forwards prompts to a [`PromptBroker`] in another process.
+///
+/// If the broker can not be reached, the prompt fails and a warning is logged.
+#[derive(Clone)]
+pub struct BrokerPrompter {
+	/// The address of the broker.
+	address: String,
+
+	/// The secret token of the broker.
+	token: String,
+}
+
+impl std::fmt::Debug for BrokerPrompter {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("BrokerPrompter")
+			.field("address", &self.address)
+			.finish_non_exhaustive()
+	}
+}
+
+impl BrokerPrompter {
+	/// Create a prompter for the broker with the given address and token.
+	pub fn new(address: impl Into<String>, token: impl Into<String>) -> Self {
+		Self {
+			address: address.into(),
+			token: token.into(),
+		}
+	}
+
+	/// Create a prompter from the environment variables set by [`PromptBroker::envs()`].
+	///
+	/// Returns `None` if the variables are not set.
+	pub fn from_env() -> Option<Self> {
+		let address = std::env::var(ADDRESS_VAR).ok()?;
+		let token = std::env::var(TOKEN_VAR).ok()?;
+		Some(Self::new(address, token))
+	}
+
+	/// Send a request to the broker and get the lines of the answer.
+	fn request(&self, kind: &str, fields: &[&str], answer_lines: usize) -> Option<Vec<String>> {
+		self.try_request(kind, fields, answer_lines).ok()
+	}
+
+	/// Send a request to the broker and get the lines of the answer, or tell why there is no answer.
+	///
+	/// If the broker can not be reached, the user could not be prompted.
+	fn try_request(&self, kind: &str, fields: &[&str], answer_lines: usize) -> Result<Vec<String>, PromptError> {
+		if fields.iter().any(|x| x.contains('\n')) {
+			warn!("Can not forward prompt with a newline in the request to the prompt broker");
+			return Err(PromptError::Unavailable);
+		}
+		request(&self.address, &self.token, kind, fields, answer_lines)
+			.map_err(|e| {
+				warn!("Failed to forward prompt to the prompt broker at {}: {e}", self.address);
+				PromptError::Unavailable
+			})?
+			.ok_or(PromptError::Declined)
+	}
+}
+
+impl Prompter for BrokerPrompter {
+	fn prompt_username_password(&mut self, url: &str, _git_config: &git2::Config) -> Option<(String, String)> {
+		let mut answer = self.request("username-password", &[url], 2)?.into_iter();
+		Some((answer.next()?, answer.next()?))
+	}
+
+	fn prompt_password(&mut self, username: &str, url: &str, _git_config: &git2::Config) -> Option<String> {
+		self.request("password", &[username, url], 1)?.pop()
+	}
+
+	fn prompt_ssh_key_passphrase(&mut self, private_key_path: &Path, _git_config: &git2::Config) -> Option<String> {
+		let path = private_key_path.to_str()?;
+		self.request("ssh-key-passphrase", &[path], 1)?.pop()
+	}
+
+	fn show_device_code(&mut self, url: &str, verification_uri: &str, user_code: &str, _git_config: &git2::Config) -> bool {
+		self.request("device-code", &[url, verification_uri, user_code], 0).is_some()
+	}
+
+	fn prompt_password_manager_unlock(&mut self, password_manager: &str, _git_config: &git2::Config) -> Option<String> {
+		self.request("password-manager-unlock", &[password_manager], 1)?.pop()
+	}
+
+	fn notify_security_key_touch(&mut self, key: &str, _git_config: &git2::Config) {
+		self.request("security-key-touch", &[key], 0);
+	}
+
+	fn try_prompt_username_password(&mut self, url: &str, _context: &PromptContext, _git_config: &git2::Config) -> Result<(String, String), PromptError> {
+		let mut answer = self.try_request("username-password", &[url], 2)?.into_iter();
+		answer.next().zip(answer.next()).ok_or(PromptError::Declined)
+	}
+
+	fn try_prompt_password(&mut self, username: &str, url: &str, _context: &PromptContext, _git_config: &git2::Config) -> Result<String, PromptError> {
+		self.try_request("password", &[username, url], 1)?.pop().ok_or(PromptError::Declined)
+	}
+
+	fn try_prompt_ssh_key_passphrase(&mut self, private_key_path: &Path, _git_config: &git2::Config) -> Result<String, PromptError> {
+		let path = private_key_path.to_str().ok_or(PromptError::Unavailable)?;
+		self.try_request("ssh-key-passphrase", &[path], 1)?.pop().ok_or(PromptError::Declined)
+	}
+}
+
+/// A listening socket.
+enum Listener {
+	#[cfg(unix)]
+	Unix(std::os::unix::net::UnixListener),
+	#[cfg(windows)]
+	Pipe(crate::named_pipe::PipeListener),
+	#[cfg(not(any(unix, windows)))]
+	Tcp(std::net::TcpListener),
+}
+
+/// A connected socket.
+trait Stream: Read + Write {
+	fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()>;
+}
+
+#[cfg(unix)]
+impl Stream for std::os::unix::net::UnixStream {
+	fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+		std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+	}
+}
+
+/// A connected named pipe.
+#[cfg(windows)]
+impl Stream for std::fs::File {
+	/// Named pipes opened for synchronous I/O do not support read timeouts,
+	/// but only processes of the same user can connect to the pipe.
+	fn set_read_timeout(&self, _timeout: Option<Duration>) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+#[cfg(not(any(unix, windows)))]
+impl Stream for std::net::TcpStream {
+	fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+		std::net::TcpStream::set_read_timeout(self, timeout)
+	}
+}
+
+impl Listener {
+	/// Bind a new listening socket.
+	///
+	/// Returns the listener, its address and the directory to remove when the broker stops.
+	#[cfg(unix)]
+	fn bind() -> std::io::Result<(Self, String, Option<TempDir>)> {
+		let dir = TempDir::create("auth-git2-broker")?;
+		let path = dir.path().join("socket");
+		let listener = std::os::unix::net::UnixListener::bind(&path)?;
+		Ok((Self::Unix(listener), format!("unix:{}", path.display()), Some(dir)))
+	}
+
+	/// Bind a new listening socket.
+	///
+	/// Returns the listener, its address and the directory to remove when the broker stops.
+	#[cfg(windows)]
+	fn bind() -> std::io::Result<(Self, String, Option<TempDir>)> {
+		let name = format!("auth-git2-broker-{}-{}", std::process::id(), &crate::token::random_token()?[..16]);
+		let listener = crate::named_pipe::PipeListener::bind(&name)?;
+		Ok((Self::Pipe(listener), format!("pipe:{name}"), None))
+	}
+
+	/// Bind a new listening socket.
+	///
+	/// Returns the listener, its address and the directory to remove when the broker stops.
+	#[cfg(not(any(unix, windows)))]
+	fn bind() -> std::io::Result<(Self, String, Option<TempDir>)> {
+		let listener = std::net::TcpListener::bind(("127.0.0.1", 0))?;
+		let address = listener.local_addr()?;
+		Ok((Self::Tcp(listener), format!("tcp:{address}"), None))
+	}
+
+	/// Accept a new connection.
+	fn accept(&mut self) -> std::io::Result<Box<dyn Stream>> {
+		match self {
+			#[cfg(unix)]
+			Self::Unix(listener) => Ok(Box::new(listener.accept()?.0)),
+			#[cfg(windows)]
+			Self::Pipe(listener) => Ok(Box::new(listener.accept()?)),
+			#[cfg(not(any(unix, windows)))]
+			Self::Tcp(listener) => Ok(Box::new(listener.accept()?.0)),
+		}
+	}
+}
+
+/// Connect to a broker.
+fn connect(address: &str) -> std::io::Result<Box<dyn Stream>> {
+	#[cfg(unix)]
+	if let Some(path) = address.strip_prefix("unix:") {
+		return Ok(Box::new(std::os::unix::net::UnixStream::connect(path)?));
+	}
+	#[cfg(windows)]
+	if let Some(name) = address.strip_prefix("pipe:") {
+		return Ok(Box::new(crate::named_pipe::connect(name)?));
+	}
+	#[cfg(not(any(unix, windows)))]
+	if let Some(address) = address.strip_prefix("tcp:") {
+		return Ok(Box::new(std::net::TcpStream::connect(address)?));
+	}
+	Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("unsupported prompt broker address: {address}")))
+}
+
+/// Send a request to a broker.
+///
+/// Returns `None` if the prompt was cancelled.
+fn request(address: &str, token: &str, kind: &str, fields: &[&str], answer_lines: usize) -> std::io::Result<Option<Vec<String>>> {
+	let mut stream = connect(address)?;
+	writeln!(stream, "{token}")?;
+	writeln!(stream, "{kind}")?;
+	for field in fields {
+		writeln!(stream, "{field}")?;
+	}
+	stream.flush()?;
+	let mut reader = BufReader::new(stream);
+	let mut status = String::new();
+	reader.read_line(&mut status)?;
+	if status.trim_end() != "ok" {
+		return Ok(None);
+	}
+	let mut answer = Vec::with_capacity(answer_lines);
+	for _ in 0..answer_lines {
+		let mut line = String::new();
+		reader.read_line(&mut line)?;
+		answer.push(line.trim_end_matches(['\r', '\n']).to_owned());
+	}
+	Ok(Some(answer))
+}
+
+/// Serve prompt requests until the stop flag is set.
+fn serve(mut listener: Listener, token: &str, stop: &AtomicBool, mut prompter: Box<dyn ClonePrompter>) {
+	loop {
+		let stream = listener.accept();
+		if stop.load(Ordering::SeqCst) {
+			break;
+		}
+		let stream = match stream {
+			Ok(x) => x,
+			Err(e) => {
+				debug!("prompt broker: failed to accept connection: {e}");
+				continue;
+			},
+		};
+		if let Err(e) = handle(stream, token, prompter.as_prompter_mut()) {
+			debug!("prompt broker: failed to handle request: {e}");
+		}
+	}
+}
+
+/// Handle a single prompt request.
+fn handle(stream: Box<dyn Stream>, token: &str, prompter: &mut dyn Prompter) -> std::io::Result<()> {
+	// Do not let a misbehaving client block the broker forever.
+	stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+	let mut reader = BufReader::new(stream);
+	let mut read_line = || -> std::io::Result<String> {
+		let mut line = String::new();
+		reader.read_line(&mut line)?;
+		Ok(line.trim_end_matches(['\r', '\n']).to_owned())
+	};
+
+	if !token_matches(token, &read_line()?) {
+		warn!("prompt broker: rejected connection with invalid token");
+		return Ok(());
+	}
+
+	let git_config = crate::env_config::open_default()
+		.map_err(|e| std::io::Error::other(format!("failed to open git config: {e}")))?;
+	let answer = match read_line()?.as_str() {
+		"username-password" => {
+			let url = read_line()?;
+			prompter.prompt_username_password(&url, &git_config)
+				.map(|(username, password)| vec![username, password])
+		},
+		"password" => {
+			let username = read_line()?;
+			let url = read_line()?;
+			prompter.prompt_password(&username, &url, &git_config)
+				.map(|password| vec![password])
+		},
+		"ssh-key-passphrase" => {
+			let path = read_line()?;
+			prompter.prompt_ssh_key_passphrase(Path::new(&path), &git_config)
+				.map(|passphrase| vec![passphrase])
+		},
+		"device-code" => {
+			let url = read_line()?;
+			let verification_uri = read_line()?;
+			let user_code = read_line()?;
+			prompter.show_device_code(&url, &verification_uri, &user_code, &git_config)
+				.then(Vec::new)
+		},
+		"password-manager-unlock" => {
+			let password_manager = read_line()?;
+			prompter.prompt_password_manager_unlock(&password_manager, &git_config)
+				.map(|password| vec![password])
+		},
+		"security-key-touch" => {
+			let key = read_line()?;
+			prompter.notify_security_key_touch(&key, &git_config);
+			Some(Vec::new())
+		},
+		kind => {
+			warn!("prompt broker: unknown request kind: {kind:?}");
+			None
+		},
+	};
+
+	let mut stream = reader.into_inner();
+	match answer {
+		Some(lines) if !lines.iter().any(|x| x.contains('\n')) => {
+			writeln!(stream, "ok")?;
+			for line in lines {
+				writeln!(stream, "{line}")?;
+			}
+		},
+		_ => writeln!(stream, "cancel")?,
+	}
+	stream.flush()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+	use crate::test_prompter::FixedPrompter;
+
+	#[test]
+	fn test_broker() {
+		let_assert!(Ok(git_config) = git2::Config::new());
+		let_assert!(Ok(broker) = PromptBroker::start(FixedPrompter));
+		let mut prompter = broker.prompter();
+
+		let_assert!(Some((username, password)) = prompter.prompt_username_password("https://example.com", &git_config));
+		assert!(username == "alice");
+		assert!(password == "hunter2");
+		assert!(let Some("password-of-git") = prompter.prompt_password("git", "https://example.com", &git_config).as_deref());
+		assert!(let None = prompter.prompt_ssh_key_passphrase(Path::new("/id_ed25519"), &git_config));
+
+		let mut wrong_token = BrokerPrompter::new(broker.address(), "wrong");
+		assert!(let None = wrong_token.prompt_password("git", "https://example.com", &git_config));
+	}
+}
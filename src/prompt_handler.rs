@@ -0,0 +1,330 @@
+//! Single-method alternative to the [`Prompter`] trait.
+//!
+//! Message-based user interfaces handle all prompts in the same way: send a request to the UI and wait for the answer.
+//! A [`PromptHandler`] receives every prompt as a [`PromptRequest`], so it can be forwarded without a method per prompt.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::{PromptContext, Prompter};
+
+/// Trait for handling all user prompts in a single method.
+///
+/// This is an alternative to implementing [`Prompter`], that is easier to bridge to message-based user interfaces.
+/// Use [`GitAuthenticator::set_prompt_handler()`][crate::GitAuthenticator::set_prompt_handler()] to use a handler.
+///
+/// If the handler returns `None`, the authenticator treats it as if the user cancelled the prompt.
+/// A response that does not match the [`PromptKind`] of the request is also treated as a cancelled prompt.
+///
+/// # Example
+/// ```
+/// use auth_git2::{GitAuthenticator, PromptHandler, PromptKind, PromptRequest, PromptResponse};
+///
+/// #[derive(Clone)]
+/// struct MyHandler;
+///
+/// impl PromptHandler for MyHandler {
+///     fn prompt(&mut self, request: PromptRequest) -> Option<PromptResponse> {
+///         match request.kind {
+///             PromptKind::UsernamePassword => Some(PromptResponse::UsernamePassword {
+///                 username: "alice".into(),
+///                 password: "hunter2".into(),
+///             }),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let auth = GitAuthenticator::default().set_prompt_handler(MyHandler);
+/// # let _ = auth;
+/// ```
+pub trait PromptHandler: Send {
+	/// Prompt the user, or show a message to the user.
+	///
+	/// If the prompt fails or the user fails to provide the requested information, this function should return `None`.
+	fn prompt(&mut self, request: PromptRequest) -> Option<PromptResponse>;
+}
+
+/// The kind of a [`PromptRequest`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PromptKind {
+	/// Prompt for a username and password for [`PromptRequest::url`].
+	///
+	/// Answer with [`PromptResponse::UsernamePassword`].
+	UsernamePassword,
+
+	/// Prompt for the password of [`PromptRequest::username`] for [`PromptRequest::url`].
+	///
+	/// Answer with [`PromptResponse::Secret`].
+	Password,
+
+	/// Prompt for the passphrase of the encrypted SSH key at [`PromptRequest::private_key`].
+	///
+	/// Answer with [`PromptResponse::Secret`].
+	SshPassphrase,
+
+	/// Show the user [`PromptRequest::user_code`] to enter at [`PromptRequest::verification_uri`] in a browser.
+	///
+	/// This is used for the OAuth device authorization flow.
+	/// Answer with [`PromptResponse::Acknowledged`] as soon as the code is shown, without waiting for the user.
+	DeviceCode,
+
+	/// Prompt for the master password of [`PromptRequest::password_manager`], like `"Bitwarden"`.
+	///
+	/// Answer with [`PromptResponse::Secret`].
+	PasswordManagerUnlock,
+
+	/// Tell the user that they may have to touch the security key described by [`PromptRequest::security_key`].
+	///
+	/// The response is ignored.
+	SecurityKeyTouch,
+}
+
+/// A prompt for a [`PromptHandler`].
+///
+/// The fields that are set depend on the [`PromptKind`].
+/// The request owns its data, so it can be sent to another thread.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct PromptRequest {
+	/// The kind of prompt.
+	pub kind: PromptKind,
+
+	/// The URL that credentials are needed for.
+	pub url: Option<String>,
+
+	/// The username that is already known.
+	pub username: Option<String>,
+
+	/// The path of the encrypted SSH key.
+	pub private_key: Option<PathBuf>,
+
+	/// The page where the user should enter the code of the OAuth device flow.
+	pub verification_uri: Option<String>,
+
+	/// The code of the OAuth device flow.
+	pub user_code: Option<String>,
+
+	/// The name of the password manager to unlock.
+	pub password_manager: Option<String>,
+
+	/// The key file or the SSH agent keys that are backed by a security key.
+	pub security_key: Option<String>,
+
+	/// The number of the prompt and the remaining attempts, for username and password prompts of the credentials callback.
+	pub context: Option<PromptContext>,
+}
+
+impl PromptRequest {
+	/// Make a request without any details.
+	fn new(kind: PromptKind) -> Self {
+		Self {
+			kind,
+			url: None,
+			username: None,
+			private_key: None,
+			verification_uri: None,
+			user_code: None,
+			password_manager: None,
+			security_key: None,
+			context: None,
+		}
+	}
+}
+
+/// The answer of a [`PromptHandler`] to a [`PromptRequest`].
+#[derive(Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PromptResponse {
+	/// A username and password, for [`PromptKind::UsernamePassword`].
+	UsernamePassword {
+		/// The username.
+		username: String,
+
+		/// The password or token.
+		password: String,
+	},
+
+	/// A password or passphrase, for [`PromptKind::Password`], [`PromptKind::SshPassphrase`] and [`PromptKind::PasswordManagerUnlock`].
+	Secret(String),
+
+	/// The message was shown to the user, for [`PromptKind::DeviceCode`] and [`PromptKind::SecurityKeyTouch`].
+	Acknowledged,
+}
+
+impl std::fmt::Debug for PromptResponse {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::UsernamePassword { username, password: _ } => f.debug_struct("UsernamePassword")
+				.field("username", username)
+				.finish_non_exhaustive(),
+			Self::Secret(_) => f.write_str("Secret(..)"),
+			Self::Acknowledged => f.write_str("Acknowledged"),
+		}
+	}
+}
+
+/// A [`Prompter`] that passes all prompts to a [`PromptHandler`].
+#[derive(Clone)]
+pub(crate) struct HandlerPrompter<H> {
+	handler: H,
+}
+
+impl<H: PromptHandler> HandlerPrompter<H> {
+	/// Wrap a prompt handler.
+	pub(crate) fn new(handler: H) -> Self {
+		Self { handler }
+	}
+
+	/// Pass a request to the handler and get the secret from the response.
+	fn secret(&mut self, request: PromptRequest) -> Option<String> {
+		let kind = request.kind;
+		match self.handler.prompt(request)? {
+			PromptResponse::Secret(secret) => Some(secret),
+			response => {
+				debug!("Prompt handler answered a {kind:?} prompt with {response:?}, treating it as cancelled");
+				None
+			},
+		}
+	}
+
+	/// Pass a username and password request to the handler.
+	fn username_password(&mut self, url: &str, context: Option<&PromptContext>) -> Option<(String, String)> {
+		let request = PromptRequest {
+			url: Some(url.into()),
+			context: context.copied(),
+			..PromptRequest::new(PromptKind::UsernamePassword)
+		};
+		match self.handler.prompt(request)? {
+			PromptResponse::UsernamePassword { username, password } => Some((username, password)),
+			response => {
+				debug!("Prompt handler answered a UsernamePassword prompt with {response:?}, treating it as cancelled");
+				None
+			},
+		}
+	}
+
+	/// Pass a password request to the handler.
+	fn password(&mut self, username: &str, url: &str, context: Option<&PromptContext>) -> Option<String> {
+		self.secret(PromptRequest {
+			url: Some(url.into()),
+			username: Some(username.into()),
+			context: context.copied(),
+			..PromptRequest::new(PromptKind::Password)
+		})
+	}
+}
+
+impl<H: PromptHandler> Prompter for HandlerPrompter<H> {
+	fn prompt_username_password(&mut self, url: &str, _git_config: &git2::Config) -> Option<(String, String)> {
+		self.username_password(url, None)
+	}
+
+	fn prompt_password(&mut self, username: &str, url: &str, _git_config: &git2::Config) -> Option<String> {
+		self.password(username, url, None)
+	}
+
+	fn prompt_ssh_key_passphrase(&mut self, private_key_path: &Path, _git_config: &git2::Config) -> Option<String> {
+		self.secret(PromptRequest {
+			private_key: Some(private_key_path.into()),
+			..PromptRequest::new(PromptKind::SshPassphrase)
+		})
+	}
+
+	fn show_device_code(&mut self, url: &str, verification_uri: &str, user_code: &str, _git_config: &git2::Config) -> bool {
+		let request = PromptRequest {
+			url: Some(url.into()),
+			verification_uri: Some(verification_uri.into()),
+			user_code: Some(user_code.into()),
+			..PromptRequest::new(PromptKind::DeviceCode)
+		};
+		matches!(self.handler.prompt(request), Some(PromptResponse::Acknowledged))
+	}
+
+	fn prompt_password_manager_unlock(&mut self, password_manager: &str, _git_config: &git2::Config) -> Option<String> {
+		self.secret(PromptRequest {
+			password_manager: Some(password_manager.into()),
+			..PromptRequest::new(PromptKind::PasswordManagerUnlock)
+		})
+	}
+
+	fn notify_security_key_touch(&mut self, key: &str, _git_config: &git2::Config) {
+		self.handler.prompt(PromptRequest {
+			security_key: Some(key.into()),
+			..PromptRequest::new(PromptKind::SecurityKeyTouch)
+		});
+	}
+
+	fn prompt_username_password_with_context(&mut self, url: &str, context: &PromptContext, _git_config: &git2::Config) -> Option<(String, String)> {
+		self.username_password(url, Some(context))
+	}
+
+	fn prompt_password_with_context(&mut self, username: &str, url: &str, context: &PromptContext, _git_config: &git2::Config) -> Option<String> {
+		self.password(username, url, Some(context))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+	use std::sync::{Arc, Mutex};
+
+	/// Handler that records the requests and answers with a fixed response.
+	#[derive(Clone)]
+	struct RecordingHandler {
+		requests: Arc<Mutex<Vec<PromptRequest>>>,
+		response: Option<PromptResponse>,
+	}
+
+	impl PromptHandler for RecordingHandler {
+		fn prompt(&mut self, request: PromptRequest) -> Option<PromptResponse> {
+			self.requests.lock().unwrap().push(request);
+			self.response.clone()
+		}
+	}
+
+	fn handler_prompter(response: Option<PromptResponse>) -> (HandlerPrompter<RecordingHandler>, Arc<Mutex<Vec<PromptRequest>>>) {
+		let requests = Arc::new(Mutex::new(Vec::new()));
+		(HandlerPrompter::new(RecordingHandler { requests: requests.clone(), response }), requests)
+	}
+
+	#[test]
+	fn test_handler_prompter() {
+		let git_config = git2::Config::new().unwrap();
+
+		let (mut prompter, requests) = handler_prompter(Some(PromptResponse::Secret("hunter2".into())));
+		let context = PromptContext::new(1, 2);
+		assert!(let Some("hunter2") = prompter.prompt_password_with_context("alice", "https://example.com/repo", &context, &git_config).as_deref());
+		assert!(let Some("hunter2") = prompter.prompt_ssh_key_passphrase(Path::new("/home/alice/.ssh/id_ed25519"), &git_config).as_deref());
+		assert!(let Some("hunter2") = prompter.prompt_password_manager_unlock("Bitwarden", &git_config).as_deref());
+		let requests = std::mem::take(&mut *requests.lock().unwrap());
+		let_assert!([password, passphrase, unlock] = &requests[..]);
+		assert!(password.kind == PromptKind::Password);
+		assert!(password.username.as_deref() == Some("alice"));
+		assert!(password.url.as_deref() == Some("https://example.com/repo"));
+		assert!(password.context == Some(context));
+		assert!(passphrase.kind == PromptKind::SshPassphrase);
+		assert!(passphrase.private_key.as_deref() == Some(Path::new("/home/alice/.ssh/id_ed25519")));
+		assert!(unlock.kind == PromptKind::PasswordManagerUnlock);
+		assert!(unlock.password_manager.as_deref() == Some("Bitwarden"));
+
+		// A response of the wrong kind is treated as a cancelled prompt.
+		assert!(let None = prompter.prompt_username_password("https://example.com/repo", &git_config));
+		assert!(!prompter.show_device_code("https://example.com/repo", "https://example.com/device", "ABCD-1234", &git_config));
+
+		let (mut prompter, requests) = handler_prompter(Some(PromptResponse::UsernamePassword { username: "alice".into(), password: "hunter2".into() }));
+		assert!(let Some((_, _)) = prompter.prompt_username_password("https://example.com/repo", &git_config));
+		assert!(let None = prompter.prompt_password("alice", "https://example.com/repo", &git_config));
+		prompter.notify_security_key_touch("/home/alice/.ssh/id_ed25519_sk", &git_config);
+		let requests = std::mem::take(&mut *requests.lock().unwrap());
+		assert!(requests[0].kind == PromptKind::UsernamePassword);
+		let_assert!(Some(touch) = requests.last());
+		assert!(touch.kind == PromptKind::SecurityKeyTouch);
+		assert!(touch.security_key.as_deref() == Some("/home/alice/.ssh/id_ed25519_sk"));
+		assert!(!format!("{:?}", PromptResponse::Secret("hunter2".into())).contains("hunter2"));
+	}
+}
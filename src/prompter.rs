@@ -1,8 +1,21 @@
+use std::panic::AssertUnwindSafe;
 use std::path::Path;
 
+#[cfg(feature = "log")]
+use crate::log::*;
+
 /// Trait for customizing user prompts.
 ///
 /// You can provide an implementor of this trait to customize the way a user is prompted for credentials and passphrases.
+///
+/// If a prompt function returns `None`, the authenticator treats it as if the user cancelled the prompt.
+/// If no other authentication mechanism succeeds, the operation fails with [`ErrorKind::Cancelled`][crate::ErrorKind::Cancelled].
+/// To report that the user could not be prompted at all, for example because there is no terminal,
+/// implement the `try_*` methods and return [`PromptError::Unavailable`].
+/// The operation then fails with the error of the other mechanisms instead.
+///
+/// If a prompt function panics, the panic is caught so that it does not unwind into libgit2.
+/// The prompter is not called again for the same operation, which fails with [`ErrorKind::PromptPanicked`][crate::ErrorKind::PromptPanicked].
 pub trait Prompter: Send {
 	/// Promp the user for a username and password.
 	///
@@ -18,6 +31,145 @@ pub trait Prompter: Send {
 	///
 	/// If the prompt fails or the user fails to provide the requested information, this function should return `None`.
 	fn prompt_ssh_key_passphrase(&mut self, private_key_path: &Path, git_config: &git2::Config) -> Option<String>;
+
+	/// Show the user a code to enter on a web page to authorize access.
+	///
+	/// This is used for the OAuth device authorization flow:
+	/// the user should open `verification_uri` in a browser and enter `user_code` to authorize access to `url`.
+	/// The function should return as soon as the code is shown, without waiting for the user.
+	///
+	/// If the code can not be shown, this function should return `false`, which cancels the authorization.
+	/// The default implementation always returns `false`.
+	fn show_device_code(&mut self, url: &str, verification_uri: &str, user_code: &str, git_config: &git2::Config) -> bool {
+		let _ = (url, verification_uri, user_code, git_config);
+		false
+	}
+
+	/// Promp the user for the master password to unlock a password manager.
+	///
+	/// This is used to unlock the vault of the Bitwarden CLI, in which case `password_manager` is `"Bitwarden"`.
+	///
+	/// If the prompt fails or the user fails to provide the requested information, this function should return `None`.
+	/// The default implementation always returns `None`.
+	fn prompt_password_manager_unlock(&mut self, password_manager: &str, git_config: &git2::Config) -> Option<String> {
+		let _ = (password_manager, git_config);
+		None
+	}
+
+	/// Tell the user that they may have to touch their security key.
+	///
+	/// This is called right before an SSH key that is backed by a FIDO2 security key (an `ed25519-sk` or `ecdsa-sk` key) is given to libgit2.
+	/// If the server accepts the key, the operation waits until the user touches the security key to confirm their presence.
+	/// `key` describes the key: the path of the private key file, or the fingerprints and comments of the keys in the SSH agent.
+	///
+	/// The function should return as soon as the message is shown, without waiting for the user.
+	/// The default implementation does nothing.
+	fn notify_security_key_touch(&mut self, key: &str, git_config: &git2::Config) {
+		let _ = (key, git_config);
+	}
+
+	/// Promp the user for a username and password, with information about the remaining attempts.
+	///
+	/// This is called instead of [`Self::prompt_username_password()`] by the credentials callback.
+	/// Implement it if you want to show the user how many attempts are left.
+	/// The default implementation ignores the context and calls [`Self::prompt_username_password()`].
+	fn prompt_username_password_with_context(&mut self, url: &str, context: &PromptContext, git_config: &git2::Config) -> Option<(String, String)> {
+		let _ = context;
+		self.prompt_username_password(url, git_config)
+	}
+
+	/// Promp the user for a password when the username is already known, with information about the remaining attempts.
+	///
+	/// This is called instead of [`Self::prompt_password()`] by the credentials callback.
+	/// Implement it if you want to show the user how many attempts are left.
+	/// The default implementation ignores the context and calls [`Self::prompt_password()`].
+	fn prompt_password_with_context(&mut self, username: &str, url: &str, context: &PromptContext, git_config: &git2::Config) -> Option<String> {
+		let _ = context;
+		self.prompt_password(username, url, git_config)
+	}
+
+	/// Promp the user for a username and password, and tell why there is no answer.
+	///
+	/// This is called instead of [`Self::prompt_username_password_with_context()`] by the credentials callback.
+	/// The default implementation calls [`Self::prompt_username_password_with_context()`],
+	/// and treats `None` as [`PromptError::Declined`].
+	fn try_prompt_username_password(&mut self, url: &str, context: &PromptContext, git_config: &git2::Config) -> Result<(String, String), PromptError> {
+		self.prompt_username_password_with_context(url, context, git_config)
+			.ok_or(PromptError::Declined)
+	}
+
+	/// Promp the user for a password when the username is already known, and tell why there is no answer.
+	///
+	/// This is called instead of [`Self::prompt_password_with_context()`] by the credentials callback.
+	/// The default implementation calls [`Self::prompt_password_with_context()`],
+	/// and treats `None` as [`PromptError::Declined`].
+	fn try_prompt_password(&mut self, username: &str, url: &str, context: &PromptContext, git_config: &git2::Config) -> Result<String, PromptError> {
+		self.prompt_password_with_context(username, url, context, git_config)
+			.ok_or(PromptError::Declined)
+	}
+
+	/// Promp the user for the passphrase of an encrypted SSH key, and tell why there is no answer.
+	///
+	/// This is called instead of [`Self::prompt_ssh_key_passphrase()`] by the credentials callback.
+	/// The default implementation calls [`Self::prompt_ssh_key_passphrase()`],
+	/// and treats `None` as [`PromptError::Declined`].
+	fn try_prompt_ssh_key_passphrase(&mut self, private_key_path: &Path, git_config: &git2::Config) -> Result<String, PromptError> {
+		self.prompt_ssh_key_passphrase(private_key_path, git_config)
+			.ok_or(PromptError::Declined)
+	}
+}
+
+/// The reason why a prompt of a [`Prompter`] did not give an answer.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PromptError {
+	/// The user declined to answer the prompt, for example by closing the dialog.
+	///
+	/// If no other authentication mechanism succeeds, the operation fails with [`ErrorKind::Cancelled`][crate::ErrorKind::Cancelled].
+	Declined,
+
+	/// The user could not be prompted, for example because there is no terminal or the askpass program could not be run.
+	///
+	/// The authenticator continues with the next mechanism, and the operation does not fail as cancelled.
+	Unavailable,
+}
+
+impl std::fmt::Display for PromptError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Declined => write!(f, "the user declined the prompt"),
+			Self::Unavailable => write!(f, "the user could not be prompted"),
+		}
+	}
+}
+
+impl std::error::Error for PromptError {}
+
+/// Information about a credentials prompt, passed to the `*_with_context()` methods of a [`Prompter`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct PromptContext {
+	/// The number of this prompt for the operation, starting at 1.
+	pub attempt: u32,
+
+	/// The number of prompts that are left if the server rejects the credentials entered in this prompt.
+	///
+	/// This is limited by [`GitAuthenticator::try_password_prompt()`][crate::GitAuthenticator::try_password_prompt()]
+	/// and [`GitAuthenticator::max_callback_invocations()`][crate::GitAuthenticator::max_callback_invocations()].
+	/// When it is 0, this is the last chance to enter the right credentials before the operation fails.
+	pub remaining_attempts: u32,
+}
+
+impl PromptContext {
+	/// Make a new prompt context.
+	pub fn new(attempt: u32, remaining_attempts: u32) -> Self {
+		Self { attempt, remaining_attempts }
+	}
+
+	/// Check if this is the last prompt for the operation.
+	pub fn is_last_attempt(&self) -> bool {
+		self.remaining_attempts == 0
+	}
 }
 
 /// Wrap a clonable [`Prompter`] in a `Box<dyn MakePrompter>`.
@@ -33,9 +185,6 @@ pub(crate) trait ClonePrompter: Prompter {
 	/// Clone the `Box<dyn ClonePrompter>`.
 	fn dyn_clone(&self) -> Box<dyn ClonePrompter>;
 
-	/// Get `self` as plain `Prompter`.
-	fn as_prompter(&self) -> &dyn Prompter;
-
 	/// Get `self` as plain `Prompter`.
 	fn as_prompter_mut(&mut self) -> &mut dyn Prompter;
 }
@@ -49,10 +198,6 @@ where
 		Box::new(self.clone())
 	}
 
-	fn as_prompter(&self) -> &dyn Prompter {
-		self
-	}
-
 	fn as_prompter_mut(&mut self) -> &mut dyn Prompter {
 		self
 	}
@@ -63,3 +208,107 @@ impl Clone for Box<dyn ClonePrompter> {
 		self.dyn_clone()
 	}
 }
+
+/// Prompter that catches panics of the wrapped prompter.
+///
+/// A panic must not unwind into libgit2, so a panic is turned into a cancelled prompt.
+/// After a panic, the prompter is poisoned: the wrapped prompter is not called again,
+/// and all prompts are cancelled immediately.
+pub(crate) struct GuardedPrompter {
+	/// The wrapped prompter.
+	inner: Box<dyn ClonePrompter>,
+
+	/// The message of the panic, if the wrapped prompter panicked.
+	panic: Option<String>,
+}
+
+impl GuardedPrompter {
+	/// Wrap a prompter.
+	pub(crate) fn new(inner: Box<dyn ClonePrompter>) -> Self {
+		Self { inner, panic: None }
+	}
+
+	/// Get the message of the panic, if the wrapped prompter panicked.
+	pub(crate) fn panic_message(&self) -> Option<&str> {
+		self.panic.as_deref()
+	}
+
+	/// Get `self` as plain `Prompter`.
+	pub(crate) fn as_prompter_mut(&mut self) -> &mut dyn Prompter {
+		self
+	}
+
+	/// Call the wrapped prompter, and return `cancelled` if it panics or if it panicked before.
+	fn guard<T>(&mut self, cancelled: T, prompt: impl FnOnce(&mut dyn Prompter) -> T) -> T {
+		if self.panic.is_some() {
+			return cancelled;
+		}
+		let inner = self.inner.as_prompter_mut();
+		match std::panic::catch_unwind(AssertUnwindSafe(|| prompt(inner))) {
+			Ok(x) => x,
+			Err(payload) => {
+				let message = panic_message(payload.as_ref());
+				warn!("The prompter panicked, not prompting again for this operation: {message}");
+				self.panic = Some(message);
+				cancelled
+			},
+		}
+	}
+}
+
+impl Prompter for GuardedPrompter {
+	fn prompt_username_password(&mut self, url: &str, git_config: &git2::Config) -> Option<(String, String)> {
+		self.guard(None, |prompter| prompter.prompt_username_password(url, git_config))
+	}
+
+	fn prompt_password(&mut self, username: &str, url: &str, git_config: &git2::Config) -> Option<String> {
+		self.guard(None, |prompter| prompter.prompt_password(username, url, git_config))
+	}
+
+	fn prompt_ssh_key_passphrase(&mut self, private_key_path: &Path, git_config: &git2::Config) -> Option<String> {
+		self.guard(None, |prompter| prompter.prompt_ssh_key_passphrase(private_key_path, git_config))
+	}
+
+	fn show_device_code(&mut self, url: &str, verification_uri: &str, user_code: &str, git_config: &git2::Config) -> bool {
+		self.guard(false, |prompter| prompter.show_device_code(url, verification_uri, user_code, git_config))
+	}
+
+	fn prompt_password_manager_unlock(&mut self, password_manager: &str, git_config: &git2::Config) -> Option<String> {
+		self.guard(None, |prompter| prompter.prompt_password_manager_unlock(password_manager, git_config))
+	}
+
+	fn notify_security_key_touch(&mut self, key: &str, git_config: &git2::Config) {
+		self.guard((), |prompter| prompter.notify_security_key_touch(key, git_config))
+	}
+
+	fn prompt_username_password_with_context(&mut self, url: &str, context: &PromptContext, git_config: &git2::Config) -> Option<(String, String)> {
+		self.guard(None, |prompter| prompter.prompt_username_password_with_context(url, context, git_config))
+	}
+
+	fn prompt_password_with_context(&mut self, username: &str, url: &str, context: &PromptContext, git_config: &git2::Config) -> Option<String> {
+		self.guard(None, |prompter| prompter.prompt_password_with_context(username, url, context, git_config))
+	}
+
+	fn try_prompt_username_password(&mut self, url: &str, context: &PromptContext, git_config: &git2::Config) -> Result<(String, String), PromptError> {
+		self.guard(Err(PromptError::Unavailable), |prompter| prompter.try_prompt_username_password(url, context, git_config))
+	}
+
+	fn try_prompt_password(&mut self, username: &str, url: &str, context: &PromptContext, git_config: &git2::Config) -> Result<String, PromptError> {
+		self.guard(Err(PromptError::Unavailable), |prompter| prompter.try_prompt_password(username, url, context, git_config))
+	}
+
+	fn try_prompt_ssh_key_passphrase(&mut self, private_key_path: &Path, git_config: &git2::Config) -> Result<String, PromptError> {
+		self.guard(Err(PromptError::Unavailable), |prompter| prompter.try_prompt_ssh_key_passphrase(private_key_path, git_config))
+	}
+}
+
+/// Get the message of a panic from the panic payload.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+	if let Some(message) = payload.downcast_ref::<&str>() {
+		(*message).into()
+	} else if let Some(message) = payload.downcast_ref::<String>() {
+		message.clone()
+	} else {
+		"unknown panic payload".into()
+	}
+}
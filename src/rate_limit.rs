@@ -0,0 +1,85 @@
+//! Detection of rate limiting by servers, and the delays between retries.
+
+use std::time::Duration;
+
+/// The maximum delay between retries, unless the server asks for a longer delay.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The maximum delay that the server can ask for with `Retry-After`.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(600);
+
+/// Check if an operation failed because the server rate limited it with HTTP status 429.
+pub(crate) fn is_rate_limited(error: &git2::Error) -> bool {
+	let message = error.message().to_ascii_lowercase();
+	message.contains("status code: 429") || message.contains("429 too many requests") || message.contains("rate limit")
+}
+
+/// Get the delay that the server asked for in a `Retry-After` header or a similar message, if it is included in an error message.
+///
+/// Only delays in seconds are supported, not absolute dates.
+pub(crate) fn retry_after(message: &str) -> Option<Duration> {
+	let message = message.to_ascii_lowercase();
+	let (_, rest) = message.split_once("retry-after:")
+		.or_else(|| message.split_once("retry after"))?;
+	let digits: String = rest.trim_start()
+		.chars()
+		.take_while(|c| c.is_ascii_digit())
+		.collect();
+	let seconds = digits.parse().ok()?;
+	Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+}
+
+/// Exponential backoff between retries of a rate limited operation.
+#[derive(Debug, Clone)]
+pub(crate) struct Backoff {
+	/// The delay for the next retry, if the server does not ask for a different delay.
+	next: Duration,
+}
+
+impl Backoff {
+	/// Create a backoff that starts with the given delay.
+	pub(crate) fn new(initial: Duration) -> Self {
+		Self { next: initial }
+	}
+
+	/// Get the delay before the next retry, and double the delay for the retry after that.
+	///
+	/// If the server asked for a delay with `Retry-After`, that delay is used instead.
+	pub(crate) fn next_delay(&mut self, retry_after: Option<Duration>) -> Duration {
+		let delay = retry_after.unwrap_or(self.next);
+		self.next = (self.next * 2).min(MAX_BACKOFF);
+		delay
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_is_rate_limited() {
+		let error = |message| git2::Error::new(git2::ErrorCode::GenericError, git2::ErrorClass::Http, message);
+		assert!(is_rate_limited(&error("unexpected http status code: 429")));
+		assert!(is_rate_limited(&error("remote: API rate limit exceeded")));
+		assert!(!is_rate_limited(&error("unexpected http status code: 404")));
+	}
+
+	#[test]
+	fn test_retry_after() {
+		assert!(retry_after("429 Too Many Requests; Retry-After: 30") == Some(Duration::from_secs(30)));
+		assert!(retry_after("remote: rate limited, retry after 5 seconds") == Some(Duration::from_secs(5)));
+		assert!(retry_after("Retry-After: 86400") == Some(MAX_RETRY_AFTER));
+		assert!(let None = retry_after("Retry-After: Wed, 21 Oct 2015 07:28:00 GMT"));
+		assert!(let None = retry_after("unexpected http status code: 429"));
+	}
+
+	#[test]
+	fn test_backoff() {
+		let mut backoff = Backoff::new(Duration::from_secs(20));
+		assert!(backoff.next_delay(None) == Duration::from_secs(20));
+		assert!(backoff.next_delay(Some(Duration::from_secs(3))) == Duration::from_secs(3));
+		assert!(backoff.next_delay(None) == Duration::from_secs(60));
+		assert!(backoff.next_delay(None) == Duration::from_secs(60));
+	}
+}
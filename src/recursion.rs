@@ -0,0 +1,69 @@
+//! Detection of recursive authentication through credential helpers and askpass programs.
+//!
+//! A credential helper or askpass program can use git itself, which may ask the same helper for credentials again.
+//! To detect that, every helper process started by this crate gets an environment variable with the nesting depth.
+//! When the depth gets too deep, the credentials callback fails instead of starting the helpers yet again.
+
+use std::process::Command;
+
+/// The environment variable with the number of nested helper processes started by this crate.
+pub(crate) const DEPTH_VAR: &str = "AUTH_GIT2_HELPER_DEPTH";
+
+/// The maximum depth at which the credentials callback still runs.
+///
+/// A helper that uses git with its own credential helper is fine, but deeper nesting is almost certainly a loop.
+const MAX_DEPTH: u32 = 2;
+
+/// Get the nesting depth of the current process.
+fn depth() -> u32 {
+	std::env::var(DEPTH_VAR)
+		.ok()
+		.and_then(|x| x.trim().parse().ok())
+		.unwrap_or(0)
+}
+
+/// Mark a helper process started by this crate, so it can detect recursion.
+pub(crate) fn mark(command: &mut Command) -> &mut Command {
+	command.env(DEPTH_VAR, (depth().saturating_add(1)).to_string())
+}
+
+/// Check that the current process is not nested too deep in helper processes.
+///
+/// Returns the error message if authentication should fail.
+pub(crate) fn check(url: &str) -> Result<(), String> {
+	check_depth(depth(), url)
+}
+
+/// Check that the given nesting depth is allowed.
+fn check_depth(depth: u32, url: &str) -> Result<(), String> {
+	if depth <= MAX_DEPTH {
+		return Ok(());
+	}
+	Err(format!(
+		"refusing to authenticate to {url}: this process runs {depth} levels deep in credential helpers or askpass programs ({DEPTH_VAR}={depth}), \
+		which probably means that a credential helper runs git and recursively asks itself for credentials"
+	))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_check_depth() {
+		assert!(let Ok(()) = check_depth(0, "https://example.com/repo.git"));
+		assert!(let Ok(()) = check_depth(MAX_DEPTH, "https://example.com/repo.git"));
+		let_assert!(Err(message) = check_depth(MAX_DEPTH + 1, "https://example.com/repo.git"));
+		assert!(message.contains("https://example.com/repo.git"));
+		assert!(message.contains(DEPTH_VAR));
+	}
+
+	#[test]
+	fn test_mark() {
+		let mut command = Command::new("true");
+		mark(&mut command);
+		let_assert!(Some((_, Some(value))) = command.get_envs().find(|(key, _)| *key == DEPTH_VAR));
+		assert!(value.to_str() == Some(&*(depth() + 1).to_string()));
+	}
+}
@@ -0,0 +1,280 @@
+//! Short-lived credentials that are obtained with a user-provided callback.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use zeroize::Zeroize;
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+/// Credentials with an optional expiry time, as returned by a [`CredentialRefresher`].
+///
+/// The password is overwritten with zeros when the credentials are dropped.
+#[derive(Clone, Eq, PartialEq)]
+pub struct ExpiringCredentials {
+	/// The username.
+	username: String,
+
+	/// The password or token.
+	password: String,
+
+	/// The time after which the credentials are no longer valid.
+	expires_at: Option<SystemTime>,
+}
+
+impl ExpiringCredentials {
+	/// Create credentials that do not expire.
+	///
+	/// They are still refreshed if the server rejects them.
+	pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+		Self {
+			username: username.into(),
+			password: password.into(),
+			expires_at: None,
+		}
+	}
+
+	/// Set the time after which the credentials are no longer valid.
+	///
+	/// If an operation may take a while, use a time slightly before the real expiry,
+	/// so the credentials do not expire during the operation.
+	pub fn expires_at(mut self, expires_at: SystemTime) -> Self {
+		self.expires_at = Some(expires_at);
+		self
+	}
+
+	/// Get the username.
+	pub fn username(&self) -> &str {
+		&self.username
+	}
+
+	/// Get the time after which the credentials are no longer valid, if any.
+	pub fn expiry(&self) -> Option<SystemTime> {
+		self.expires_at
+	}
+
+	/// Check if the credentials are expired at the given time.
+	fn is_expired(&self, now: SystemTime) -> bool {
+		self.expires_at.is_some_and(|expiry| expiry <= now)
+	}
+}
+
+impl std::fmt::Debug for ExpiringCredentials {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ExpiringCredentials")
+			.field("username", &self.username)
+			.field("expires_at", &self.expires_at)
+			.finish_non_exhaustive()
+	}
+}
+
+impl Drop for ExpiringCredentials {
+	fn drop(&mut self) {
+		self.password.zeroize();
+	}
+}
+
+impl From<ExpiringCredentials> for crate::PlaintextCredentials {
+	fn from(mut credentials: ExpiringCredentials) -> Self {
+		Self {
+			username: std::mem::take(&mut credentials.username),
+			password: std::mem::take(&mut credentials.password),
+		}
+	}
+}
+
+/// Callback to get new short-lived credentials.
+///
+/// You can add a refresher for a domain with [`GitAuthenticator::add_refreshing_credentials()`][crate::GitAuthenticator::add_refreshing_credentials()].
+pub trait CredentialRefresher: Send + Sync {
+	/// Get new credentials for a URL.
+	///
+	/// Return `None` if no credentials could be obtained.
+	fn refresh(&self, url: &str) -> Option<ExpiringCredentials>;
+}
+
+impl<F> CredentialRefresher for F
+where
+	F: Fn(&str) -> Option<ExpiringCredentials> + Send + Sync,
+{
+	fn refresh(&self, url: &str) -> Option<ExpiringCredentials> {
+		self(url)
+	}
+}
+
+/// An in-memory cache of credentials by host, that forgets the credentials when they expire.
+///
+/// The cache is shared between all clones, and it is dropped with the last clone.
+/// Cached passwords are overwritten with zeros when they are removed from the cache or dropped.
+#[derive(Clone, Default)]
+pub(crate) struct CredentialCache {
+	/// The cached credentials by host.
+	entries: Arc<Mutex<BTreeMap<String, ExpiringCredentials>>>,
+}
+
+impl std::fmt::Debug for CredentialCache {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_set()
+			.entries(self.lock().keys())
+			.finish()
+	}
+}
+
+impl CredentialCache {
+	/// Get the cached credentials for a host, if they did not expire yet.
+	///
+	/// Expired credentials are removed from the cache.
+	pub(crate) fn get(&self, host: &str, now: SystemTime) -> Option<ExpiringCredentials> {
+		let mut entries = self.lock();
+		let credentials = entries.get(host)?;
+		if !credentials.is_expired(now) {
+			return Some(credentials.clone());
+		}
+		debug!("cached credentials for {host:?} expired");
+		entries.remove(host);
+		None
+	}
+
+	/// Add credentials for a host to the cache, replacing earlier credentials for the host.
+	pub(crate) fn insert(&self, host: &str, credentials: ExpiringCredentials) {
+		self.lock().insert(host.to_owned(), credentials);
+	}
+
+	/// Remove the cached credentials for a host, because the server rejected them.
+	pub(crate) fn invalidate(&self, host: &str) {
+		self.lock().remove(host);
+	}
+
+	/// Remove the cached credentials for all hosts that match a host pattern.
+	pub(crate) fn forget_host(&self, pattern: &str) {
+		self.lock().retain(|host, _| !crate::credential_helper::host_matches(pattern, host));
+	}
+
+	/// Remove all cached credentials.
+	pub(crate) fn purge(&self) {
+		self.lock().clear();
+	}
+
+	/// Lock the cache, ignoring poisoning.
+	fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, ExpiringCredentials>> {
+		self.entries.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// A [`CredentialRefresher`] with a cache of the credentials it returned, by host.
+///
+/// The cache is shared between all clones, and it is dropped with the last clone.
+#[derive(Clone)]
+pub(crate) struct RefreshingCredentials {
+	/// The callback to get new credentials.
+	refresher: Arc<dyn CredentialRefresher>,
+
+	/// The cached credentials by host.
+	cache: CredentialCache,
+}
+
+impl std::fmt::Debug for RefreshingCredentials {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("RefreshingCredentials")
+			.field("cached_hosts", &self.cache)
+			.finish_non_exhaustive()
+	}
+}
+
+impl RefreshingCredentials {
+	/// Wrap a refresher with an empty cache.
+	pub(crate) fn new(refresher: Arc<dyn CredentialRefresher>) -> Self {
+		Self {
+			refresher,
+			cache: Default::default(),
+		}
+	}
+
+	/// Get credentials for a URL, from the cache if they did not expire yet.
+	///
+	/// The credentials are cached per `host`, which is the same for all aliases of a host.
+	/// Returns the credentials and whether they came from the cache.
+	pub(crate) fn get(&self, url: &str, host: &str, now: SystemTime) -> Option<(ExpiringCredentials, bool)> {
+		if let Some(credentials) = self.cache.get(host, now) {
+			return Some((credentials, true));
+		}
+		let credentials = self.refresher.refresh(url)?;
+		if credentials.is_expired(now) {
+			warn!("credential refresher returned expired credentials for {host:?}");
+			return None;
+		}
+		self.cache.insert(host, credentials.clone());
+		Some((credentials, false))
+	}
+
+	/// Remove the cached credentials for a host, because the server rejected them.
+	pub(crate) fn invalidate(&self, host: &str) {
+		self.cache.invalidate(host);
+	}
+
+	/// Remove the cached credentials for all hosts that match a host pattern.
+	pub(crate) fn forget_host(&self, pattern: &str) {
+		self.cache.forget_host(pattern);
+	}
+
+	/// Remove all cached credentials.
+	pub(crate) fn purge(&self) {
+		self.cache.purge();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::time::Duration;
+
+	#[test]
+	fn test_cache() {
+		let count = Arc::new(AtomicUsize::new(0));
+		let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+		let credentials = RefreshingCredentials::new(Arc::new({
+			let count = count.clone();
+			move |_url: &str| {
+				let n = count.fetch_add(1, Ordering::Relaxed);
+				let expires_at = now + Duration::from_secs(60 * (n as u64 + 1));
+				Some(ExpiringCredentials::new("bot", format!("token-{n}")).expires_at(expires_at))
+			}
+		}));
+
+		let_assert!(Some((first, false)) = credentials.get("https://example.com/repo", "example.com", now));
+		assert!(first.password == "token-0");
+		let_assert!(Some((cached, true)) = credentials.get("https://example.com/other", "example.com", now + Duration::from_secs(59)));
+		assert!(cached == first);
+		assert!(count.load(Ordering::Relaxed) == 1);
+
+		let_assert!(Some((refreshed, false)) = credentials.get("https://example.com/repo", "example.com", now + Duration::from_secs(60)));
+		assert!(refreshed.password == "token-1");
+
+		credentials.invalidate("example.com");
+		assert!(let Some((_, false)) = credentials.get("https://example.com/repo", "example.com", now));
+		assert!(count.load(Ordering::Relaxed) == 3);
+		assert!(!format!("{refreshed:?}").contains("token"));
+	}
+
+	#[test]
+	fn test_purge_and_drop() {
+		let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+		let credentials = RefreshingCredentials::new(Arc::new(|_url: &str| Some(ExpiringCredentials::new("bot", "token"))));
+		let_assert!(Some((_, false)) = credentials.get("https://example.com/repo", "example.com", now));
+		credentials.purge();
+		assert!(let Some((_, false)) = credentials.get("https://example.com/repo", "example.com", now));
+
+		// The cache is dropped with the last clone, even if that clone lives on another thread.
+		let cache = Arc::downgrade(&credentials.cache.entries);
+		let clone = credentials.clone();
+		drop(credentials);
+		assert!(let Some(_) = cache.upgrade());
+		std::thread::spawn(move || {
+			assert!(let Some((_, true)) = clone.get("https://example.com/repo", "example.com", now));
+		}).join().unwrap();
+		assert!(let None = cache.upgrade());
+	}
+}
@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+
+/// Credentials found by a [`GitAuthenticator`][crate::GitAuthenticator], before they are given to libgit2.
+///
+/// Use [`GitAuthenticator::resolver()`][crate::GitAuthenticator::resolver()] to get resolved credentials,
+/// and [`Self::into_cred()`] to convert them to a [`git2::Cred`].
+/// This allows you to use the credential sources of this crate with your own callback or retry logic.
+///
+/// The [`Debug`] implementation does not show passwords, passphrases or private keys,
+/// and they are overwritten with zeros when the credentials are dropped.
+#[derive(Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ResolvedCredential {
+	/// Only a username, for servers that ask for the username first.
+	Username {
+		/// The username.
+		username: String,
+	},
+
+	/// Authenticate with the keys of the SSH agent.
+	SshAgent {
+		/// The username.
+		username: String,
+	},
+
+	/// Authenticate with a private key file.
+	SshKeyFile {
+		/// The username.
+		username: String,
+
+		/// The path of the public key file, if it exists.
+		public_key: Option<PathBuf>,
+
+		/// The path of the private key file.
+		private_key: PathBuf,
+
+		/// The passphrase of the private key, if it is encrypted.
+		passphrase: Option<Zeroizing<String>>,
+	},
+
+	/// Authenticate with a private key from memory.
+	SshKeyMemory {
+		/// The username.
+		username: String,
+
+		/// The private key in PEM format.
+		private_key: Zeroizing<String>,
+
+		/// The passphrase of the private key, if it is encrypted.
+		passphrase: Option<Zeroizing<String>>,
+	},
+
+	/// Authenticate with the default credentials of the current user, for Negotiate (Kerberos) or NTLM authentication.
+	Default,
+
+	/// Authenticate with a username and password (or token).
+	UserPassPlaintext {
+		/// The username.
+		username: String,
+
+		/// The password or token.
+		password: Zeroizing<String>,
+	},
+}
+
+impl ResolvedCredential {
+	/// Get the username of the credentials.
+	///
+	/// This is empty for [`Self::Default`], since the username is determined by the operating system.
+	pub fn username(&self) -> &str {
+		match self {
+			Self::Username { username } => username,
+			Self::SshAgent { username } => username,
+			Self::SshKeyFile { username, .. } => username,
+			Self::SshKeyMemory { username, .. } => username,
+			Self::Default => "",
+			Self::UserPassPlaintext { username, .. } => username,
+		}
+	}
+
+	/// Convert the credentials to a [`git2::Cred`].
+	pub fn into_cred(self) -> Result<git2::Cred, git2::Error> {
+		self.to_cred()
+	}
+
+	/// Convert the credentials to a [`git2::Cred`] without consuming them.
+	pub(crate) fn to_cred(&self) -> Result<git2::Cred, git2::Error> {
+		match self {
+			Self::Username { username } => git2::Cred::username(username),
+			Self::SshAgent { username } => git2::Cred::ssh_key_from_agent(username),
+			Self::SshKeyFile { username, public_key, private_key, passphrase } => {
+				git2::Cred::ssh_key(username, public_key.as_deref(), private_key, passphrase.as_deref().map(String::as_str))
+			},
+			Self::SshKeyMemory { username, private_key, passphrase } => {
+				git2::Cred::ssh_key_from_memory(username, None, private_key, passphrase.as_deref().map(String::as_str))
+			},
+			Self::Default => git2::Cred::default(),
+			Self::UserPassPlaintext { username, password } => git2::Cred::userpass_plaintext(username, password),
+		}
+	}
+}
+
+impl std::fmt::Debug for ResolvedCredential {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Username { username } => f.debug_struct("Username")
+				.field("username", username)
+				.finish(),
+			Self::SshAgent { username } => f.debug_struct("SshAgent")
+				.field("username", username)
+				.finish(),
+			Self::SshKeyFile { username, public_key, private_key, passphrase } => f.debug_struct("SshKeyFile")
+				.field("username", username)
+				.field("public_key", public_key)
+				.field("private_key", private_key)
+				.field("has_passphrase", &passphrase.is_some())
+				.finish(),
+			Self::SshKeyMemory { username, private_key: _, passphrase } => f.debug_struct("SshKeyMemory")
+				.field("username", username)
+				.field("has_passphrase", &passphrase.is_some())
+				.finish_non_exhaustive(),
+			Self::Default => f.write_str("Default"),
+			Self::UserPassPlaintext { username, password: _ } => f.debug_struct("UserPassPlaintext")
+				.field("username", username)
+				.finish_non_exhaustive(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_into_cred() {
+		let credential = ResolvedCredential::UserPassPlaintext {
+			username: "alice".into(),
+			password: Zeroizing::new("hunter2".into()),
+		};
+		assert!(credential.username() == "alice");
+		assert!(!format!("{credential:?}").contains("hunter2"));
+		let_assert!(Ok(cred) = credential.into_cred());
+		assert!(cred.has_username());
+	}
+}
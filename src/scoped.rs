@@ -0,0 +1,606 @@
+//! Settings that are overridden for single operations, and the state of one run of an operation.
+//!
+//! The convenience functions like [`GitAuthenticator::fetch()`] may run an operation more than once,
+//! for example to retry with another SSH username.
+//! The settings in [`Overrides`] stay the same for all runs, while each run gets its own [`OperationRun`].
+
+use std::path::Path;
+
+use zeroize::Zeroizing;
+
+use crate::prompter::{self, ClonePrompter};
+use crate::{capabilities, error, rate_limit, telemetry, CloneOptions, ClonedRepo, Error, GitAuthenticator, PlannedAttempt, Prompter};
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+/// Settings of a [`GitAuthenticator`] that are overridden for a single operation.
+#[derive(Clone, Default)]
+pub(crate) struct Overrides {
+	/// The prompter to use instead of the prompter of the authenticator.
+	///
+	/// `Some(None)` disables prompts.
+	prompter: Option<Option<Box<dyn ClonePrompter>>>,
+
+	/// The maximum number of password prompts.
+	try_password_prompt: Option<u32>,
+
+	/// The maximum number of identical username requests.
+	max_username_requests: Option<u32>,
+}
+
+impl Overrides {
+	/// Get the prompter to use for the operation.
+	pub(crate) fn prompter<'a>(&'a self, authenticator: &'a GitAuthenticator) -> Option<&'a dyn ClonePrompter> {
+		match &self.prompter {
+			Some(prompter) => prompter.as_deref(),
+			None => authenticator.prompter.as_deref(),
+		}
+	}
+
+	/// Check if the operation uses the default prompter of the `prompts` feature.
+	#[cfg(feature = "prompts")]
+	pub(crate) fn uses_default_prompter(&self, authenticator: &GitAuthenticator) -> bool {
+		self.prompter.is_none() && !authenticator.custom_prompter
+	}
+
+	/// Get the maximum number of password prompts for the operation.
+	pub(crate) fn try_password_prompt(&self, authenticator: &GitAuthenticator) -> u32 {
+		self.try_password_prompt.unwrap_or(authenticator.try_password_prompt)
+	}
+
+	/// Get the maximum number of identical username requests for the operation.
+	pub(crate) fn max_username_requests(&self, authenticator: &GitAuthenticator) -> u32 {
+		self.max_username_requests.unwrap_or(authenticator.max_username_requests)
+	}
+}
+
+/// The state of a single run of an operation, decided by the retry loop of the convenience functions.
+///
+/// The plain credentials callback does not run the operation itself, so it uses the default.
+#[derive(Clone, Default)]
+pub(crate) struct OperationRun {
+	/// The username to give when libgit2 asks for one.
+	username: Option<String>,
+
+	/// The operation can be run again with an `Authorization` header if a credential helper returns a pre-encoded credential.
+	authorization_retry: bool,
+
+	/// The value of the `Authorization` header to send, from a credential helper.
+	authorization: Option<Zeroizing<String>>,
+
+	/// The host alias from the SSH configuration that the URL of the operation was resolved from.
+	ssh_config_host: Option<String>,
+
+	/// The URL of the operation, before any redirects of the server.
+	operation_url: Option<String>,
+}
+
+impl OperationRun {
+	/// Get the username to give when libgit2 asks for one, instead of the configured username.
+	pub(crate) fn username(&self) -> Option<&str> {
+		self.username.as_deref()
+	}
+
+	/// Get the host to look up in the SSH configuration, if the URL of the operation was resolved from a host alias.
+	pub(crate) fn ssh_config_host(&self) -> Option<&str> {
+		self.ssh_config_host.as_deref()
+	}
+
+	/// Get the URL of the operation, before any redirects of the server.
+	pub(crate) fn operation_url(&self) -> Option<&str> {
+		self.operation_url.as_deref()
+	}
+
+	/// Check if the credentials callback may ask to run the operation again with an `Authorization` header.
+	///
+	/// This is only possible once per operation, and only for the convenience functions that run the operation themselves.
+	pub(crate) fn can_retry_with_authorization(&self) -> bool {
+		self.authorization_retry && self.authorization.is_none()
+	}
+
+	/// Get the custom headers to send with the requests of the operation.
+	fn custom_headers(&self) -> Vec<String> {
+		match &self.authorization {
+			Some(authorization) => vec![format!("Authorization: {}", authorization.as_str())],
+			None => Vec::new(),
+		}
+	}
+}
+
+/// A [`GitAuthenticator`] with settings overridden for individual operations.
+///
+/// This is a cheap wrapper around a reference to the authenticator:
+/// the authenticator itself is not cloned or modified.
+/// Use it to run some operations with different prompt behaviour than others.
+///
+/// Create one with [`GitAuthenticator::scoped()`], [`GitAuthenticator::with_prompter()`] or [`GitAuthenticator::without_prompts()`].
+///
+/// # Example: Silent background fetch, interactive clone
+/// ```no_run
+/// # fn main() -> Result<(), auth_git2::Error> {
+/// # let repo = git2::Repository::open("/tmp/repo")?;
+/// # let mut remote = repo.find_remote("origin")?;
+/// use auth_git2::GitAuthenticator;
+///
+/// let auth = GitAuthenticator::default();
+///
+/// // Never prompt during the background sync.
+/// auth.without_prompts().fetch(&repo, &mut remote, &["main"], None)?;
+///
+/// // The user asked for the clone, so prompting is fine.
+/// auth.clone_repo("https://github.com/de-vri-es/auth-git2-rs", "/tmp/auth-git2-rs")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ScopedAuthenticator<'a> {
+	/// The authenticator with the base settings.
+	authenticator: &'a GitAuthenticator,
+
+	/// The overridden settings.
+	overrides: Overrides,
+}
+
+impl std::fmt::Debug for ScopedAuthenticator<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ScopedAuthenticator")
+			.field("authenticator", &self.authenticator)
+			.field("prompter", &self.overrides.prompter.as_ref().map(|x| x.is_some()))
+			.field("try_password_prompt", &self.overrides.try_password_prompt)
+			.field("max_username_requests", &self.overrides.max_username_requests)
+			.finish()
+	}
+}
+
+impl<'a> ScopedAuthenticator<'a> {
+	/// Create a wrapper without any overridden settings.
+	pub(crate) fn new(authenticator: &'a GitAuthenticator) -> Self {
+		Self {
+			authenticator,
+			overrides: Overrides::default(),
+		}
+	}
+
+	/// Create a wrapper with the given overridden settings.
+	pub(crate) fn with_overrides(authenticator: &'a GitAuthenticator, overrides: Overrides) -> Self {
+		Self {
+			authenticator,
+			overrides,
+		}
+	}
+
+	/// Use a different [`Prompter`] for the operations of this wrapper.
+	///
+	/// See [`GitAuthenticator::set_prompter()`] for more details.
+	pub fn with_prompter<P: Prompter + Clone + Send + 'static>(mut self, prompter: P) -> Self {
+		self.overrides.prompter = Some(Some(prompter::wrap_prompter(prompter)));
+		self
+	}
+
+	/// Never prompt the user during the operations of this wrapper.
+	pub fn without_prompts(mut self) -> Self {
+		self.overrides.prompter = Some(None);
+		self
+	}
+
+	/// Override the maximum number of password prompts.
+	///
+	/// See [`GitAuthenticator::try_password_prompt()`] for more details.
+	pub fn try_password_prompt(mut self, max_count: u32) -> Self {
+		self.overrides.try_password_prompt = Some(max_count);
+		self
+	}
+
+	/// Override the maximum number of identical username requests.
+	///
+	/// See [`GitAuthenticator::max_username_requests()`] for more details.
+	pub fn max_username_requests(mut self, max_count: u32) -> Self {
+		self.overrides.max_username_requests = Some(max_count);
+		self
+	}
+
+	/// Get the wrapped authenticator.
+	pub fn authenticator(&self) -> &'a GitAuthenticator {
+		self.authenticator
+	}
+
+	/// Get a [`git2::Credentials`] callback with the overridden settings.
+	///
+	/// See [`GitAuthenticator::credentials()`] for more details.
+	pub fn credentials<'c>(
+		&self,
+		git_config: &'c git2::Config,
+	) -> impl 'c + FnMut(&str, Option<&str>, git2::CredentialType) -> Result<git2::Cred, git2::Error>
+	where
+		'a: 'c,
+	{
+		crate::make_credentials_callback(self.authenticator, git_config, error::FailureTracker::new(), self.overrides.clone(), OperationRun::default())
+	}
+
+	/// Clone a repository with the overridden settings.
+	///
+	/// See [`GitAuthenticator::clone_repo()`] for more details.
+	pub fn clone_repo(&self, url: impl AsRef<str>, into: impl AsRef<Path>) -> Result<git2::Repository, Error> {
+		self.clone_repo_with(url, into, CloneOptions::new())
+	}
+
+	/// Clone a repository with custom options and the overridden settings.
+	///
+	/// See [`GitAuthenticator::clone_repo_with()`] for more details.
+	pub fn clone_repo_with(&self, url: impl AsRef<str>, into: impl AsRef<Path>, options: CloneOptions<'_>) -> Result<git2::Repository, Error> {
+		let (url, ssh_config_host) = self.authenticator.resolve_url(url.as_ref());
+		let into = into.as_ref();
+		capabilities::check_url(&url)?;
+
+		let git_config = self.authenticator.apply_env_config(&mut git2::Config::open_default()?)?;
+		let mut repo_builder = git2::build::RepoBuilder::new();
+		options.apply(&mut repo_builder);
+		self.retry_usernames(telemetry::Operation::Clone, &url, ssh_config_host, &git_config, |run, failure| {
+			let mut fetch_options = git2::FetchOptions::new();
+			let mut remote_callbacks = git2::RemoteCallbacks::new();
+			let headers = run.custom_headers();
+			fetch_options.custom_headers(&headers.iter().map(String::as_str).collect::<Vec<_>>());
+
+			remote_callbacks.credentials(crate::make_credentials_callback(self.authenticator, &git_config, failure, self.overrides.clone(), run));
+			fetch_options.remote_callbacks(remote_callbacks);
+			repo_builder.fetch_options(fetch_options);
+
+			repo_builder.clone(&url, into)
+		})
+	}
+
+	/// Clone a repository with the overridden settings, and keep the authenticator for later operations.
+	///
+	/// See [`GitAuthenticator::clone_repo_authenticated()`] for more details.
+	pub fn clone_repo_authenticated(&self, url: impl AsRef<str>, into: impl AsRef<Path>, options: CloneOptions<'_>) -> Result<ClonedRepo, Error> {
+		let repo = self.clone_repo_with(url, into, options)?;
+		Ok(ClonedRepo::new(repo, self.authenticator.clone(), self.overrides.clone()))
+	}
+
+	/// Fetch from a remote with the overridden settings.
+	///
+	/// See [`GitAuthenticator::fetch()`] for more details.
+	pub fn fetch(&self, repo: &git2::Repository, remote: &mut git2::Remote, refspecs: &[&str], reflog_msg: Option<&str>) -> Result<(), Error> {
+		let git_config = self.authenticator.apply_env_config(&mut repo.config()?)?;
+		let (url, ssh_config_host) = self.resolve_remote_url(remote.url());
+		if let Some(url) = &url {
+			capabilities::check_url(url)?;
+		}
+		let mut rewritten = self.authenticator.rewrite_remote(repo, remote.url())?;
+		let rewritten_refspecs = match rewritten {
+			Some(_) => anonymous_fetch_refspecs(remote, refspecs),
+			None => Vec::new(),
+		};
+		self.retry_usernames(telemetry::Operation::Fetch, url.as_deref().unwrap_or_default(), ssh_config_host, &git_config, |run, failure| {
+			let mut fetch_options = git2::FetchOptions::new();
+			let mut remote_callbacks = git2::RemoteCallbacks::new();
+			let headers = run.custom_headers();
+			fetch_options.custom_headers(&headers.iter().map(String::as_str).collect::<Vec<_>>());
+
+			remote_callbacks.credentials(crate::make_credentials_callback(self.authenticator, &git_config, failure, self.overrides.clone(), run));
+			fetch_options.remote_callbacks(remote_callbacks);
+			match &mut rewritten {
+				Some(remote) => remote.fetch(&rewritten_refspecs, Some(&mut fetch_options), reflog_msg),
+				None => remote.fetch(refspecs, Some(&mut fetch_options), reflog_msg),
+			}
+		})
+	}
+
+	/// Push to a remote with the overridden settings.
+	///
+	/// See [`GitAuthenticator::push()`] for more details.
+	pub fn push(&self, repo: &git2::Repository, remote: &mut git2::Remote, refspecs: &[&str]) -> Result<(), Error> {
+		let git_config = self.authenticator.apply_env_config(&mut repo.config()?)?;
+		let (url, ssh_config_host) = self.resolve_remote_url(remote.pushurl().or(remote.url()));
+		if let Some(url) = &url {
+			capabilities::check_url(url)?;
+		}
+		let mut rewritten = self.authenticator.rewrite_remote(repo, remote.pushurl().or(remote.url()))?;
+		self.retry_usernames(telemetry::Operation::Push, url.as_deref().unwrap_or_default(), ssh_config_host, &git_config, |run, failure| {
+			let mut push_options = git2::PushOptions::new();
+			let mut remote_callbacks = git2::RemoteCallbacks::new();
+			let headers = run.custom_headers();
+			push_options.custom_headers(&headers.iter().map(String::as_str).collect::<Vec<_>>());
+
+			remote_callbacks.credentials(crate::make_credentials_callback(self.authenticator, &git_config, failure, self.overrides.clone(), run));
+			push_options.remote_callbacks(remote_callbacks);
+			match &mut rewritten {
+				Some(remote) => remote.push(refspecs, Some(&mut push_options)),
+				None => remote.push(refspecs, Some(&mut push_options)),
+			}
+		})
+	}
+
+	/// Push `HEAD` to a Gerrit server for review with the overridden settings.
+	///
+	/// See [`GitAuthenticator::push_for_review()`] for more details.
+	pub fn push_for_review(&self, repo: &git2::Repository, remote: &mut git2::Remote, branch: &str, topic: Option<&str>) -> Result<(), Error> {
+		let refspec = crate::gerrit_review_refspec("HEAD", branch, topic)?;
+		debug!("pushing for review with refspec: {refspec:?}");
+		self.push(repo, remote, &[&refspec])
+	}
+
+	/// Resolve the URL of a remote, see [`GitAuthenticator::resolve_url()`].
+	fn resolve_remote_url(&self, url: Option<&str>) -> (Option<String>, Option<String>) {
+		match url {
+			Some(url) => {
+				let (url, ssh_config_host) = self.authenticator.resolve_url(url);
+				(Some(url.into_owned()), ssh_config_host)
+			},
+			None => (None, None),
+		}
+	}
+
+	/// Run an operation, and retry it with other SSH usernames if the server rejected the username.
+	///
+	/// Each run of the operation gets an [`OperationRun`] with the username and headers to use.
+	/// Use `ssh_config_host` to look up a host alias in the SSH configuration instead of the host of the URL.
+	///
+	/// The operation is run only once if [`GitAuthenticator::retry_ssh_usernames()`] is disabled.
+	/// If the server rate limited the operation, it is retried with the same username after a delay (see [`GitAuthenticator::rate_limit_retries()`]).
+	///
+	/// The statistics of all runs together are reported to the telemetry sink of the authenticator.
+	fn retry_usernames<T>(
+		&self,
+		kind: telemetry::Operation,
+		url: &str,
+		ssh_config_host: Option<String>,
+		git_config: &git2::Config,
+		mut operation: impl FnMut(OperationRun, error::FailureTracker) -> Result<T, git2::Error>,
+	) -> Result<T, Error> {
+		let start = self.authenticator.clock.now();
+		let counters = telemetry::Counters::default();
+		let mut runs = 0;
+		let mut candidates = self.authenticator.username_candidates(url, ssh_config_host.as_deref(), git_config).into_iter();
+		let mut run = OperationRun {
+			username: candidates.next(),
+			authorization_retry: true,
+			authorization: None,
+			ssh_config_host,
+			operation_url: Some(url.into()),
+		};
+		let mut rate_limit_retries = self.authenticator.rate_limit_retries;
+		let mut backoff = rate_limit::Backoff::new(self.authenticator.rate_limit_backoff);
+		let result = loop {
+			let failure = error::FailureTracker::with_telemetry(counters.clone());
+			runs += 1;
+			let error = match operation(run.clone(), failure.clone()) {
+				Ok(x) => {
+					failure.approval().approve();
+					break Ok(x);
+				},
+				Err(e) => e,
+			};
+			if rate_limit_retries > 0 && rate_limit::is_rate_limited(&error) {
+				rate_limit_retries -= 1;
+				let delay = backoff.next_delay(rate_limit::retry_after(error.message()));
+				warn!("{url:?} is rate limited by the server, retrying in {delay:?}: {}", error.message());
+				self.authenticator.clock.sleep(delay);
+				continue;
+			}
+			if let Some(authorization) = failure.take_authorization() {
+				debug!("credential helper returned a pre-encoded credential for {url:?}, retrying with an Authorization header");
+				run.authorization = Some(authorization);
+				continue;
+			}
+			if !failure.username_rejected() {
+				break Err(failure.wrap(error));
+			}
+			match candidates.next() {
+				Some(username) => {
+					debug!("username {:?} was rejected for {url:?}, retrying with username {username:?}", run.username);
+					run.username = Some(username);
+				},
+				None => break Err(failure.wrap(error)),
+			}
+		};
+		self.authenticator.report_telemetry(|| {
+			let duration = self.authenticator.clock.now() - start;
+			counters.to_stats(kind, url, runs, result.as_ref().map(|_| ()), duration)
+		});
+		result
+	}
+
+	/// Simulate the authentication attempts with the overridden settings.
+	///
+	/// See [`GitAuthenticator::simulate()`] for more details.
+	pub fn simulate(&self, url: &str, username: Option<&str>, allowed: git2::CredentialType) -> Vec<PlannedAttempt> {
+		let mut state = crate::CallbackState::new(self.authenticator, &self.overrides, &OperationRun::default());
+		let mut planned = Vec::new();
+		while let Some(attempt) = state.next_attempt(url, username, allowed) {
+			planned.push(attempt.to_planned(self.authenticator, &self.overrides));
+			if attempt.is_final() {
+				break;
+			}
+		}
+		planned
+	}
+}
+
+/// Get the refspecs to fetch with an anonymous remote in place of a named remote.
+///
+/// An anonymous remote has no configured refspecs, so the configured fetch refspecs of the named remote are used if no refspecs are given.
+/// Refspecs without destination get the destination of the first configured refspec that matches,
+/// so the remote-tracking refs are updated like git does when fetching from the named remote.
+/// A refspec without destination that does not start with `refs/` is taken to be a branch name.
+fn anonymous_fetch_refspecs(remote: &git2::Remote, refspecs: &[&str]) -> Vec<String> {
+	let configured: Vec<git2::Refspec> = remote.refspecs()
+		.filter(|spec| spec.direction() == git2::Direction::Fetch)
+		.collect();
+	if refspecs.is_empty() {
+		return configured.iter().filter_map(|spec| spec.str().map(String::from)).collect();
+	}
+
+	refspecs.iter()
+		.map(|&refspec| {
+			let (force, src) = match refspec.strip_prefix('+') {
+				Some(src) => (true, src),
+				None => (false, refspec),
+			};
+			if src.is_empty() || src.contains(':') || src.contains('*') {
+				return refspec.to_owned();
+			}
+			let src = match src.starts_with("refs/") {
+				true => src.to_owned(),
+				false => format!("refs/heads/{src}"),
+			};
+			let tracking = configured.iter()
+				.filter(|spec| spec.src_matches(&src))
+				.find_map(|spec| Some((spec.is_force(), spec.transform(&src).ok()?.as_str()?.to_owned())));
+			match tracking {
+				Some((force_tracking, dst)) => format!("{}{src}:{dst}", if force || force_tracking { "+" } else { "" }),
+				None => refspec.to_owned(),
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+	use std::time::Duration;
+
+	#[test]
+	fn test_rate_limit_retries() {
+		let clock = crate::ManualClock::new();
+		let start = crate::Clock::now(&clock);
+		let auth = GitAuthenticator::new_empty()
+			.rate_limit_retries(2)
+			.rate_limit_backoff(Duration::from_secs(5))
+			.set_clock(clock.clone());
+		let_assert!(Ok(git_config) = git2::Config::new());
+		let rate_limited = || git2::Error::new(git2::ErrorCode::GenericError, git2::ErrorClass::Http, "unexpected http status code: 429");
+
+		let mut calls = 0;
+		let_assert!(Ok(()) = auth.scoped().retry_usernames(telemetry::Operation::Fetch, "https://example.com/repo.git", None, &git_config, |_, _| {
+			calls += 1;
+			if calls < 3 {
+				Err(rate_limited())
+			} else {
+				Ok(())
+			}
+		}));
+		assert!(crate::Clock::now(&clock) - start == Duration::from_secs(15));
+
+		let_assert!(Err(e) = auth.scoped().retry_usernames(telemetry::Operation::Fetch, "https://example.com/repo.git", None, &git_config, |_, _| Err::<(), _>(rate_limited())));
+		assert!(e.kind() == crate::ErrorKind::RateLimited);
+	}
+
+	#[test]
+	fn test_fetch_rewritten_remote() {
+		let_assert!(Ok(temp_dir) = crate::temp_dir::TempDir::create("auth-git2-test-fetch-rewritten"));
+		let source = temp_dir.path().join("source");
+		let_assert!(Ok(repo) = git2::Repository::init(&source));
+		let_assert!(Ok(signature) = git2::Signature::now("Test", "test@example.com"));
+		let_assert!(Ok(tree) = repo.index().and_then(|mut x| x.write_tree()).and_then(|x| repo.find_tree(x)));
+		let_assert!(Ok(first) = repo.commit(Some("HEAD"), &signature, &signature, "First commit", &tree, &[]));
+		let_assert!(Ok(branch) = repo.head().map(|x| x.shorthand().unwrap_or_default().to_owned()));
+
+		// The URL of the remote only works after rewriting it.
+		let_assert!(Ok(clone) = git2::Repository::clone(source.to_str().unwrap(), temp_dir.path().join("clone")));
+		assert!(let Ok(()) = clone.remote_set_url("origin", "https://example.invalid/source"));
+		let_assert!(Ok(mut remote) = clone.find_remote("origin"));
+		let auth = GitAuthenticator::new_empty().rewrite_url("https://example.invalid/source", source.to_str().unwrap());
+		let tracking = || clone.refname_to_id(&format!("refs/remotes/origin/{branch}")).ok();
+
+		assert!(anonymous_fetch_refspecs(&remote, &[]) == ["+refs/heads/*:refs/remotes/origin/*"]);
+		assert!(anonymous_fetch_refspecs(&remote, &["main", "refs/tags/v1", "+a:b"]) == [
+			"+refs/heads/main:refs/remotes/origin/main",
+			"refs/tags/v1",
+			"+a:b",
+		]);
+
+		// Without refspecs, the configured refspecs of the remote are used.
+		let_assert!(Ok(parent) = repo.find_commit(first));
+		let_assert!(Ok(second) = repo.commit(Some("HEAD"), &signature, &signature, "Second commit", &tree, &[&parent]));
+		assert!(let Ok(()) = auth.fetch(&clone, &mut remote, &[], None));
+		assert!(tracking() == Some(second));
+
+		// A branch name updates the remote-tracking branch.
+		let_assert!(Ok(parent) = repo.find_commit(second));
+		let_assert!(Ok(third) = repo.commit(Some("HEAD"), &signature, &signature, "Third commit", &tree, &[&parent]));
+		assert!(let Ok(()) = auth.fetch(&clone, &mut remote, &[&branch], None));
+		assert!(tracking() == Some(third));
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn test_authorization_retry() {
+		let_assert!(Ok(config_file) = crate::temp_key::TempKeyFile::create(
+			"[credential]\n\thelper = \"!f() { echo capability[]=authtype; echo authtype=Bearer; echo credential=token; }; f\"\n",
+		));
+		let_assert!(Ok(mut git_config) = git2::Config::new());
+		assert!(let Ok(()) = git_config.add_file(config_file.path(), git2::ConfigLevel::App, true));
+		let auth = GitAuthenticator::new_empty().try_cred_helper(true);
+		let allowed = git2::CredentialType::USER_PASS_PLAINTEXT;
+
+		let mut runs = 0;
+		let_assert!(Ok(()) = auth.scoped().retry_usernames(telemetry::Operation::Fetch, "https://example.com/repo.git", None, &git_config, |run, failure| {
+			runs += 1;
+			let headers = run.custom_headers();
+			let mut callback = crate::make_credentials_callback(&auth, &git_config, failure, Overrides::default(), run);
+			if runs == 1 {
+				assert!(headers.is_empty());
+				callback("https://example.com/repo.git", None, allowed).map(drop)
+			} else {
+				assert!(headers == ["Authorization: Bearer token"]);
+				// The server rejected the header, so the credential helper can not be used again.
+				assert!(let Err(_) = callback("https://example.com/repo.git", None, allowed));
+				Ok(())
+			}
+		}));
+		assert!(runs == 2);
+
+		// The plain credentials callback can not send the header.
+		let mut callback = auth.credentials(&git_config);
+		assert!(let Err(_) = callback("https://example.com/repo.git", None, allowed));
+	}
+
+	#[test]
+	fn test_redirect_credentials() {
+		let auth = GitAuthenticator::new_empty()
+			.add_plaintext_credentials("example.com", "alice", "hunter2")
+			.allow_redirect_credentials("*.mirror.example.com");
+		let_assert!(Ok(git_config) = git2::Config::new());
+		let allowed = git2::CredentialType::USER_PASS_PLAINTEXT;
+
+		let mut labels = Vec::new();
+		let_assert!(Ok(()) = auth.scoped().retry_usernames(telemetry::Operation::Fetch, "https://example.com/repo.git", None, &git_config, |run, failure| {
+			let mut callback = crate::make_labeled_credentials_callback(&auth, &git_config, failure, Overrides::default(), run.clone());
+			labels.push(callback("https://eu.mirror.example.com/repo.git", None, allowed).map(|x| x.label).ok());
+
+			// Other hosts and downgrades to plain HTTP do not get the credentials of the original host.
+			let mut callback = crate::make_labeled_credentials_callback(&auth, &git_config, error::FailureTracker::new(), Overrides::default(), run.clone());
+			labels.push(callback("https://example.org/repo.git", None, allowed).map(|x| x.label).ok());
+			let mut callback = crate::make_labeled_credentials_callback(&auth, &git_config, error::FailureTracker::new(), Overrides::default(), run);
+			labels.push(callback("http://eu.mirror.example.com/repo.git", None, allowed).map(|x| x.label).ok());
+			Ok(())
+		}));
+		assert!(labels == [Some("plaintext:alice".into()), None, None]);
+	}
+
+	#[test]
+	fn test_telemetry() {
+		let reported = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+		let auth = GitAuthenticator::new_empty()
+			.rate_limit_retries(1)
+			.rate_limit_backoff(Duration::ZERO)
+			.set_telemetry_sink({
+				let reported = reported.clone();
+				move |stats: &crate::OperationStats| reported.lock().unwrap().push(stats.clone())
+			});
+		let_assert!(Ok(git_config) = git2::Config::new());
+		let rate_limited = || git2::Error::new(git2::ErrorCode::GenericError, git2::ErrorClass::Http, "unexpected http status code: 429");
+		let_assert!(Err(_) = auth.scoped().retry_usernames(telemetry::Operation::Push, "https://example.com/repo.git", None, &git_config, |_, _| Err::<(), _>(rate_limited())));
+
+		let reported = reported.lock().unwrap();
+		let_assert!([stats] = reported.as_slice());
+		assert!(stats.operation == telemetry::Operation::Push);
+		assert!(stats.transport == "https");
+		assert!(!stats.succeeded);
+		assert!(stats.error_code == Some(crate::ErrorCode::RateLimited));
+		assert!(stats.runs == 2);
+		assert!(let None = stats.succeeded_with);
+	}
+}
@@ -0,0 +1,104 @@
+//! Parsing of scp-like SSH URLs, like `git@example.com:org/repo.git`.
+
+/// The parts of an scp-like SSH URL: `[user@]host:path`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct ScpUrl<'a> {
+	/// The username, if any.
+	pub user: Option<&'a str>,
+
+	/// The host, which may be an IPv6 address in brackets, or include a port when written as `[host:port]:path`.
+	pub host: &'a str,
+
+	/// The path, exactly as it appears after the colon.
+	pub path: &'a str,
+}
+
+impl<'a> ScpUrl<'a> {
+	/// Parse an scp-like URL, following the rules of git.
+	///
+	/// Returns `None` for real URLs with a scheme and for local paths.
+	/// Like git, the text is a local path if it has a slash before the first colon.
+	/// The host may be put in brackets to use an IPv6 address (`[::1]:repo.git` or `git@[::1]:repo.git`)
+	/// or a port (`[git@example.com:2222]:repo.git`).
+	/// The username is everything before the last `@` before the colon, so the path may contain `@` and `:`.
+	pub(crate) fn parse(url: &'a str) -> Option<Self> {
+		let colon = match url.find('[') {
+			// A bracketed host can contain colons, so look for the colon after the closing bracket.
+			Some(open) if !url[..open].contains([':', '/']) => {
+				let close = open + url[open..].find(']')?;
+				if url.as_bytes().get(close + 1) != Some(&b':') {
+					return None;
+				}
+				close + 1
+			},
+			_ => url.find(':')?,
+		};
+		let (authority, path) = (&url[..colon], &url[colon + 1..]);
+		if path.starts_with("//") || authority.is_empty() || authority.contains(['/', '\\']) {
+			return None;
+		}
+		if cfg!(windows) && authority.len() == 1 && authority.as_bytes()[0].is_ascii_alphabetic() {
+			// A drive letter, not a host.
+			return None;
+		}
+
+		// Remove the brackets around `[user@host:port]`, but keep them around IPv6 addresses.
+		let authority = match authority.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+			Some(inner) if inner.contains('@') || inner.matches(':').count() < 2 => inner,
+			_ => authority,
+		};
+		let (user, host) = match authority.rsplit_once('@') {
+			Some((user, host)) => (Some(user), host),
+			None => (None, authority),
+		};
+		if host.is_empty() || host == "[]" {
+			return None;
+		}
+		Some(Self { user, host, path })
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	fn parse(url: &str) -> Option<(Option<&str>, &str, &str)> {
+		ScpUrl::parse(url).map(|x| (x.user, x.host, x.path))
+	}
+
+	#[test]
+	fn test_parse() {
+		// Host names, single-label hosts and IPv4 addresses.
+		assert!(parse("git@example.com:org/repo.git") == Some((Some("git"), "example.com", "org/repo.git")));
+		assert!(parse("example.com:repo.git") == Some((None, "example.com", "repo.git")));
+		assert!(parse("gitserver:repo.git") == Some((None, "gitserver", "repo.git")));
+		assert!(parse("10.0.0.5:repo.git") == Some((None, "10.0.0.5", "repo.git")));
+		assert!(parse("git@10.0.0.5:/srv/git/repo.git") == Some((Some("git"), "10.0.0.5", "/srv/git/repo.git")));
+
+		// IPv6 addresses and ports in brackets.
+		assert!(parse("[::1]:repo.git") == Some((None, "[::1]", "repo.git")));
+		assert!(parse("git@[fe80::1]:repo.git") == Some((Some("git"), "[fe80::1]", "repo.git")));
+		assert!(parse("[git@example.com:2222]:repo.git") == Some((Some("git"), "example.com:2222", "repo.git")));
+		assert!(parse("[10.0.0.5:2222]:repo.git") == Some((None, "10.0.0.5:2222", "repo.git")));
+		assert!(parse("[gitserver]:repo.git") == Some((None, "gitserver", "repo.git")));
+
+		// Paths and usernames containing `@` and `:`.
+		assert!(parse("git@example.com:org/repo@v2.git") == Some((Some("git"), "example.com", "org/repo@v2.git")));
+		assert!(parse("example.com:path@with:stuff") == Some((None, "example.com", "path@with:stuff")));
+		assert!(parse("alice@corp.example@example.com:repo.git") == Some((Some("alice@corp.example"), "example.com", "repo.git")));
+		assert!(parse("example.com:") == Some((None, "example.com", "")));
+
+		// Real URLs and local paths.
+		assert!(let None = parse("ssh://git@example.com/repo.git"));
+		assert!(let None = parse("file:///srv/git/repo.git"));
+		assert!(let None = parse("some/relative/path"));
+		assert!(let None = parse("./dir:with:colons"));
+		assert!(let None = parse("/srv/git/repo:name.git"));
+		assert!(let None = parse("some/path/[::1]:repo.git"));
+		assert!(let None = parse(":repo.git"));
+		assert!(let None = parse("git@:repo.git"));
+		assert!(let None = parse("[::1]repo.git"));
+		assert!(let None = parse("[::1"));
+	}
+}
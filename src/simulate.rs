@@ -0,0 +1,186 @@
+use std::path::PathBuf;
+
+/// An authentication attempt that the credentials callback would make.
+///
+/// See [`GitAuthenticator::simulate()`][crate::GitAuthenticator::simulate()] for more details.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PlannedAttempt {
+	/// Return a username to libgit2, without any real credentials.
+	///
+	/// This happens when the URL does not specify a username.
+	/// After receiving the username, libgit2 will invoke the callback again to ask for the real credentials.
+	Username {
+		/// The username that would be returned.
+		username: String,
+	},
+
+	/// Try to authenticate with a key from the SSH agent.
+	SshAgent {
+		/// The username used for authentication.
+		username: String,
+	},
+
+	/// Try to authenticate with a private key file.
+	SshKeyFile {
+		/// The username used for authentication.
+		username: String,
+
+		/// The path of the private key file.
+		private_key: PathBuf,
+
+		/// Was a password for the private key provided explicitly?
+		has_password: bool,
+
+		/// Would the user be prompted for the password if the key turns out to be encrypted?
+		may_prompt_password: bool,
+	},
+
+	/// Try to authenticate with a private key that was read from an environment variable.
+	SshKeyMemory {
+		/// The username used for authentication.
+		username: String,
+
+		/// The name of the environment variable that the key was read from.
+		var_name: String,
+
+		/// Was a password for the private key provided explicitly?
+		has_password: bool,
+	},
+
+	/// Use the default credentials of the current user for Negotiate or NTLM authentication.
+	DefaultCredentials,
+
+	/// Use credentials from a callback that was added with [`GitAuthenticator::add_refreshing_credentials()`][crate::GitAuthenticator::add_refreshing_credentials()].
+	///
+	/// The callback is only called at runtime if there are no cached credentials that did not expire yet.
+	RefreshingCredentials {
+		/// The domain that the callback was added for.
+		domain: String,
+	},
+
+	/// Use plaintext credentials that were added with [`GitAuthenticator::add_plaintext_credentials()`][crate::GitAuthenticator::add_plaintext_credentials()].
+	PlaintextCredentials {
+		/// The username of the credentials.
+		username: String,
+	},
+
+	/// Read the username and password from environment variables.
+	///
+	/// The attempt is skipped at runtime if the variables are not set.
+	EnvCredentials {
+		/// The environment variable with the username.
+		username_var: String,
+
+		/// The environment variable with the password.
+		password_var: String,
+	},
+
+	/// Read the password from a systemd credential that was added with [`GitAuthenticator::add_credentials_from_systemd()`][crate::GitAuthenticator::add_credentials_from_systemd()].
+	///
+	/// The attempt is skipped at runtime if `CREDENTIALS_DIRECTORY` is not set, the credential does not exist or there is no username.
+	SystemdCredentials {
+		/// The name of the credential.
+		name: String,
+	},
+
+	/// Look up the username and password in the `.netrc` file.
+	Netrc,
+
+	/// Look up the username and password in the files of the `store` credential helper.
+	CredentialStore,
+
+	/// Look up the username and password in an entry of `pass`.
+	Pass {
+		/// The name of the entry.
+		entry: String,
+	},
+
+	/// Read the username and password from a secret in HashiCorp Vault.
+	///
+	/// This is only used with the `vault` feature.
+	Vault {
+		/// The path of the secret, including the mount point.
+		path: String,
+	},
+
+	/// Look up the username and password with the Bitwarden CLI.
+	///
+	/// This is only used with the `bitwarden` feature.
+	Bitwarden,
+
+	/// Get the username and password from a docker credential helper.
+	///
+	/// This is only used with the `docker-credential-helper` feature.
+	DockerCredentialHelper {
+		/// The name of the helper, without the `docker-credential-` prefix.
+		helper: String,
+	},
+
+	/// Get an installation access token for a GitHub App.
+	///
+	/// This is only used with the `github-app` feature.
+	GitHubApp {
+		/// The app ID of the GitHub App.
+		app_id: String,
+	},
+
+	/// Get a token from the GitHub CLI.
+	///
+	/// This is only planned for GitHub hosts.
+	GhCli,
+
+	/// Get a token from the GitLab CLI.
+	///
+	/// This is only planned for GitLab hosts.
+	GlabCli,
+
+	/// Get an access token from the Google Cloud CLI.
+	///
+	/// This is only planned for Google Cloud Source Repositories.
+	Gcloud,
+
+	/// Look up the password in the OS keyring.
+	///
+	/// This is only used with the `keyring` feature.
+	Keyring {
+		/// The username to look up the password for.
+		username: String,
+	},
+
+	/// Look up the password in the macOS Keychain.
+	///
+	/// This is only used on macOS.
+	MacosKeychain,
+
+	/// Use cached credentials from the git credential helper,
+	/// see [`GitAuthenticator::cache_helper_credentials()`][crate::GitAuthenticator::cache_helper_credentials()].
+	CachedHelperCredentials {
+		/// The cached username.
+		username: String,
+	},
+
+	/// Query the git credential helper.
+	CredentialHelper,
+
+	/// Let the user authorize access in a browser with the OAuth device flow.
+	///
+	/// This is only used with the `oauth-device-flow` feature.
+	OAuthDeviceFlow {
+		/// The client ID of the OAuth application.
+		client_id: String,
+	},
+
+	/// Use credentials that the user entered in a prompt recently,
+	/// see [`GitAuthenticator::remember_prompted_credentials()`][crate::GitAuthenticator::remember_prompted_credentials()].
+	RememberedCredentials {
+		/// The remembered username.
+		username: String,
+	},
+
+	/// Prompt the user for a password (and a username if it is not known yet).
+	Prompt {
+		/// The username that is already known, if any.
+		username: Option<String>,
+	},
+}
@@ -0,0 +1,248 @@
+//! Minimal client for the SSH agent protocol, to list the keys loaded in the agent.
+//!
+//! libgit2 offers all keys of the agent to the server, and it can not be told to use a specific key.
+//! The authenticator lists the keys itself to find the keys selected with
+//! [`GitAuthenticator::try_ssh_agent_key()`][crate::GitAuthenticator::try_ssh_agent_key()].
+//!
+//! On Windows, the agent listens on a named pipe instead of a unix socket, or it is Pageant (see the `pageant` module).
+
+use std::path::{Path, PathBuf};
+
+use crate::base64_decode::base64_decode;
+use crate::sha256::sha256;
+
+/// Message type of a request for the list of keys.
+#[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+
+/// Message type of the response with the list of keys.
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+
+/// The maximum size of a response that is accepted from the agent.
+#[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+const MAX_RESPONSE_SIZE: usize = 256 * 1024;
+
+/// A public key loaded in the SSH agent.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct AgentKey {
+	/// The public key in the SSH wire format.
+	pub blob: Vec<u8>,
+
+	/// The comment of the key, normally the path of the key file or an email address.
+	pub comment: String,
+}
+
+impl AgentKey {
+	/// Get the SHA256 fingerprint of the key, in the format of `ssh-add -l`.
+	pub(crate) fn fingerprint(&self) -> String {
+		format!("SHA256:{}", base64_encode_unpadded(&sha256(&self.blob)))
+	}
+
+	/// Check if the key matches a SHA256 fingerprint or the exact comment of the key.
+	pub(crate) fn matches(&self, fingerprint_or_comment: &str) -> bool {
+		match fingerprint_or_comment.strip_prefix("SHA256:") {
+			Some(fingerprint) => self.fingerprint()["SHA256:".len()..] == *fingerprint.trim_end_matches('='),
+			None => self.comment == fingerprint_or_comment,
+		}
+	}
+
+	/// Check if the key is backed by a FIDO2 security key.
+	pub(crate) fn is_security_key(&self) -> bool {
+		is_security_key(&self.blob)
+	}
+}
+
+/// The named pipe of the Windows OpenSSH agent.
+const OPENSSH_AGENT_PIPE: &str = r"\\.\pipe\openssh-ssh-agent";
+
+/// Get the socket of the SSH agent to use if `SSH_AUTH_SOCK` is not set.
+///
+/// On Windows, this is the named pipe of the OpenSSH agent that comes with Windows.
+/// On other platforms, there is no default socket.
+pub(crate) fn default_socket() -> Option<PathBuf> {
+	if cfg!(windows) {
+		Some(PathBuf::from(OPENSSH_AGENT_PIPE))
+	} else {
+		None
+	}
+}
+
+/// Get the socket of the SSH agent that libssh2 connects to.
+///
+/// This is the socket from `SSH_AUTH_SOCK`, or the default socket if it is not set.
+pub(crate) fn libssh2_socket() -> Option<PathBuf> {
+	std::env::var_os("SSH_AUTH_SOCK")
+		.filter(|x| !x.is_empty())
+		.map(PathBuf::from)
+		.or_else(default_socket)
+}
+
+/// List the keys of the SSH agent that listens on a socket.
+#[cfg(unix)]
+pub(crate) fn list_keys(socket: &Path) -> std::io::Result<Vec<AgentKey>> {
+	let mut stream = std::os::unix::net::UnixStream::connect(socket)?;
+	stream.set_read_timeout(Some(std::time::Duration::from_secs(5)))?;
+	stream.set_write_timeout(Some(std::time::Duration::from_secs(5)))?;
+	let response = request_identities(&mut stream)?;
+	parse_identities(&response)
+		.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid list of keys from SSH agent"))
+}
+
+/// List the keys of the SSH agent that listens on a named pipe.
+///
+/// If the pipe is the default pipe of the Windows OpenSSH agent and that agent is not running, the keys of Pageant are listed instead.
+/// Sockets that are not a named pipe, like the socket emulation of the `ssh-agent` of Git for Windows, are not supported.
+#[cfg(windows)]
+pub(crate) fn list_keys(socket: &Path) -> std::io::Result<Vec<AgentKey>> {
+	let Some(pipe) = socket.to_str().filter(|x| x.starts_with(r"\\.\pipe\")) else {
+		return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, format!("{} is not a named pipe", socket.display())));
+	};
+	let response = match std::fs::OpenOptions::new().read(true).write(true).open(pipe) {
+		Ok(mut pipe) => request_identities(&mut pipe)?,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound && pipe == OPENSSH_AGENT_PIPE => {
+			crate::pageant::request(&[SSH_AGENTC_REQUEST_IDENTITIES])
+				.map_err(|pageant| std::io::Error::new(pageant.kind(), format!("the OpenSSH agent is not running ({e}), and {pageant}")))?
+		},
+		Err(e) => return Err(e),
+	};
+	parse_identities(&response)
+		.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid list of keys from SSH agent"))
+}
+
+/// List the keys of the SSH agent that listens on a socket.
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn list_keys(_socket: &Path) -> std::io::Result<Vec<AgentKey>> {
+	Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "listing the keys of the SSH agent is not supported on this platform"))
+}
+
+/// Send a request for the list of keys to a connected agent, and read the response without the length prefix.
+#[cfg(any(unix, windows))]
+fn request_identities(stream: &mut (impl std::io::Read + std::io::Write)) -> std::io::Result<Vec<u8>> {
+	stream.write_all(&[0, 0, 0, 1, SSH_AGENTC_REQUEST_IDENTITIES])?;
+	let mut len = [0; 4];
+	stream.read_exact(&mut len)?;
+	let len = u32::from_be_bytes(len) as usize;
+	if len > MAX_RESPONSE_SIZE {
+		return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("response of SSH agent is too large ({len} bytes)")));
+	}
+	let mut response = vec![0; len];
+	stream.read_exact(&mut response)?;
+	Ok(response)
+}
+
+/// Read the key blob from a public key file in the OpenSSH format: `type base64-blob comment`.
+pub(crate) fn read_public_key_blob(path: &Path) -> Option<Vec<u8>> {
+	let contents = std::fs::read_to_string(path).ok()?;
+	let blob = contents.split_whitespace().nth(1)?;
+	base64_decode(blob.as_bytes()).ok()
+}
+
+/// Check if a public key in the SSH wire format is backed by a FIDO2 security key.
+///
+/// The key types of security keys start with `sk-`, like `sk-ssh-ed25519@openssh.com`, also for their certificates.
+pub(crate) fn is_security_key(blob: &[u8]) -> bool {
+	let mut data = blob;
+	read_string(&mut data).is_some_and(|kind| kind.starts_with(b"sk-"))
+}
+
+/// Parse the response of the agent to a request for the list of keys.
+fn parse_identities(response: &[u8]) -> Option<Vec<AgentKey>> {
+	let (&kind, mut data) = response.split_first()?;
+	if kind != SSH_AGENT_IDENTITIES_ANSWER {
+		return None;
+	}
+	let count = read_u32(&mut data)?;
+	let mut keys = Vec::new();
+	for _ in 0..count {
+		let blob = read_string(&mut data)?.to_vec();
+		let comment = String::from_utf8_lossy(read_string(&mut data)?).into_owned();
+		keys.push(AgentKey { blob, comment });
+	}
+	Some(keys)
+}
+
+/// Read a big-endian `u32` from the start of some data.
+fn read_u32(data: &mut &[u8]) -> Option<u32> {
+	let value = data.get(..4)?;
+	let value = u32::from_be_bytes([value[0], value[1], value[2], value[3]]);
+	*data = &data[4..];
+	Some(value)
+}
+
+/// Read a string with a `u32` length prefix from the start of some data.
+fn read_string<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+	let len = read_u32(data)? as usize;
+	if data.len() < len {
+		return None;
+	}
+	let (value, rest) = data.split_at(len);
+	*data = rest;
+	Some(value)
+}
+
+/// Encode data as base64 without padding, like the fingerprints of OpenSSH.
+pub(crate) fn base64_encode_unpadded(data: &[u8]) -> String {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut output = String::with_capacity(data.len().div_ceil(3) * 4);
+	for chunk in data.chunks(3) {
+		let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+		let value = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+		for i in 0..=chunk.len() {
+			output.push(ALPHABET[(value >> (18 - 6 * i) & 0x3F) as usize] as char);
+		}
+	}
+	output
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	/// A public key and its fingerprint, generated with `ssh-keygen -t ed25519 -C deploy@example`.
+	pub(crate) const PUBLIC_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIIzejRMtfIbeeux9Pmix19rLWm1TDRdxK6QwCBTykVnI deploy@example\n";
+	pub(crate) const FINGERPRINT: &str = "SHA256:kvqQnHZ80/ZKmOMBS2G1smr2cTyJx4zxEl9hnybyCXs";
+
+	/// Encode a response to a request for the list of keys.
+	pub(crate) fn identities_answer(keys: &[(&[u8], &str)]) -> Vec<u8> {
+		let mut body = vec![SSH_AGENT_IDENTITIES_ANSWER];
+		body.extend_from_slice(&(keys.len() as u32).to_be_bytes());
+		for (blob, comment) in keys {
+			body.extend_from_slice(&(blob.len() as u32).to_be_bytes());
+			body.extend_from_slice(blob);
+			body.extend_from_slice(&(comment.len() as u32).to_be_bytes());
+			body.extend_from_slice(comment.as_bytes());
+		}
+		let mut message = (body.len() as u32).to_be_bytes().to_vec();
+		message.extend_from_slice(&body);
+		message
+	}
+
+	#[test]
+	fn test_parse_identities() {
+		let_assert!(Ok(blob) = base64_decode(PUBLIC_KEY.split_whitespace().nth(1).unwrap().as_bytes()));
+		let message = identities_answer(&[(&blob, "deploy@example"), (b"other", "other@example")]);
+		let_assert!(Some(keys) = parse_identities(&message[4..]));
+		assert!(keys.len() == 2);
+		assert!(keys[0].fingerprint() == FINGERPRINT);
+		assert!(keys[0].matches(FINGERPRINT));
+		assert!(keys[0].matches(&format!("{FINGERPRINT}=")));
+		assert!(keys[0].matches("deploy@example"));
+		assert!(!keys[0].matches("deploy"));
+		assert!(keys[1].comment == "other@example");
+		assert!(!keys[1].matches(FINGERPRINT));
+
+		// Truncated responses are rejected.
+		assert!(let None = parse_identities(&message[4..message.len() - 1]));
+		assert!(let None = parse_identities(&[5]));
+	}
+
+	#[test]
+	fn test_base64_encode_unpadded() {
+		assert!(base64_encode_unpadded(b"").is_empty());
+		assert!(base64_encode_unpadded(b"f") == "Zg");
+		assert!(base64_encode_unpadded(b"fo") == "Zm8");
+		assert!(base64_encode_unpadded(b"foo") == "Zm9v");
+		assert!(base64_encode_unpadded(b"foobar") == "Zm9vYmFy");
+	}
+}
@@ -0,0 +1,429 @@
+//! Minimal parser for the OpenSSH client configuration file.
+//!
+//! Only the options that matter for authentication are used: `HostName`, `Port`, `User` and `IdentityFile`.
+//! `Match` blocks and `Include` directives are not supported: they are skipped with a debug message.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+/// A parsed OpenSSH client configuration file.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub(crate) struct SshConfig {
+	/// The blocks of the file, in order.
+	blocks: Vec<Block>,
+}
+
+/// A block of options that applies to some hosts.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Block {
+	/// The host patterns of the block.
+	///
+	/// `None` for the options before the first `Host` line, which apply to all hosts.
+	/// An empty list never matches, which is used for unsupported `Match` blocks.
+	patterns: Option<Vec<String>>,
+
+	/// The options of the block, with the keyword in lowercase.
+	options: Vec<(String, String)>,
+}
+
+/// The options of the SSH configuration for a single host.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub(crate) struct HostConfig {
+	/// The real host name to connect to.
+	pub hostname: Option<String>,
+
+	/// The port to connect to.
+	pub port: Option<u16>,
+
+	/// The username to log in with.
+	pub user: Option<String>,
+
+	/// The identity files to try, before token expansion.
+	pub identity_files: Vec<String>,
+}
+
+impl SshConfig {
+	/// Read and parse a configuration file.
+	///
+	/// Returns `None` if the file does not exist or can not be read.
+	pub(crate) fn read(path: &Path) -> Option<Self> {
+		match std::fs::read_to_string(path) {
+			Ok(text) => Some(Self::parse(&text)),
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+				debug!("No SSH configuration file at {}", path.display());
+				None
+			},
+			Err(e) => {
+				warn!("Failed to read SSH configuration file {}: {e}", path.display());
+				None
+			},
+		}
+	}
+
+	/// Parse the contents of a configuration file.
+	pub(crate) fn parse(text: &str) -> Self {
+		let mut blocks = vec![Block { patterns: None, options: Vec::new() }];
+		for line in text.lines() {
+			let Some((keyword, args)) = split_line(line) else {
+				continue;
+			};
+			let keyword = keyword.to_ascii_lowercase();
+			match keyword.as_str() {
+				"host" => blocks.push(Block {
+					patterns: Some(split_args(args)),
+					options: Vec::new(),
+				}),
+				"match" => {
+					debug!("Match blocks in the SSH configuration are not supported, ignoring: {line:?}");
+					blocks.push(Block { patterns: Some(Vec::new()), options: Vec::new() });
+				},
+				"include" => debug!("Include directives in the SSH configuration are not supported, ignoring: {line:?}"),
+				_ => {
+					let value = split_args(args).into_iter().next().unwrap_or_default();
+					if let Some(block) = blocks.last_mut() {
+						block.options.push((keyword, value));
+					}
+				},
+			}
+		}
+		Self { blocks }
+	}
+
+	/// Get the options for a host, as given on the command line or in the URL.
+	///
+	/// Like OpenSSH, the first value of an option wins, except for `IdentityFile`, which can be given multiple times.
+	pub(crate) fn lookup(&self, host: &str) -> HostConfig {
+		let mut config = HostConfig::default();
+		for block in &self.blocks {
+			if !block.matches(host) {
+				continue;
+			}
+			for (keyword, value) in &block.options {
+				match keyword.as_str() {
+					"hostname" => {
+						config.hostname.get_or_insert_with(|| value.replace("%h", host));
+					},
+					"port" if config.port.is_none() => config.port = value.parse().ok(),
+					"user" => {
+						config.user.get_or_insert_with(|| value.clone());
+					},
+					"identityfile" if !value.eq_ignore_ascii_case("none") => config.identity_files.push(value.clone()),
+					_ => (),
+				}
+			}
+		}
+		config
+	}
+}
+
+impl Block {
+	/// Check if the block applies to a host.
+	///
+	/// A negated pattern that matches excludes the host, even if other patterns match.
+	fn matches(&self, host: &str) -> bool {
+		let Some(patterns) = &self.patterns else {
+			return true;
+		};
+		let mut matched = false;
+		for pattern in patterns {
+			match pattern.strip_prefix('!') {
+				Some(pattern) => if glob_matches(pattern, host) {
+					return false;
+				},
+				None => matched |= glob_matches(pattern, host),
+			}
+		}
+		matched
+	}
+}
+
+impl HostConfig {
+	/// Get the paths of the identity files, with the tokens and the `~` prefix expanded.
+	///
+	/// The `host` is the host as given in the URL, `username` is the remote username, if known,
+	/// and `local_user` is the name of the local user, if known.
+	pub(crate) fn identity_paths(&self, host: &str, username: Option<&str>, local_user: Option<&str>, home: Option<&Path>) -> Vec<PathBuf> {
+		let hostname = self.hostname.as_deref().unwrap_or(host);
+		let username = username.or(self.user.as_deref());
+		self.identity_files.iter()
+			.filter_map(|file| expand_path(file, host, hostname, username, local_user, home))
+			.collect()
+	}
+}
+
+/// The parts of an SSH URL that are affected by the SSH configuration.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct SshUrl {
+	/// The URL uses the scp-like syntax `user@host:path`.
+	pub scp_like: bool,
+
+	/// The username in the URL.
+	pub user: Option<String>,
+
+	/// The host in the URL.
+	pub host: String,
+
+	/// The port in the URL.
+	pub port: Option<u16>,
+
+	/// The path in the URL, including the leading slash for `ssh://` URLs.
+	pub path: String,
+}
+
+impl SshUrl {
+	/// Parse an `ssh://`, `git+ssh://` or `ssh+git://` URL, or an scp-like URL.
+	///
+	/// Returns `None` for other URLs.
+	pub(crate) fn parse(url: &str) -> Option<Self> {
+		if let Some((scheme, rest)) = url.split_once("://") {
+			if !matches!(scheme, "ssh" | "git+ssh" | "ssh+git") {
+				return None;
+			}
+			let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+			let (user, host_port) = split_user(authority);
+			let (host, port) = match host_port.rsplit_once(':') {
+				Some((host, port)) => (host, Some(port.parse().ok()?)),
+				None => (host_port, None),
+			};
+			if host.is_empty() {
+				return None;
+			}
+			Some(Self { scp_like: false, user, host: host.into(), port, path: path.into() })
+		} else {
+			let url = crate::scp_url::ScpUrl::parse(url)?;
+			// The host can include a port when written as `[host:port]:path`.
+			let (host, port) = match url.host.rsplit_once(':') {
+				Some((host, port)) if !url.host.starts_with('[') => (host, Some(port.parse().ok()?)),
+				_ => (url.host, None),
+			};
+			let user = url.user.map(String::from);
+			Some(Self { scp_like: true, user, host: host.into(), port, path: url.path.into() })
+		}
+	}
+
+	/// Format the URL again.
+	///
+	/// An scp-like URL stays scp-like, unless it needs a port: then it is converted to an `ssh://` URL.
+	pub(crate) fn to_url(&self) -> String {
+		let user = self.user.as_ref().map(|user| format!("{user}@")).unwrap_or_default();
+		match self.port {
+			None if self.scp_like => format!("{user}{}:{}", self.host, self.path),
+			None => format!("ssh://{user}{}{}", self.host, self.path),
+			Some(port) if self.scp_like && !self.path.starts_with('/') => format!("ssh://{user}{}:{port}/~/{}", self.host, self.path),
+			Some(port) => format!("ssh://{user}{}:{port}{}", self.host, self.path),
+		}
+	}
+}
+
+/// Split the username from the authority part of a URL.
+fn split_user(authority: &str) -> (Option<String>, &str) {
+	match authority.rsplit_once('@') {
+		Some((user, host)) => (Some(user.into()), host),
+		None => (None, authority),
+	}
+}
+
+/// Split a line in the keyword and the arguments.
+///
+/// Returns `None` for empty lines and comments.
+fn split_line(line: &str) -> Option<(&str, &str)> {
+	let line = line.trim();
+	if line.is_empty() || line.starts_with('#') {
+		return None;
+	}
+	let end = line.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(line.len());
+	let (keyword, rest) = line.split_at(end);
+	let rest = rest.trim_start();
+	let rest = rest.strip_prefix('=').unwrap_or(rest).trim_start();
+	Some((keyword, rest))
+}
+
+/// Split the arguments of an option, taking double quotes into account.
+fn split_args(args: &str) -> Vec<String> {
+	let mut result = Vec::new();
+	let mut current = String::new();
+	let mut in_quotes = false;
+	let mut has_arg = false;
+	for c in args.chars() {
+		match c {
+			'"' => {
+				in_quotes = !in_quotes;
+				has_arg = true;
+			},
+			c if c.is_whitespace() && !in_quotes => {
+				if has_arg {
+					result.push(std::mem::take(&mut current));
+					has_arg = false;
+				}
+			},
+			c => {
+				current.push(c);
+				has_arg = true;
+			},
+		}
+	}
+	if has_arg {
+		result.push(current);
+	}
+	result
+}
+
+/// Check if a host matches a pattern with `*` and `?` wildcards, ignoring case.
+fn glob_matches(pattern: &str, host: &str) -> bool {
+	let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+	let host: Vec<char> = host.to_lowercase().chars().collect();
+	let (mut p, mut h) = (0, 0);
+	let mut backtrack = None;
+	while h < host.len() {
+		if p < pattern.len() && (pattern[p] == '?' || pattern[p] == host[h]) {
+			p += 1;
+			h += 1;
+		} else if p < pattern.len() && pattern[p] == '*' {
+			backtrack = Some((p, h));
+			p += 1;
+		} else if let Some((star, matched)) = backtrack {
+			p = star + 1;
+			h = matched + 1;
+			backtrack = Some((star, matched + 1));
+		} else {
+			return false;
+		}
+	}
+	pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Expand the `~` prefix and the tokens in an `IdentityFile` path.
+///
+/// Supported tokens are `%%`, `%d` (home directory), `%h` (host name), `%n` (host as given), `%r` (remote user) and `%u` (local user).
+/// Returns `None` if a token can not be expanded.
+fn expand_path(path: &str, host: &str, hostname: &str, username: Option<&str>, local_user: Option<&str>, home: Option<&Path>) -> Option<PathBuf> {
+	let home_str = home.map(|x| x.to_string_lossy().into_owned());
+	let mut result = String::new();
+	let mut rest = path;
+	if let Some(tail) = path.strip_prefix("~/").or((path == "~").then_some("")) {
+		result.push_str(home_str.as_deref()?);
+		result.push('/');
+		rest = tail;
+	}
+	let mut chars = rest.chars();
+	while let Some(c) = chars.next() {
+		if c != '%' {
+			result.push(c);
+			continue;
+		}
+		match chars.next() {
+			Some('%') => result.push('%'),
+			Some('d') => result.push_str(home_str.as_deref()?),
+			Some('h') => result.push_str(hostname),
+			Some('n') => result.push_str(host),
+			Some('r') => result.push_str(username?),
+			Some('u') => result.push_str(local_user?),
+			other => {
+				debug!("Unsupported token %{} in SSH IdentityFile {path:?}, skipping it", other.unwrap_or(' '));
+				return None;
+			},
+		}
+	}
+	Some(PathBuf::from(result))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_glob_matches() {
+		assert!(glob_matches("*", "github.com"));
+		assert!(glob_matches("*.example.com", "git.example.com"));
+		assert!(!glob_matches("*.example.com", "example.com"));
+		assert!(glob_matches("git?.example.com", "git1.example.com"));
+		assert!(glob_matches("GitHub.com", "github.com"));
+		assert!(!glob_matches("github.com", "github.com.evil"));
+	}
+
+	#[test]
+	fn test_split() {
+		assert!(let None = split_line("  # comment"));
+		assert!(split_line("HostName example.com") == Some(("HostName", "example.com")));
+		assert!(split_line("Port=2222") == Some(("Port", "2222")));
+		assert!(split_line("User = git") == Some(("User", "git")));
+		assert!(split_args(r#"work "with space" *.example.com"#) == ["work", "with space", "*.example.com"]);
+	}
+
+	#[test]
+	fn test_ssh_url() {
+		let_assert!(Some(url) = SshUrl::parse("git@work:org/repo.git"));
+		assert!(url.scp_like);
+		assert!(url.user.as_deref() == Some("git"));
+		assert!(url.host == "work");
+		assert!(url.to_url() == "git@work:org/repo.git");
+
+		let_assert!(Some(mut url) = SshUrl::parse("work:org/repo.git"));
+		url.port = Some(2222);
+		assert!(url.to_url() == "ssh://work:2222/~/org/repo.git");
+
+		let_assert!(Some(url) = SshUrl::parse("[git@10.0.0.5:2222]:org/repo.git"));
+		assert!(url.host == "10.0.0.5");
+		assert!(url.port == Some(2222));
+		assert!(url.to_url() == "ssh://git@10.0.0.5:2222/~/org/repo.git");
+
+		let_assert!(Some(url) = SshUrl::parse("git+ssh://alice@example.com:2222/org/repo.git"));
+		assert!(!url.scp_like);
+		assert!(url.user.as_deref() == Some("alice"));
+		assert!(url.port == Some(2222));
+		assert!(url.to_url() == "ssh://alice@example.com:2222/org/repo.git");
+
+		assert!(let None = SshUrl::parse("https://example.com/repo.git"));
+		assert!(let None = SshUrl::parse("/local/path:with/colon"));
+	}
+
+	#[test]
+	fn test_lookup() {
+		let config = SshConfig::parse(r#"
+			IdentityFile ~/.ssh/global
+
+			Host work
+				HostName github.com
+				User git
+				IdentityFile "~/.ssh/work key"
+				IdentitiesOnly yes
+
+			Host *.example.com !secret.example.com
+				User alice
+				Port 2222
+				IdentityFile %d/.ssh/%h_%r
+
+			Match exec "true"
+				User ignored
+
+			Host *
+				User fallback
+				IdentityFile none
+		"#);
+
+		let work = config.lookup("work");
+		assert!(work.hostname.as_deref() == Some("github.com"));
+		assert!(work.user.as_deref() == Some("git"));
+		assert!(let None = work.port);
+		assert!(work.identity_paths("work", None, None, Some(Path::new("/home/me"))) == [
+			PathBuf::from("/home/me/.ssh/global"),
+			PathBuf::from("/home/me/.ssh/work key"),
+		]);
+
+		let git = config.lookup("git.example.com");
+		assert!(let None = git.hostname);
+		assert!(git.port == Some(2222));
+		assert!(git.user.as_deref() == Some("alice"));
+		assert!(git.identity_paths("git.example.com", None, None, Some(Path::new("/home/me")))[1] == Path::new("/home/me/.ssh/git.example.com_alice"));
+
+		let secret = config.lookup("secret.example.com");
+		assert!(secret.user.as_deref() == Some("fallback"));
+		assert!(secret.identity_files == ["~/.ssh/global"]);
+
+		// The tilde can not be expanded without a home directory.
+		assert!(secret.identity_paths("secret.example.com", None, None, None).is_empty());
+	}
+}
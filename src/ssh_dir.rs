@@ -0,0 +1,78 @@
+//! Discovery of the directory with SSH keys and configuration.
+
+use std::path::{Path, PathBuf};
+
+use crate::Paths;
+
+/// Find the SSH directory to use.
+///
+/// In order of precedence, this is:
+/// * the directory set explicitly on the authenticator,
+/// * the SSH home directory of the [`Paths`], normally the `SSH_HOME` environment variable,
+/// * `$HOME/.ssh` if it exists,
+/// * `$XDG_CONFIG_HOME/ssh` (or `$HOME/.config/ssh`) if it exists,
+/// * `$HOME/.ssh` otherwise.
+///
+/// The `home` function is only called if the home directory is needed.
+pub(crate) fn find_ssh_dir(explicit: Option<&Path>, paths: &dyn Paths, home: impl FnOnce() -> Option<PathBuf>) -> Option<PathBuf> {
+	let ssh_home = paths.ssh_home();
+	if let Some(dir) = explicit.or(ssh_home.as_deref()) {
+		return Some(dir.to_path_buf());
+	}
+	let config_home = paths.config_home();
+	discover(None, None, home().as_deref(), config_home.as_deref(), |path| path.is_dir())
+}
+
+/// Find the SSH directory from the given inputs.
+fn discover(
+	explicit: Option<&Path>,
+	ssh_home: Option<&Path>,
+	home: Option<&Path>,
+	config_home: Option<&Path>,
+	is_dir: impl Fn(&Path) -> bool,
+) -> Option<PathBuf> {
+	if let Some(dir) = explicit.or(ssh_home) {
+		return Some(dir.to_path_buf());
+	}
+
+	let dot_ssh = home.map(|home| home.join(".ssh"));
+	if let Some(dot_ssh) = &dot_ssh {
+		if is_dir(dot_ssh) {
+			return Some(dot_ssh.clone());
+		}
+	}
+
+	let config_ssh = match config_home {
+		Some(config_home) => Some(config_home.join("ssh")),
+		None => home.map(|home| home.join(".config/ssh")),
+	};
+	if let Some(config_ssh) = config_ssh {
+		if is_dir(&config_ssh) {
+			return Some(config_ssh);
+		}
+	}
+
+	dot_ssh
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_discover() {
+		let home = Some(Path::new("/home/user"));
+		let config_home = Some(Path::new("/home/user/.cfg"));
+		let none = |_: &Path| false;
+		let all = |_: &Path| true;
+		let only_config = |path: &Path| path == Path::new("/home/user/.cfg/ssh");
+
+		assert!(discover(Some(Path::new("/explicit")), Some(Path::new("/env")), home, None, all) == Some("/explicit".into()));
+		assert!(discover(None, Some(Path::new("/env")), home, None, all) == Some("/env".into()));
+		assert!(discover(None, None, home, config_home, all) == Some("/home/user/.ssh".into()));
+		assert!(discover(None, None, home, config_home, only_config) == Some("/home/user/.cfg/ssh".into()));
+		assert!(discover(None, None, home, None, none) == Some("/home/user/.ssh".into()));
+		assert!(let None = discover(None, None, None, None, none));
+	}
+}
@@ -0,0 +1,121 @@
+//! Support for reading credentials provisioned by systemd with `LoadCredential=` or `SetCredential=`.
+//!
+//! systemd makes the credentials of a service available as files in the directory named by `$CREDENTIALS_DIRECTORY`.
+//! Each file is named after the credential, and it contains the secret as-is.
+
+use std::path::Path;
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::label::LabelTarget;
+use crate::{PlaintextCredentials, PlannedAttempt};
+
+/// Get the password from a systemd credential, combined with a username.
+///
+/// Returns `None` if `$CREDENTIALS_DIRECTORY` is not set, if the credential does not exist or if there is no username.
+/// The `env` function gets the value of an environment variable.
+pub(crate) fn get_credentials(name: &str, username: Option<&str>, env: impl Fn(&str) -> Option<String>) -> Option<PlaintextCredentials> {
+	let directory = env("CREDENTIALS_DIRECTORY")
+		.filter(|x| !x.is_empty())
+		.or_else(|| {
+			debug!("Not reading systemd credential {name:?}: CREDENTIALS_DIRECTORY is not set");
+			None
+		})?;
+	let password = read_credential(Path::new(&directory), name)?;
+	let username = match username {
+		Some(x) => x,
+		None => {
+			debug!("Not using systemd credential {name:?}: the URL has no username and no username is configured for the domain");
+			return None;
+		},
+	};
+	Some(PlaintextCredentials {
+		username: username.to_owned(),
+		password,
+	})
+}
+
+/// Read a credential from the credentials directory of a service.
+///
+/// A single trailing newline is removed, since files are often written with one.
+fn read_credential(directory: &Path, name: &str) -> Option<String> {
+	if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+		warn!("Invalid systemd credential name: {name:?}");
+		return None;
+	}
+	let path = directory.join(name);
+	let data = match std::fs::read_to_string(&path) {
+		Ok(x) => x,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+			debug!("No systemd credential at {}", path.display());
+			return None;
+		},
+		Err(e) => {
+			warn!("Failed to read systemd credential {}: {e}", path.display());
+			return None;
+		},
+	};
+	let password = data.strip_suffix('\n').unwrap_or(&data);
+	let password = password.strip_suffix('\r').unwrap_or(password);
+	if password.is_empty() {
+		debug!("systemd credential {} is empty", path.display());
+		return None;
+	}
+	Some(password.to_owned())
+}
+
+/// A systemd credential for a domain as source of credentials.
+pub(crate) struct SystemdCredential<'a> {
+	/// The domain that the credential was added for.
+	pub domain: &'a str,
+
+	/// The name of the credential.
+	pub name: &'a str,
+}
+
+impl CredentialSource for SystemdCredential<'_> {
+	fn mechanism(&self) -> &'static str {
+		"systemd"
+	}
+
+	fn label(&self) -> String {
+		format!("systemd:{}", self.name)
+	}
+
+	fn label_target(&self) -> Option<LabelTarget> {
+		Some(LabelTarget::SystemdCredentials(self.domain.into()))
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::SystemdCredentials {
+			name: self.name.into(),
+		}
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		let authenticator = request.authenticator;
+		let username = request.username.or_else(|| authenticator.get_username(request.url, request.use_http_path));
+		Ok(get_credentials(self.name, username, |name| authenticator.env_var(name)))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_read_credential() {
+		let_assert!(Ok(temp_dir) = crate::temp_dir::TempDir::create("auth-git2-test-systemd-creds"));
+		let dir = temp_dir.path();
+		assert!(let Ok(()) = std::fs::write(dir.join("git-token"), "hunter2\n"));
+		assert!(let Ok(()) = std::fs::write(dir.join("empty"), "\n"));
+
+		assert!(let Some("hunter2") = read_credential(dir, "git-token").as_deref());
+		assert!(let None = read_credential(dir, "empty"));
+		assert!(let None = read_credential(dir, "missing"));
+		assert!(let None = read_credential(dir, "../git-token"));
+	}
+}
@@ -0,0 +1,195 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::ErrorCode;
+
+/// Receiver for aggregated, anonymous statistics of git operations.
+///
+/// The convenience functions of [`GitAuthenticator`][crate::GitAuthenticator] (like [`fetch()`][crate::GitAuthenticator::fetch()])
+/// report one [`OperationStats`] per operation when it finished, whether it succeeded or not.
+/// The statistics only contain counters and the names of authentication mechanisms:
+/// never URLs, host names, usernames, labels, paths or secrets.
+///
+/// This is separate from the [`AuditSink`][crate::AuditSink], which records individual prompts with the host they are for.
+/// Telemetry is disabled unless you set a sink with [`GitAuthenticator::set_telemetry_sink()`][crate::GitAuthenticator::set_telemetry_sink()].
+pub trait TelemetrySink: Send + Sync {
+	/// Record the statistics of a finished operation.
+	fn record(&self, stats: &OperationStats);
+}
+
+impl<F> TelemetrySink for F
+where
+	F: Fn(&OperationStats) + Send + Sync,
+{
+	fn record(&self, stats: &OperationStats) {
+		self(stats)
+	}
+}
+
+/// The kind of git operation that [`OperationStats`] are about.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Operation {
+	/// A clone of a repository.
+	Clone,
+
+	/// A fetch from a remote.
+	Fetch,
+
+	/// A push to a remote.
+	Push,
+}
+
+/// Aggregated statistics of a single git operation, reported to a [`TelemetrySink`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct OperationStats {
+	/// The kind of operation.
+	pub operation: Operation,
+
+	/// The transport of the URL, like `"https"` or `"ssh"`.
+	///
+	/// This is `"other"` for unknown or missing URL schemes, and `"local"` for local paths.
+	pub transport: &'static str,
+
+	/// If the operation succeeded.
+	pub succeeded: bool,
+
+	/// The code of the error if the operation failed.
+	pub error_code: Option<ErrorCode>,
+
+	/// The number of times the operation was run, including retries with other SSH usernames and retries after rate limiting.
+	pub runs: u32,
+
+	/// The number of times credentials were offered to the server, by authentication mechanism.
+	///
+	/// The mechanisms are named like the default labels of credentials, without the details: for example `"ssh-agent"`, `"ssh-key"` or `"credential-helper"`.
+	pub offered: BTreeMap<&'static str, u32>,
+
+	/// The mechanism of the last offered credentials, if the operation succeeded.
+	pub succeeded_with: Option<&'static str>,
+
+	/// The number of prompts shown to the user, for credentials and for passphrases of SSH keys.
+	pub prompts: u32,
+
+	/// The number of prompts that the user cancelled.
+	pub prompts_cancelled: u32,
+
+	/// The time the operation took, according to the [`Clock`][crate::Clock] of the authenticator.
+	pub duration: Duration,
+}
+
+/// Counters for the statistics of an operation, shared between the credentials callbacks of all runs of the operation.
+#[derive(Clone, Default)]
+pub(crate) struct Counters {
+	inner: Rc<RefCell<CountersInner>>,
+}
+
+#[derive(Default)]
+struct CountersInner {
+	offered: BTreeMap<&'static str, u32>,
+	current: Option<&'static str>,
+	last_offered: Option<&'static str>,
+	prompts: u32,
+	prompts_cancelled: u32,
+}
+
+impl Counters {
+	/// Record the mechanism that the credentials callback is trying.
+	pub(crate) fn attempt(&self, mechanism: &'static str) {
+		self.inner.borrow_mut().current = Some(mechanism);
+	}
+
+	/// Record that the credentials of the current mechanism were offered to the server.
+	pub(crate) fn offered(&self) {
+		let mut inner = self.inner.borrow_mut();
+		if let Some(mechanism) = inner.current {
+			*inner.offered.entry(mechanism).or_default() += 1;
+			inner.last_offered = Some(mechanism);
+		}
+	}
+
+	/// Record that a prompt was shown to the user.
+	pub(crate) fn prompt(&self, cancelled: bool) {
+		let mut inner = self.inner.borrow_mut();
+		inner.prompts += 1;
+		if cancelled {
+			inner.prompts_cancelled += 1;
+		}
+	}
+
+	/// Make the statistics of a finished operation.
+	pub(crate) fn to_stats(&self, operation: Operation, url: &str, runs: u32, result: Result<(), &crate::Error>, duration: Duration) -> OperationStats {
+		let inner = self.inner.borrow();
+		OperationStats {
+			operation,
+			transport: transport(url),
+			succeeded: result.is_ok(),
+			error_code: result.err().map(|e| e.code()),
+			runs,
+			offered: inner.offered.clone(),
+			succeeded_with: inner.last_offered.filter(|_| result.is_ok()),
+			prompts: inner.prompts,
+			prompts_cancelled: inner.prompts_cancelled,
+			duration,
+		}
+	}
+}
+
+/// Get the transport of a URL, without any identifying details.
+fn transport(url: &str) -> &'static str {
+	let Some((scheme, _)) = url.split_once("://") else {
+		return if crate::capabilities::is_ssh_url(url) {
+			"ssh"
+		} else if url.is_empty() {
+			"other"
+		} else {
+			"local"
+		};
+	};
+	match scheme.to_ascii_lowercase().as_str() {
+		"https" => "https",
+		"http" => "http",
+		"ssh" | "ssh+git" | "git+ssh" => "ssh",
+		"git" => "git",
+		"file" => "local",
+		_ => "other",
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_transport() {
+		assert!(transport("https://example.com/repo.git") == "https");
+		assert!(transport("ssh://git@example.com/repo.git") == "ssh");
+		assert!(transport("git@example.com:repo.git") == "ssh");
+		assert!(transport("/srv/git/repo.git") == "local");
+		assert!(transport("file:///srv/git/repo.git") == "local");
+		assert!(transport("foo://example.com/repo.git") == "other");
+	}
+
+	#[test]
+	fn test_counters() {
+		let counters = Counters::default();
+		counters.attempt("ssh-agent");
+		counters.offered();
+		counters.attempt("ssh-key");
+		counters.prompt(false);
+		counters.offered();
+		counters.attempt("ssh-key");
+		counters.prompt(true);
+
+		let stats = counters.to_stats(Operation::Fetch, "ssh://git@example.com/repo.git", 1, Ok(()), Duration::from_secs(1));
+		assert!(stats.succeeded);
+		assert!(stats.offered == BTreeMap::from([("ssh-agent", 1), ("ssh-key", 1)]));
+		assert!(stats.succeeded_with == Some("ssh-key"));
+		assert!(stats.prompts == 2);
+		assert!(stats.prompts_cancelled == 1);
+	}
+}
@@ -0,0 +1,73 @@
+//! Private temporary directories.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+/// A temporary directory that is only accessible by the current user, removed with its contents when dropped.
+pub(crate) struct TempDir {
+	path: PathBuf,
+}
+
+impl TempDir {
+	/// Create a new temporary directory with a random name that starts with the given prefix.
+	pub(crate) fn create(prefix: &str) -> std::io::Result<Self> {
+		let token = crate::token::random_token()?;
+		let path = std::env::temp_dir().join(format!("{prefix}-{}-{}", std::process::id(), &token[..16]));
+		create_private_dir(&path)?;
+		Ok(Self { path })
+	}
+
+	/// Get the path of the directory.
+	pub(crate) fn path(&self) -> &Path {
+		&self.path
+	}
+}
+
+impl Drop for TempDir {
+	fn drop(&mut self) {
+		if let Err(e) = std::fs::remove_dir_all(&self.path) {
+			warn!("Failed to remove temporary directory {}: {e}", self.path.display());
+		}
+	}
+}
+
+/// Create a directory that is only accessible by the current user.
+///
+/// This fails if the path already exists, so a directory or symlink placed by another user is never used.
+#[cfg(unix)]
+fn create_private_dir(path: &Path) -> std::io::Result<()> {
+	use std::os::unix::fs::DirBuilderExt;
+	std::fs::DirBuilder::new().mode(0o700).create(path)
+}
+
+/// Create a directory that is only accessible by the current user.
+///
+/// The temporary directory on Windows is private to the user, and new directories inherit its permissions.
+#[cfg(not(unix))]
+fn create_private_dir(path: &Path) -> std::io::Result<()> {
+	std::fs::DirBuilder::new().create(path)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_temp_dir() {
+		let_assert!(Ok(dir) = TempDir::create("auth-git2-test-temp-dir"));
+		let path = dir.path().to_owned();
+		assert!(path.is_dir());
+		assert!(let Ok(()) = std::fs::write(path.join("file"), "contents"));
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			let_assert!(Ok(metadata) = std::fs::metadata(&path));
+			assert!(metadata.permissions().mode() & 0o777 == 0o700);
+		}
+		drop(dir);
+		assert!(!path.exists());
+	}
+}
@@ -0,0 +1,123 @@
+//! Fallback for in-memory SSH keys when libgit2 does not support them.
+//!
+//! libgit2 only supports in-memory SSH keys if it was built with support for them.
+//! Otherwise, the key can be written to a temporary file that is only readable by the current user.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+/// Check if libgit2 supports in-memory SSH keys.
+///
+/// libgit2 does not parse the key when creating the credentials,
+/// so creating credentials from a dummy key only fails if the feature is missing.
+pub(crate) fn memory_keys_supported() -> bool {
+	static SUPPORTED: OnceLock<bool> = OnceLock::new();
+	*SUPPORTED.get_or_init(|| {
+		match git2::Cred::ssh_key_from_memory("git", None, "-", None) {
+			Ok(_) => true,
+			Err(e) => {
+				debug!("libgit2 does not support in-memory SSH keys: {e}");
+				false
+			},
+		}
+	})
+}
+
+/// A temporary file with a private key, deleted when dropped.
+pub(crate) struct TempKeyFile {
+	path: PathBuf,
+}
+
+impl TempKeyFile {
+	/// Write a private key to a new temporary file that is only accessible by the current user.
+	pub(crate) fn create(private_key: &str) -> std::io::Result<Self> {
+		use std::io::Write;
+		use std::sync::atomic::{AtomicU32, Ordering};
+
+		static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+		let dir = std::env::temp_dir();
+		let nanos = std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.map(|x| x.subsec_nanos())
+			.unwrap_or(0);
+		let mut attempt = 0;
+		loop {
+			let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+			let path = dir.join(format!("auth-git2-key-{}-{nanos:x}-{counter}", std::process::id()));
+			// Never overwrite existing files, and refuse to follow symlinks placed by other users.
+			let file = open_private(&path);
+			let mut file = match file {
+				Ok(x) => x,
+				Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < 10 => {
+					attempt += 1;
+					continue;
+				},
+				Err(e) => return Err(e),
+			};
+			let temp_file = Self { path };
+			file.write_all(private_key.as_bytes())?;
+			file.sync_all()?;
+			return Ok(temp_file);
+		}
+	}
+
+	/// Get the path of the temporary file.
+	pub(crate) fn path(&self) -> &Path {
+		&self.path
+	}
+}
+
+impl Drop for TempKeyFile {
+	fn drop(&mut self) {
+		if let Err(e) = std::fs::remove_file(&self.path) {
+			warn!("Failed to remove temporary SSH key file {}: {e}", self.path.display());
+		}
+	}
+}
+
+/// Create a new file that is only readable and writable by the current user.
+#[cfg(unix)]
+fn open_private(path: &Path) -> std::io::Result<std::fs::File> {
+	use std::os::unix::fs::OpenOptionsExt;
+	std::fs::OpenOptions::new()
+		.write(true)
+		.create_new(true)
+		.mode(0o600)
+		.open(path)
+}
+
+/// Create a new file that is only readable and writable by the current user.
+///
+/// On Windows, the temporary directory is private to the user by default.
+#[cfg(not(unix))]
+fn open_private(path: &Path) -> std::io::Result<std::fs::File> {
+	std::fs::OpenOptions::new()
+		.write(true)
+		.create_new(true)
+		.open(path)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_temp_key_file() {
+		let_assert!(Ok(file) = TempKeyFile::create("not a real key"));
+		let path = file.path().to_path_buf();
+		assert!(let Ok("not a real key") = std::fs::read_to_string(&path).as_deref());
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			let_assert!(Ok(metadata) = std::fs::metadata(&path));
+			assert!(metadata.permissions().mode() & 0o777 == 0o600);
+		}
+		drop(file);
+		assert!(!path.exists());
+	}
+}
@@ -0,0 +1,75 @@
+//! Capture of structured events from the credentials callback, for the tests of this crate.
+//!
+//! The callback records an event for every invocation and for every attempt it makes, in order.
+//! Tests use [`capture()`] to collect the events of a closure and assert the sequencing of the authentication mechanisms,
+//! without network access and without parsing log messages.
+//! The events are kept per thread, so tests running in parallel do not see each other's events.
+
+use std::cell::RefCell;
+
+/// An event recorded by the credentials callback.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum Event {
+	/// The callback was invoked by libgit2.
+	Invocation {
+		/// The credential types that libgit2 allows.
+		allowed: git2::CredentialType,
+	},
+
+	/// The callback picked the next attempt.
+	///
+	/// The attempt may still be skipped, for example when a key file does not exist.
+	Attempt {
+		/// The name of the mechanism, as reported to telemetry.
+		mechanism: &'static str,
+
+		/// The label of the attempt.
+		label: String,
+	},
+}
+
+thread_local! {
+	/// The events captured on this thread, if a capture is active.
+	static EVENTS: RefCell<Option<Vec<Event>>> = const { RefCell::new(None) };
+}
+
+/// Record an event, if a capture is active on this thread.
+pub(crate) fn record(event: Event) {
+	EVENTS.with(|events| {
+		if let Some(events) = events.borrow_mut().as_mut() {
+			events.push(event);
+		}
+	});
+}
+
+/// Run a function and collect the events recorded on this thread while it runs.
+pub(crate) fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<Event>) {
+	let outer = EVENTS.with(|events| events.replace(Some(Vec::new())));
+	let result = f();
+	let captured = EVENTS.with(|events| events.replace(outer)).unwrap_or_default();
+	(result, captured)
+}
+
+/// Get the mechanisms of all attempts in a list of events, in order.
+pub(crate) fn mechanisms(events: &[Event]) -> Vec<&'static str> {
+	events.iter()
+		.filter_map(|event| match event {
+			Event::Attempt { mechanism, .. } => Some(*mechanism),
+			Event::Invocation { .. } => None,
+		})
+		.collect()
+}
+
+/// Get the mechanisms of the attempts in a list of events, split per invocation of the callback.
+pub(crate) fn mechanisms_per_invocation(events: &[Event]) -> Vec<Vec<&'static str>> {
+	let mut invocations: Vec<Vec<&'static str>> = Vec::new();
+	for event in events {
+		match event {
+			Event::Invocation { .. } => invocations.push(Vec::new()),
+			Event::Attempt { mechanism, .. } => if let Some(last) = invocations.last_mut() {
+				last.push(mechanism);
+			},
+		}
+	}
+	invocations
+}
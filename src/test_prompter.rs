@@ -0,0 +1,27 @@
+//! Prompters with fixed answers, for the tests of this crate.
+
+use std::path::Path;
+
+use crate::Prompter;
+
+/// Prompter that always enters the same answers.
+///
+/// The username is always `alice` and the password from a username and password prompt is always `hunter2`.
+/// The password for a known username is `password-of-{username}`, so tests can see which username was used.
+/// SSH key passphrase prompts are always cancelled.
+#[derive(Clone)]
+pub(crate) struct FixedPrompter;
+
+impl Prompter for FixedPrompter {
+	fn prompt_username_password(&mut self, _url: &str, _git_config: &git2::Config) -> Option<(String, String)> {
+		Some(("alice".into(), "hunter2".into()))
+	}
+
+	fn prompt_password(&mut self, username: &str, _url: &str, _git_config: &git2::Config) -> Option<String> {
+		Some(format!("password-of-{username}"))
+	}
+
+	fn prompt_ssh_key_passphrase(&mut self, _private_key_path: &Path, _git_config: &git2::Config) -> Option<String> {
+		None
+	}
+}
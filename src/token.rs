@@ -0,0 +1,50 @@
+//! Secret tokens that authenticate local clients of the askpass shim and the prompt broker.
+
+/// Generate a random token from the random source of the operating system.
+///
+/// The token is 32 random bytes, encoded as lowercase hexadecimal digits.
+pub(crate) fn random_token() -> std::io::Result<String> {
+	let mut bytes = [0u8; 32];
+	getrandom::getrandom(&mut bytes)?;
+	Ok(bytes.iter().map(|x| format!("{x:02x}")).collect())
+}
+
+/// Check if a token sent by a client matches the expected token.
+///
+/// The comparison takes the same time wherever the first difference is,
+/// so a client can not guess the token one character at a time.
+pub(crate) fn token_matches(expected: &str, actual: &str) -> bool {
+	let expected = expected.as_bytes();
+	let actual = actual.as_bytes();
+	if expected.len() != actual.len() {
+		return false;
+	}
+	let difference = expected.iter()
+		.zip(actual)
+		.fold(0, |difference, (a, b)| difference | (a ^ b));
+	// Prevent the compiler from turning the loop into an early-exit comparison.
+	std::hint::black_box(difference) == 0
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_random_token() {
+		let_assert!(Ok(a) = random_token());
+		let_assert!(Ok(b) = random_token());
+		assert!(a.len() == 64);
+		assert!(a.bytes().all(|x| x.is_ascii_hexdigit()));
+		assert!(a != b);
+	}
+
+	#[test]
+	fn test_token_matches() {
+		assert!(token_matches("0123abcd", "0123abcd"));
+		assert!(!token_matches("0123abcd", "0123abce"));
+		assert!(!token_matches("0123abcd", "0123abc"));
+		assert!(!token_matches("0123abcd", ""));
+	}
+}
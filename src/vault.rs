@@ -0,0 +1,213 @@
+//! Credentials from a key/value secrets engine of HashiCorp Vault.
+//!
+//! The address and token of the Vault server are taken from the standard `VAULT_ADDR` and `VAULT_TOKEN` environment variables,
+//! so nothing has to be cached on disk.
+
+#[cfg(feature = "log")]
+use crate::log::*;
+
+use crate::credential_source::{CredentialSource, SourceRequest};
+use crate::{PlaintextCredentials, PlannedAttempt};
+
+/// A secret in a key/value secrets engine of HashiCorp Vault.
+///
+/// Use [`GitAuthenticator::add_vault_secret()`][crate::GitAuthenticator::add_vault_secret()] to use the secret for a domain.
+///
+/// This is only available with the `vault` feature.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VaultSecret {
+	/// The mount point of the secrets engine.
+	mount: String,
+
+	/// The path of the secret in the secrets engine.
+	path: String,
+
+	/// Is the secrets engine a version 2 key/value engine?
+	kv_v2: bool,
+
+	/// The field with the username.
+	username_field: String,
+
+	/// The field with the password or token.
+	password_field: String,
+
+	/// The username to use if the secret has no username field.
+	default_username: Option<String>,
+}
+
+impl VaultSecret {
+	/// Create a reference to a secret in a version 2 key/value secrets engine.
+	///
+	/// The username is read from the `username` field and the password from the `password` field.
+	/// If there is no `password` field, the `token` field is used instead.
+	pub fn kv2(mount: impl Into<String>, path: impl Into<String>) -> Self {
+		Self {
+			mount: mount.into(),
+			path: path.into(),
+			kv_v2: true,
+			username_field: "username".into(),
+			password_field: "password".into(),
+			default_username: None,
+		}
+	}
+
+	/// Create a reference to a secret in a version 1 key/value secrets engine.
+	///
+	/// The username is read from the `username` field and the password from the `password` field.
+	/// If there is no `password` field, the `token` field is used instead.
+	pub fn kv1(mount: impl Into<String>, path: impl Into<String>) -> Self {
+		Self {
+			kv_v2: false,
+			..Self::kv2(mount, path)
+		}
+	}
+
+	/// Set the name of the field with the username.
+	pub fn username_field(mut self, field: impl Into<String>) -> Self {
+		self.username_field = field.into();
+		self
+	}
+
+	/// Set the name of the field with the password or token.
+	pub fn password_field(mut self, field: impl Into<String>) -> Self {
+		self.password_field = field.into();
+		self
+	}
+
+	/// Set the username to use if the secret does not contain a username.
+	///
+	/// This is useful for secrets that only contain a token.
+	pub fn default_username(mut self, username: impl Into<String>) -> Self {
+		self.default_username = Some(username.into());
+		self
+	}
+
+	/// Get the path of the secret, including the mount point.
+	pub fn path(&self) -> String {
+		format!("{}/{}", self.mount.trim_matches('/'), self.path.trim_matches('/'))
+	}
+
+	/// Get the URL of the secret in the HTTP API of a Vault server.
+	fn api_url(&self, address: &str) -> String {
+		let address = address.trim_end_matches('/');
+		let mount = self.mount.trim_matches('/');
+		let path = self.path.trim_matches('/');
+		if self.kv_v2 {
+			format!("{address}/v1/{mount}/data/{path}")
+		} else {
+			format!("{address}/v1/{mount}/{path}")
+		}
+	}
+}
+
+/// Get credentials from a Vault secret.
+///
+/// Returns `None` if `VAULT_ADDR` or `VAULT_TOKEN` is not set, if the secret could not be read,
+/// or if the secret has a different username than the URL.
+/// The `env` function gets the value of an environment variable.
+pub(crate) fn get_credentials(secret: &VaultSecret, username: Option<&str>, env: impl Fn(&str) -> Option<String>) -> Option<PlaintextCredentials> {
+	let address = env("VAULT_ADDR")
+		.or_else(|| {
+			debug!("Not reading Vault secret {:?}: VAULT_ADDR is not set", secret.path());
+			None
+		})?;
+	let token = env("VAULT_TOKEN")
+		.or_else(|| {
+			debug!("Not reading Vault secret {:?}: VAULT_TOKEN is not set", secret.path());
+			None
+		})?;
+
+	let mut request = ureq::get(&secret.api_url(&address))
+		.set("X-Vault-Token", &token)
+		.set("X-Vault-Request", "true");
+	if let Some(namespace) = env("VAULT_NAMESPACE") {
+		request = request.set("X-Vault-Namespace", &namespace);
+	}
+	let body = request.call()
+		.map_err(|e| warn!("Failed to read Vault secret {:?}: {e}", secret.path()))
+		.ok()?
+		.into_string()
+		.map_err(|e| warn!("Failed to read Vault secret {:?}: {e}", secret.path()))
+		.ok()?;
+	let credentials = parse_secret(secret, &body)
+		.map_err(|e| warn!("Invalid Vault secret {:?}: {e}", secret.path()))
+		.ok()?;
+
+	match username {
+		Some(username) if username != credentials.username => {
+			debug!("Vault secret {:?} is for username {:?}, not {username:?}", secret.path(), credentials.username);
+			None
+		},
+		_ => Some(credentials),
+	}
+}
+
+/// Parse the response of the Vault API for a secret.
+pub(crate) fn parse_secret(secret: &VaultSecret, body: &str) -> Result<PlaintextCredentials, String> {
+	let value: serde_json::Value = serde_json::from_str(body).map_err(|e| format!("invalid JSON response: {e}"))?;
+	let mut data = value.get("data").ok_or("missing data in response")?;
+	if secret.kv_v2 {
+		data = data.get("data").ok_or("missing data in response")?;
+	}
+	let field = |name: &str| data.get(name).and_then(|x| x.as_str()).map(String::from);
+	let username = field(&secret.username_field)
+		.or_else(|| secret.default_username.clone())
+		.ok_or_else(|| format!("missing field {:?}", secret.username_field))?;
+	let password = field(&secret.password_field)
+		.or_else(|| field("token"))
+		.ok_or_else(|| format!("missing field {:?}", secret.password_field))?;
+	Ok(PlaintextCredentials { username, password })
+}
+
+impl CredentialSource for VaultSecret {
+	fn mechanism(&self) -> &'static str {
+		"vault"
+	}
+
+	fn label(&self) -> String {
+		format!("vault:{}", self.path())
+	}
+
+	fn to_planned(&self) -> PlannedAttempt {
+		PlannedAttempt::Vault {
+			path: self.path(),
+		}
+	}
+
+	fn get_credentials(&self, request: SourceRequest<'_>) -> Result<Option<PlaintextCredentials>, String> {
+		let authenticator = request.authenticator;
+		Ok(get_credentials(self, request.username, |name| authenticator.env_var(name)))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_api_url() {
+		let secret = VaultSecret::kv2("secret/", "/git/github.com");
+		assert!(secret.path() == "secret/git/github.com");
+		assert!(secret.api_url("https://vault.example.com:8200/") == "https://vault.example.com:8200/v1/secret/data/git/github.com");
+		let secret = VaultSecret::kv1("kv", "git/github.com");
+		assert!(secret.api_url("https://vault.example.com:8200") == "https://vault.example.com:8200/v1/kv/git/github.com");
+	}
+
+	#[test]
+	fn test_parse_secret() {
+		let secret = VaultSecret::kv2("secret", "git");
+		let_assert!(Ok(credentials) = parse_secret(&secret, r#"{"data": {"data": {"username": "bot", "password": "hunter2"}, "metadata": {}}}"#));
+		assert!(credentials.username == "bot");
+		assert!(credentials.password == "hunter2");
+
+		let secret = VaultSecret::kv1("kv", "git").default_username("x-access-token");
+		let_assert!(Ok(credentials) = parse_secret(&secret, r#"{"data": {"token": "ghp_123"}}"#));
+		assert!(credentials.username == "x-access-token");
+		assert!(credentials.password == "ghp_123");
+
+		let secret = VaultSecret::kv2("secret", "git");
+		assert!(let Err(_) = parse_secret(&secret, r#"{"data": {"data": {"password": "hunter2"}}}"#));
+		assert!(let Err(_) = parse_secret(&secret, r#"{"errors": ["permission denied"]}"#));
+	}
+}
@@ -0,0 +1,47 @@
+//! Problems with the environment that make some authentication mechanisms unavailable.
+
+/// A problem with the environment that silently disables some authentication mechanisms.
+///
+/// You can get the current warnings with [`GitAuthenticator::warnings()`][crate::GitAuthenticator::warnings()].
+/// They are also logged when an affected mechanism is skipped, and included in the [support bundle][crate::GitAuthenticator::support_bundle()].
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Warning {
+	/// The home directory of the current user could not be determined.
+	///
+	/// This is common in containers where `HOME` is not set.
+	/// Without a home directory, the default SSH keys, the `.netrc` file and the files of the `store` credential helper can not be found.
+	///
+	/// Set the `HOME` environment variable, or set the home directory with [`GitAuthenticator::home_dir()`][crate::GitAuthenticator::home_dir()].
+	HomeDirUnavailable,
+
+	/// A configured credential helper failed to run.
+	///
+	/// This is only reported by [`GitAuthenticator::check_credential_helpers()`][crate::GitAuthenticator::check_credential_helpers()].
+	/// Without a working helper, stored credentials are not found and the user may be prompted instead.
+	CredentialHelperFailed {
+		/// The shell command of the helper, without the action.
+		command: String,
+
+		/// The error that occurred, including the error output of the helper.
+		error: String,
+	},
+
+	/// Prompts are enabled, but there is no prompter to show them.
+	///
+	/// Without the `prompts` feature, there is no default prompter,
+	/// so [`GitAuthenticator::try_password_prompt()`][crate::GitAuthenticator::try_password_prompt()]
+	/// and [`GitAuthenticator::prompt_ssh_key_password()`][crate::GitAuthenticator::prompt_ssh_key_password()] have no effect.
+	/// Set a prompter with [`GitAuthenticator::set_prompter()`][crate::GitAuthenticator::set_prompter()], or enable the `prompts` feature.
+	NoPrompter,
+}
+
+impl std::fmt::Display for Warning {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::HomeDirUnavailable => write!(f, "could not determine the home directory: set the HOME environment variable or use GitAuthenticator::home_dir()"),
+			Self::CredentialHelperFailed { command, error } => write!(f, "credential helper {command:?} failed: {error}"),
+			Self::NoPrompter => write!(f, "prompts are enabled, but there is no prompter: use GitAuthenticator::set_prompter() or enable the `prompts` feature"),
+		}
+	}
+}
@@ -0,0 +1,193 @@
+//! Prompting on the Windows console with the wide-character console API.
+//!
+//! Reading from the console with the byte-oriented API converts the input to the code page of the console.
+//! Characters that do not exist in that code page are mangled, so passwords with non-ASCII characters would not work.
+//! The wide-character API gives the input as UTF-16, independent of the code page.
+
+use std::ffi::c_void;
+use zeroize::Zeroize;
+
+type Handle = *mut c_void;
+
+const INVALID_HANDLE_VALUE: Handle = -1isize as Handle;
+const GENERIC_READ: u32 = 0x8000_0000;
+const GENERIC_WRITE: u32 = 0x4000_0000;
+const FILE_SHARE_READ: u32 = 0x0000_0001;
+const FILE_SHARE_WRITE: u32 = 0x0000_0002;
+const OPEN_EXISTING: u32 = 3;
+const ENABLE_ECHO_INPUT: u32 = 0x0004;
+
+/// The maximum length of a line of input in UTF-16 code units.
+///
+/// The line buffer is allocated with this capacity up front,
+/// so it is never reallocated and no copies of a secret are left behind in freed memory.
+const MAX_LINE_LEN: usize = 4096;
+
+#[link(name = "kernel32")]
+extern "system" {
+	fn CreateFileW(name: *const u16, access: u32, share_mode: u32, security: *mut c_void, disposition: u32, flags: u32, template: Handle) -> Handle;
+	fn CloseHandle(handle: Handle) -> i32;
+	fn GetConsoleMode(handle: Handle, mode: *mut u32) -> i32;
+	fn SetConsoleMode(handle: Handle, mode: u32) -> i32;
+	fn ReadConsoleW(handle: Handle, buffer: *mut c_void, chars_to_read: u32, chars_read: *mut u32, input_control: *mut c_void) -> i32;
+	fn WriteConsoleW(handle: Handle, buffer: *const c_void, chars_to_write: u32, chars_written: *mut u32, reserved: *mut c_void) -> i32;
+}
+
+/// A handle that is closed when it is dropped.
+struct OwnedHandle(Handle);
+
+impl OwnedHandle {
+	/// Open a console device, like `CONIN$` or `CONOUT$`.
+	fn open(name: &str) -> std::io::Result<Self> {
+		let name: Vec<u16> = name.encode_utf16().chain([0]).collect();
+		// SAFETY: The name is a null terminated UTF-16 string, and the other pointers may be null.
+		let handle = unsafe {
+			CreateFileW(
+				name.as_ptr(),
+				GENERIC_READ | GENERIC_WRITE,
+				FILE_SHARE_READ | FILE_SHARE_WRITE,
+				std::ptr::null_mut(),
+				OPEN_EXISTING,
+				0,
+				std::ptr::null_mut(),
+			)
+		};
+		if handle == INVALID_HANDLE_VALUE || handle.is_null() {
+			return Err(std::io::Error::last_os_error());
+		}
+		Ok(Self(handle))
+	}
+}
+
+impl Drop for OwnedHandle {
+	fn drop(&mut self) {
+		// SAFETY: The handle was opened by us and it is not used after this.
+		unsafe {
+			CloseHandle(self.0);
+		}
+	}
+}
+
+/// The console of the process, used with the wide-character API.
+///
+/// This has the same interface as `terminal_prompt::Terminal`, so the default prompter can use either.
+pub(crate) struct Console {
+	input: OwnedHandle,
+	output: OwnedHandle,
+}
+
+impl Console {
+	/// Open the console of the process, even if the standard streams are redirected.
+	pub(crate) fn open() -> std::io::Result<Self> {
+		Ok(Self {
+			input: OwnedHandle::open("CONIN$")?,
+			output: OwnedHandle::open("CONOUT$")?,
+		})
+	}
+
+	/// Prompt the user for a line of text, showing the input.
+	pub(crate) fn prompt(&mut self, prompt: &str) -> std::io::Result<String> {
+		self.write_wide(prompt)?;
+		self.read_line()
+	}
+
+	/// Prompt the user for a line of text, without showing the input.
+	pub(crate) fn prompt_sensitive(&mut self, prompt: &str) -> std::io::Result<String> {
+		self.write_wide(prompt)?;
+		let mut mode = 0;
+		// SAFETY: The handle is valid and `mode` is a valid pointer.
+		if unsafe { GetConsoleMode(self.input.0, &mut mode) } == 0 {
+			return Err(std::io::Error::last_os_error());
+		}
+		// SAFETY: The handle is valid.
+		if unsafe { SetConsoleMode(self.input.0, mode & !ENABLE_ECHO_INPUT) } == 0 {
+			return Err(std::io::Error::last_os_error());
+		}
+		let line = self.read_line();
+		// SAFETY: The handle is valid.
+		unsafe {
+			SetConsoleMode(self.input.0, mode);
+		}
+		// The newline of the user was not echoed either.
+		self.write_wide("\r\n")?;
+		line
+	}
+
+	/// Read a line of input, without the line ending.
+	///
+	/// Lines longer than [`MAX_LINE_LEN`] are rejected.
+	fn read_line(&mut self) -> std::io::Result<String> {
+		let mut line: Vec<u16> = Vec::with_capacity(MAX_LINE_LEN);
+		let mut buffer = [0u16; 256];
+		let result = loop {
+			let mut read = 0;
+			// SAFETY: The buffer is valid for `buffer.len()` characters, and `read` is a valid pointer.
+			let ok = unsafe {
+				ReadConsoleW(self.input.0, buffer.as_mut_ptr().cast(), buffer.len() as u32, &mut read, std::ptr::null_mut())
+			};
+			if ok == 0 {
+				break Err(std::io::Error::last_os_error());
+			}
+			let read = &buffer[..(read as usize).min(buffer.len())];
+			if line.len() + read.len() > line.capacity() {
+				break Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "input line is too long"));
+			}
+			line.extend_from_slice(read);
+			if read.is_empty() || line.last() == Some(&u16::from(b'\n')) {
+				break Ok(());
+			}
+		};
+		buffer.zeroize();
+		let text = result.and_then(|()| {
+			let end = line.iter().rposition(|&c| c != u16::from(b'\n') && c != u16::from(b'\r')).map_or(0, |x| x + 1);
+			// A UTF-16 code unit takes at most three bytes in UTF-8, so the text is never reallocated either.
+			let mut text = String::with_capacity(end * 3);
+			for c in char::decode_utf16(line[..end].iter().copied()) {
+				match c {
+					Ok(c) => text.push(c),
+					Err(e) => {
+						text.zeroize();
+						return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+					},
+				}
+			}
+			Ok(text)
+		});
+		line.zeroize();
+		text
+	}
+
+	/// Write text to the console.
+	///
+	/// It is an error if the console accepts no characters, so this never loops forever.
+	fn write_wide(&mut self, text: &str) -> std::io::Result<()> {
+		let wide: Vec<u16> = text.encode_utf16().collect();
+		let mut remaining = &wide[..];
+		while !remaining.is_empty() {
+			let mut written = 0;
+			// SAFETY: The buffer is valid for `remaining.len()` characters, and `written` is a valid pointer.
+			let ok = unsafe {
+				WriteConsoleW(self.output.0, remaining.as_ptr().cast(), remaining.len() as u32, &mut written, std::ptr::null_mut())
+			};
+			if ok == 0 {
+				return Err(std::io::Error::last_os_error());
+			}
+			if written == 0 {
+				return Err(std::io::Error::new(std::io::ErrorKind::WriteZero, "console did not accept any characters"));
+			}
+			remaining = &remaining[(written as usize).min(remaining.len())..];
+		}
+		Ok(())
+	}
+}
+
+impl std::io::Write for Console {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.write_wide(&String::from_utf8_lossy(buf))?;
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}